@@ -1,3 +1,4 @@
 pub mod client;
 pub mod entry;
+pub mod memento;
 pub mod mime_type;
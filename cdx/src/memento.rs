@@ -0,0 +1,301 @@
+//! A Memento (RFC 7089) client for archives other than the Wayback Machine.
+//!
+//! TimeMaps in link format (RFC 6690) are parsed into a list of captures
+//! tagged with the archive that was queried, and TimeGates are used to find
+//! the capture closest to a given time.
+
+use aib_core::{archive::Archive, timestamp::Timestamp};
+use chrono::{DateTime, Utc};
+use reqwest::{header, redirect, Client, StatusCode};
+use std::time::Duration;
+
+const DEFAULT_REQUEST_TIMEOUT_DURATION: Duration = Duration::from_secs(60);
+const HTTP_DATE_FMT: &str = "%a, %d %b %Y %H:%M:%S GMT";
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("HTTP client error: {0}")]
+    HttpClientError(#[from] reqwest::Error),
+    #[error("Unexpected status code: {0:?}")]
+    UnexpectedStatus(StatusCode),
+    #[error("Invalid link format: {0}")]
+    InvalidLinkFormat(String),
+    #[error("Invalid datetime: {0}")]
+    InvalidDatetime(String),
+    #[error("Missing memento location")]
+    MissingLocation,
+    #[error("Missing memento datetime: {0}")]
+    MissingDatetime(String),
+}
+
+/// A link in a link-format document.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Link {
+    pub uri: String,
+    pub params: Vec<(String, String)>,
+}
+
+impl Link {
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Whether the link's `rel` parameter includes the given relation type.
+    pub fn has_rel(&self, rel: &str) -> bool {
+        self.param("rel")
+            .map(|value| value.split_whitespace().any(|value| value == rel))
+            .unwrap_or(false)
+    }
+}
+
+/// A capture in a Memento-compliant archive.
+#[derive(Clone, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub struct Memento {
+    pub archive: Archive,
+    /// The original URL.
+    pub url: String,
+    pub timestamp: Timestamp,
+    /// The URL of the capture in the archive (the URI-M).
+    pub memento_url: String,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TimeMap {
+    pub original: Option<String>,
+    pub timegate: Option<String>,
+    pub mementos: Vec<Memento>,
+}
+
+impl TimeMap {
+    /// Parse a link-format TimeMap, using the given URL if it does not specify the original.
+    pub fn parse(archive: Archive, url: &str, input: &str) -> Result<Self, Error> {
+        let links = parse_links(input)?;
+
+        let original = links
+            .iter()
+            .find(|link| link.has_rel("original"))
+            .map(|link| link.uri.clone());
+        let timegate = links
+            .iter()
+            .find(|link| link.has_rel("timegate"))
+            .map(|link| link.uri.clone());
+
+        let mementos = links
+            .iter()
+            .filter(|link| link.has_rel("memento"))
+            .map(|link| {
+                let datetime = link
+                    .param("datetime")
+                    .ok_or_else(|| Error::MissingDatetime(link.uri.clone()))?;
+
+                Ok(Memento {
+                    archive,
+                    url: original.as_deref().unwrap_or(url).to_string(),
+                    timestamp: parse_http_date(datetime)?,
+                    memento_url: link.uri.clone(),
+                })
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        Ok(Self {
+            original,
+            timegate,
+            mementos,
+        })
+    }
+}
+
+/// Parse a link-format (RFC 6690) document.
+pub fn parse_links(input: &str) -> Result<Vec<Link>, Error> {
+    let invalid = || Error::InvalidLinkFormat(input.to_string());
+    let mut links = vec![];
+    let mut rest = input.trim_start_matches(|c: char| c.is_whitespace() || c == ',');
+
+    while !rest.is_empty() {
+        let (uri, after) = rest
+            .strip_prefix('<')
+            .and_then(|value| value.split_once('>'))
+            .ok_or_else(invalid)?;
+        let mut params = vec![];
+        rest = after.trim_start();
+
+        while let Some(after) = rest.strip_prefix(';') {
+            let after = after.trim_start();
+            let name_end = after.find(['=', ';', ',']).unwrap_or(after.len());
+            let name = after[..name_end].trim().to_string();
+            rest = &after[name_end..];
+
+            let value = match rest.strip_prefix('=') {
+                Some(after) => {
+                    let after = after.trim_start();
+
+                    match after.strip_prefix('"') {
+                        Some(quoted) => {
+                            let (value, after) = quoted.split_once('"').ok_or_else(invalid)?;
+                            rest = after;
+                            value.to_string()
+                        }
+                        None => {
+                            let value_end = after.find([';', ',']).unwrap_or(after.len());
+                            rest = &after[value_end..];
+                            after[..value_end].trim().to_string()
+                        }
+                    }
+                }
+                None => String::new(),
+            };
+
+            params.push((name, value));
+            rest = rest.trim_start();
+        }
+
+        links.push(Link {
+            uri: uri.trim().to_string(),
+            params,
+        });
+
+        rest = match rest.strip_prefix(',') {
+            Some(after) => after.trim_start_matches(|c: char| c.is_whitespace() || c == ','),
+            None if rest.trim().is_empty() => "",
+            None => return Err(invalid()),
+        };
+    }
+
+    Ok(links)
+}
+
+fn parse_http_date(value: &str) -> Result<Timestamp, Error> {
+    DateTime::parse_from_rfc2822(value)
+        .map(|datetime| Timestamp(datetime.with_timezone(&Utc)))
+        .map_err(|_| Error::InvalidDatetime(value.to_string()))
+}
+
+/// Find a Wayback-style timestamp path segment in a memento URL.
+fn url_timestamp(memento_url: &str) -> Option<Timestamp> {
+    memento_url
+        .split('/')
+        .filter(|segment| {
+            segment.len() >= 14 && segment.bytes().take(14).all(|byte| byte.is_ascii_digit())
+        })
+        .find_map(|segment| segment[..14].parse().ok())
+}
+
+pub struct MementoClient {
+    underlying: Client,
+    archive: Archive,
+    timemap_base: String,
+    timegate_base: String,
+}
+
+impl MementoClient {
+    pub fn new(archive: Archive) -> Result<Self, Error> {
+        Ok(Self {
+            underlying: Client::builder()
+                .timeout(DEFAULT_REQUEST_TIMEOUT_DURATION)
+                .redirect(redirect::Policy::none())
+                .build()?,
+            archive,
+            timemap_base: archive.timemap_base().to_string(),
+            timegate_base: archive.timegate_base().to_string(),
+        })
+    }
+
+    /// Request TimeMaps at `<base><url>`.
+    pub fn with_timemap_base(self, timemap_base: &str) -> Self {
+        Self {
+            timemap_base: timemap_base.to_string(),
+            ..self
+        }
+    }
+
+    /// Make TimeGate requests at `<base><url>`.
+    pub fn with_timegate_base(self, timegate_base: &str) -> Self {
+        Self {
+            timegate_base: timegate_base.to_string(),
+            ..self
+        }
+    }
+
+    pub fn archive(&self) -> Archive {
+        self.archive
+    }
+
+    /// All captures of a URL, in the order listed by the archive.
+    ///
+    /// An archive with no captures of the URL may respond with a 404, which
+    /// is treated as an empty TimeMap.
+    pub async fn timemap(&self, url: &str) -> Result<TimeMap, Error> {
+        let response = self
+            .underlying
+            .get(format!("{}{}", self.timemap_base, url))
+            .send()
+            .await?;
+
+        match response.status() {
+            StatusCode::OK => TimeMap::parse(self.archive, url, &response.text().await?),
+            StatusCode::NOT_FOUND => Ok(TimeMap {
+                original: None,
+                timegate: None,
+                mementos: vec![],
+            }),
+            other => Err(Error::UnexpectedStatus(other)),
+        }
+    }
+
+    /// The capture of a URL closest to the given time, if there is one.
+    pub async fn timegate(
+        &self,
+        url: &str,
+        timestamp: Timestamp,
+    ) -> Result<Option<Memento>, Error> {
+        let request_url = format!("{}{}", self.timegate_base, url);
+        let response = self
+            .underlying
+            .get(&request_url)
+            .header(
+                "Accept-Datetime",
+                timestamp.0.format(HTTP_DATE_FMT).to_string(),
+            )
+            .send()
+            .await?;
+
+        let status = response.status();
+
+        let location = match status {
+            StatusCode::OK => response.headers().get(header::CONTENT_LOCATION),
+            StatusCode::NOT_FOUND => return Ok(None),
+            status if status.is_redirection() => response.headers().get(header::LOCATION),
+            other => return Err(Error::UnexpectedStatus(other)),
+        };
+
+        let memento_url = match location.and_then(|value| value.to_str().ok()) {
+            Some(location) => response
+                .url()
+                .join(location)
+                .map_or_else(|_| location.to_string(), |url| url.to_string()),
+            // The TimeGate may serve the memento itself.
+            None if status == StatusCode::OK => request_url,
+            None => return Err(Error::MissingLocation),
+        };
+
+        let timestamp = match response.headers().get("Memento-Datetime") {
+            Some(value) => parse_http_date(
+                value
+                    .to_str()
+                    .map_err(|_| Error::InvalidDatetime(format!("{:?}", value)))?,
+            )?,
+            None => url_timestamp(&memento_url)
+                .ok_or_else(|| Error::MissingDatetime(memento_url.clone()))?,
+        };
+
+        Ok(Some(Memento {
+            archive: self.archive,
+            url: url.to_string(),
+            timestamp,
+            memento_url,
+        }))
+    }
+}
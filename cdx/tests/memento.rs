@@ -0,0 +1,62 @@
+use aib_cdx::memento::{parse_links, Link, Memento, TimeMap};
+use aib_core::archive::Archive;
+
+#[test]
+fn parse_timemap() {
+    let input = r#"<http://example.com/>; rel="original",
+<https://arquivo.pt/wayback/timemap/link/http://example.com/>; rel="self"; type="application/link-format"; from="Sun, 13 Oct 1996 14:56:50 GMT",
+<https://arquivo.pt/wayback/http://example.com/>; rel="timegate",
+<https://arquivo.pt/wayback/19961013145650/http://example.com/>; rel="first memento"; datetime="Sun, 13 Oct 1996 14:56:50 GMT",
+<https://arquivo.pt/wayback/20200101000000/http://example.com/>; rel="last memento"; datetime="Wed, 01 Jan 2020 00:00:00 GMT"
+"#;
+
+    let timemap = TimeMap::parse(Archive::ArquivoPt, "example.com", input).unwrap();
+
+    assert_eq!(timemap.original.as_deref(), Some("http://example.com/"));
+    assert_eq!(
+        timemap.timegate.as_deref(),
+        Some("https://arquivo.pt/wayback/http://example.com/")
+    );
+    assert_eq!(
+        timemap.mementos,
+        vec![
+            Memento {
+                archive: Archive::ArquivoPt,
+                url: "http://example.com/".to_string(),
+                timestamp: "19961013145650".parse().unwrap(),
+                memento_url: "https://arquivo.pt/wayback/19961013145650/http://example.com/"
+                    .to_string(),
+            },
+            Memento {
+                archive: Archive::ArquivoPt,
+                url: "http://example.com/".to_string(),
+                timestamp: "20200101000000".parse().unwrap(),
+                memento_url: "https://arquivo.pt/wayback/20200101000000/http://example.com/"
+                    .to_string(),
+            },
+        ]
+    );
+}
+
+#[test]
+fn parse_link_edge_cases() {
+    assert!(parse_links("").unwrap().is_empty());
+    assert_eq!(
+        parse_links(r#"<a,b>; title="x, y"; anchor, <c>"#).unwrap(),
+        vec![
+            Link {
+                uri: "a,b".to_string(),
+                params: vec![
+                    ("title".to_string(), "x, y".to_string()),
+                    ("anchor".to_string(), String::new())
+                ],
+            },
+            Link {
+                uri: "c".to_string(),
+                params: vec![],
+            }
+        ]
+    );
+    assert!(parse_links("<a>; rel=\"memento").is_err());
+    assert!(parse_links("a; rel=memento").is_err());
+}
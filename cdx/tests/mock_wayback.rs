@@ -0,0 +1,92 @@
+use aib_cdx::{client::IndexClient, entry::EntryList, memento::MementoClient};
+use aib_core::archive::Archive;
+use aib_mock_wayback::MockWayback;
+use futures::TryStreamExt;
+use std::time::Duration;
+
+#[tokio::test]
+async fn lookup_pages() {
+    let server = MockWayback::start_bundled().await.unwrap();
+    let client = IndexClient::new(server.cdx_base(), 5, Duration::ZERO).unwrap();
+
+    let (num_pages, pages) = client.lookup("example.com/", false, None).await.unwrap();
+    let pages = pages.try_collect::<Vec<_>>().await.unwrap();
+
+    assert_eq!(num_pages, 2);
+    assert_eq!(pages.len(), 2);
+
+    let entries = pages
+        .iter()
+        .map(|page| serde_json::from_str::<EntryList>(&page.content).unwrap())
+        .flat_map(|entries| entries.values)
+        .map(|entry| entry.original)
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        entries,
+        vec![
+            "https://example.com/",
+            "https://example.com/new",
+            "https://example.com/old"
+        ]
+    );
+    assert!(pages[1].url.contains("page=1"));
+
+    let (num_pages, _) = client.lookup("example.com/", true, Some(1)).await.unwrap();
+
+    assert_eq!(num_pages, 0);
+}
+
+#[tokio::test]
+async fn memento_lookup() {
+    let server = MockWayback::start_bundled().await.unwrap();
+    let client = MementoClient::new(Archive::ArquivoPt)
+        .unwrap()
+        .with_timemap_base(&server.timemap_base())
+        .with_timegate_base(&server.timegate_base());
+
+    let timemap = client.timemap("https://example.com/").await.unwrap();
+
+    assert_eq!(timemap.original.as_deref(), Some("https://example.com/"));
+    assert_eq!(
+        timemap
+            .mementos
+            .iter()
+            .map(|memento| (memento.archive, memento.timestamp.to_string()))
+            .collect::<Vec<_>>(),
+        vec![
+            (Archive::ArquivoPt, "20200101000000".to_string()),
+            (Archive::ArquivoPt, "20220101000000".to_string())
+        ]
+    );
+    assert_eq!(
+        timemap.mementos[1].memento_url,
+        format!(
+            "{}20220101000000/https://example.com/",
+            server.timegate_base()
+        )
+    );
+
+    let memento = client
+        .timegate("https://example.com/", "20210601000000".parse().unwrap())
+        .await
+        .unwrap()
+        .unwrap();
+
+    assert_eq!(memento, timemap.mementos[1]);
+
+    assert!(client
+        .timemap("https://example.com/missing")
+        .await
+        .unwrap()
+        .mementos
+        .is_empty());
+    assert!(client
+        .timegate(
+            "https://example.com/missing",
+            "20210601000000".parse().unwrap()
+        )
+        .await
+        .unwrap()
+        .is_none());
+}
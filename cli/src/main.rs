@@ -1,7 +1,11 @@
+use aib_extractor::Identifier;
 use aib_indexer::{query::Range, Query};
-use aib_manager::model::entry::InvalidDigest;
-use aib_store::items::ValidationError;
-use chrono::{NaiveDate, NaiveTime};
+use aib_manager::model::{entry::InvalidDigest, LinkTarget};
+use aib_store::items::{
+    ledger::{Ledger, Record, Status},
+    ValidationError,
+};
+use chrono::{Duration, NaiveDate, NaiveTime, Utc};
 use cli_helpers::prelude::*;
 use futures::stream::{StreamExt, TryStreamExt};
 use sqlx::Connection;
@@ -21,22 +25,34 @@ async fn main() -> Result<(), Error> {
             input,
             output,
             redirects,
+            index,
         } => {
             let legacy_store = aib_store::legacy::wayback::Store::new(input);
             let new_store = aib_store::items::ItemStore::new(output, Some(14));
-            let mut digests: HashSet<String> = new_store
-                .entries(32)
-                .filter_map(|result| async {
-                    match result {
-                        Ok(result) => result.ok().map(|entry| entry.digest.to_string()),
-                        Err(error) => {
-                            log::error!("{:?}", error);
-                            None
-                        }
-                    }
-                })
-                .collect()
-                .await;
+
+            let (new_store, mut digests): (_, HashSet<String>) = match index {
+                Some(index) => (
+                    new_store.with_index(aib_store::items::index::DigestIndex::open(index)?),
+                    HashSet::new(),
+                ),
+                None => {
+                    let digests = new_store
+                        .entries(32)
+                        .filter_map(|result| async {
+                            match result {
+                                Ok(result) => result.ok().map(|entry| entry.digest.to_string()),
+                                Err(error) => {
+                                    log::error!("{:?}", error);
+                                    None
+                                }
+                            }
+                        })
+                        .collect()
+                        .await;
+
+                    (new_store, digests)
+                }
+            };
 
             let redirect_digests = redirects
                 .map(|redirects| {
@@ -75,7 +91,11 @@ async fn main() -> Result<(), Error> {
                         .filter(|result| {
                             result
                                 .as_ref()
-                                .map(|(digest, _)| !digests.contains(digest))
+                                .map(|(digest, _)| {
+                                    !digests.contains(digest)
+                                        && (new_store.index().is_none()
+                                            || !new_store.contains(digest))
+                                })
                                 .unwrap_or(true)
                         })
                         .map(|result| result.map_err(Error::from)),
@@ -106,26 +126,103 @@ async fn main() -> Result<(), Error> {
                 println!("{:?}", dir);
             }
         }
-        Command::Validate { base } => {
+        Command::Validate {
+            base,
+            ledger,
+            max_age,
+            limit,
+            report,
+            quarantine,
+            index,
+        } => {
             let new_store = aib_store::items::ItemStore::new(base, Some(14));
+            let new_store = match index {
+                Some(index) => {
+                    new_store.with_index(aib_store::items::index::DigestIndex::open(index)?)
+                }
+                None => new_store,
+            };
+            let mut ledger = ledger.map(Ledger::open).transpose()?;
+            let mut report = report
+                .map(|path| csv::WriterBuilder::new().has_headers(false).from_path(path))
+                .transpose()?;
 
-            new_store
-                .entries(64)
-                .try_for_each(|result| async move {
-                    match result {
-                        Ok(entry) => {
-                            println!("{}", entry.digest);
-                        }
-                        Err(ValidationError::InvalidDigest { entry, digest: _ }) => {
-                            log::error!("Expected {}", entry.digest);
-                        }
-                        Err(ValidationError::Unexpected(path)) => {
-                            log::error!("Unexpected path: {:?}", path);
-                        }
+            let entries = new_store
+                .unchecked_entries()
+                .filter_map(|result| match result {
+                    Ok(Ok(entry)) => Some(Ok(entry)),
+                    Ok(Err(ValidationError::Unexpected(path))) => {
+                        log::error!("Unexpected path: {:?}", path);
+                        None
                     }
-                    Ok(())
-                })
-                .await?;
+                    Ok(Err(ValidationError::InvalidDigest { .. })) => None,
+                    Err(error) => Some(Err(error)),
+                });
+
+            let entries: Box<dyn Iterator<Item = aib_store::items::Entry>> = match &ledger {
+                Some(ledger) => {
+                    let cutoff = max_age.map(|days| Utc::now() - Duration::days(days));
+                    let entries = ledger.select(entries.collect::<Result<_, _>>()?, cutoff, limit);
+
+                    log::info!("Selected {} items for verification", entries.len());
+
+                    Box::new(entries.into_iter())
+                }
+                None => Box::new(
+                    entries
+                        .filter_map(|result| {
+                            result.map_err(|error| log::error!("{:?}", error)).ok()
+                        })
+                        .take(limit.unwrap_or(usize::MAX)),
+                ),
+            };
+
+            let mut results = new_store.verify(entries, 64);
+
+            while let Some(result) = results.next().await {
+                let (entry, record) = match result {
+                    Ok(Ok(entry)) => {
+                        println!("{}", entry.digest);
+                        let record = Record::new(entry.digest, Status::Valid, None);
+
+                        (entry, record)
+                    }
+                    Ok(Err(ValidationError::InvalidDigest { entry, digest })) => {
+                        log::error!("Expected {}", entry.digest);
+                        let record =
+                            Record::new(entry.digest, Status::Invalid, digest.parse().ok());
+
+                        (entry, record)
+                    }
+                    Ok(Err(ValidationError::Unexpected(path))) => {
+                        log::error!("Unexpected path: {:?}", path);
+                        continue;
+                    }
+                    Err(aib_store::items::Error::ValidationIo { entry, error }) => {
+                        log::error!("Unreadable {}: {:?}", entry.digest, error);
+                        let record = Record::new(entry.digest, Status::Unreadable, None);
+
+                        (entry, record)
+                    }
+                    Err(error) => return Err(error.into()),
+                };
+
+                if !record.is_valid() {
+                    if let Some(report) = report.as_mut() {
+                        report.serialize(&record)?;
+                        report.flush()?;
+                    }
+
+                    if let Some(quarantine) = quarantine.as_ref() {
+                        let destination = new_store.quarantine(&entry, quarantine)?;
+                        log::warn!("Quarantined {} to {:?}", entry.digest, destination);
+                    }
+                }
+
+                if let Some(ledger) = ledger.as_mut() {
+                    ledger.record(record)?;
+                }
+            }
         }
         Command::Invalid { base } => {
             let new_store = aib_store::items::ItemStore::new(base, Some(14));
@@ -244,24 +341,50 @@ async fn main() -> Result<(), Error> {
             )
             .await?;
 
-            manager.extract()?;
+            let count = manager.extract("text/html").await?;
+
+            log::info!("Recorded {} links", count);
         }
         Command::ManagerIndex {
             index,
             item_store,
             item_level,
+            mime_type,
+            metadata,
+            main_content,
         } => {
-            let mut manager = aib_manager::Manager::open(
+            let manager = aib_manager::Manager::open(
                 "sqlite://manager/data/state.db",
                 index,
                 item_store,
                 item_level,
             )
-            .await?;
+            .await?
+            .with_mode(if main_content {
+                aib_extractor::Mode::MainContent
+            } else {
+                aib_extractor::Mode::Full
+            });
+            let mut manager = match metadata {
+                Some(metadata) => manager
+                    .with_metadata(aib_store::items::metadata::MetadataTable::open(metadata)?),
+                None => manager,
+            };
 
-            let count = manager.index("text/html").await?;
+            let summary = manager.index(&mime_type).await?;
+
+            for (charset, count) in &summary.charsets {
+                log::info!("{}: {} documents", charset, count);
+            }
 
-            log::info!("Indexed {} documents", count);
+            log::info!(
+                "Indexed {} documents ({} with decoding errors, {} not extracted, {} skipped, {} links)",
+                summary.documents,
+                summary.decoding_errors,
+                summary.extraction_errors,
+                summary.skipped,
+                summary.links
+            );
         }
         Command::Search {
             index,
@@ -273,6 +396,20 @@ async fn main() -> Result<(), Error> {
             end_date,
             pattern,
             year,
+            lang,
+            status_author,
+            status_id,
+            posted_start,
+            posted_end,
+            in_reply_to,
+            retweeted_by,
+            identifier,
+            types,
+            author,
+            published_start,
+            published_end,
+            modified_start,
+            modified_end,
             limit,
             offset,
         } => {
@@ -289,17 +426,27 @@ async fn main() -> Result<(), Error> {
                 manager.index.initialize_surt_ids()?
             );
 
-            let date_range = Range::new(start_date, end_date);
-            let date_time_range =
-                date_range.map(|range| range.map(|value| value.and_time(NaiveTime::MIN).and_utc()));
+            let date_time_range = |start: Option<NaiveDate>, end: Option<NaiveDate>| {
+                Range::new(start, end)
+                    .map(|range| range.map(|value| value.and_time(NaiveTime::MIN).and_utc()))
+            };
 
-            let query = Query::new(
-                &query,
-                email.as_deref(),
-                date_time_range,
-                pattern.unwrap_or_default(),
-                year.unwrap_or_default(),
-            );
+            let query = Query::new(&query)
+                .with_gravatar_email(email.as_deref())
+                .with_date_range(date_time_range(start_date, end_date))
+                .with_pattern_slugs(pattern.unwrap_or_default())
+                .with_years(year.unwrap_or_default())
+                .with_langs(lang.unwrap_or_default())
+                .with_status_authors(status_author.unwrap_or_default())
+                .with_status_ids(status_id.unwrap_or_default())
+                .with_posted_range(date_time_range(posted_start, posted_end))
+                .with_in_reply_to(in_reply_to.unwrap_or_default())
+                .with_retweeted_by(retweeted_by.unwrap_or_default())
+                .with_identifiers(identifier.unwrap_or_default())
+                .with_types(types.unwrap_or_default())
+                .with_author(author.as_deref())
+                .with_published_range(date_time_range(published_start, published_end))
+                .with_modified_range(date_time_range(modified_start, modified_end));
 
             let result = manager.search(100, &query, limit, offset).await?;
 
@@ -326,8 +473,13 @@ async fn main() -> Result<(), Error> {
             store,
             level,
             mime_type,
+            index,
         } => {
             let store = aib_store::items::ItemStore::new(store, level);
+            let store = match index {
+                Some(index) => store.with_index(aib_store::items::index::DigestIndex::open(index)?),
+                None => store,
+            };
             let mut connection = sqlx::SqliteConnection::connect(&db_url).await?;
 
             let count =
@@ -349,7 +501,324 @@ async fn main() -> Result<(), Error> {
                 writer.serialize(entry)?;
             }
         }
+        Command::Download {
+            db_url,
+            store,
+            level,
+            index,
+            mime_type,
+            parallelism,
+            rate,
+            limit,
+            max_attempts,
+            retry_delay,
+            base_url,
+            max_body_size,
+            temp_dir,
+            metadata,
+            requisites,
+        } => {
+            let store = aib_store::items::ItemStore::new(store, level);
+            let store = match index {
+                Some(index) => store.with_index(aib_store::items::index::DigestIndex::open(index)?),
+                None => store,
+            };
+            let store = match temp_dir {
+                Some(temp_dir) => store.with_temp_dir(temp_dir),
+                None => store,
+            };
+            let store = match metadata {
+                Some(metadata) => {
+                    store.with_metadata(aib_store::items::metadata::MetadataTable::open(metadata)?)
+                }
+                None => store,
+            };
+            let downloader = aib_downloader::Downloader::default()
+                .with_rate_limit(rate)?
+                .with_max_body_size(max_body_size);
+            let downloader = match base_url {
+                Some(base_url) => downloader.with_base_url(&base_url)?,
+                None => downloader,
+            };
+            let mut connection = sqlx::SqliteConnection::connect(&db_url).await?;
+
+            let config = aib_manager::download::Config {
+                parallelism,
+                limit,
+                retry_policy: aib_manager::model::RetryPolicy {
+                    max_attempts,
+                    base_delay: chrono::Duration::seconds(retry_delay),
+                },
+                ..Default::default()
+            };
+
+            let summary = aib_manager::download::run(
+                &mut connection,
+                &aib_downloader::ArchiveDownloader::new(downloader.clone()),
+                &store,
+                &mime_type,
+                &config,
+            )
+            .await?;
+
+            log::info!(
+                "Downloaded {} entries ({} with invalid digests), {} failed ({} permanently)",
+                summary.downloaded,
+                summary.invalid_digest,
+                summary.failed,
+                summary.permanent
+            );
+
+            if requisites {
+                let config = aib_manager::requisites::Config {
+                    parallelism,
+                    ..Default::default()
+                };
+
+                let summary = aib_manager::requisites::run(
+                    &mut connection,
+                    &downloader,
+                    &store,
+                    &mime_type,
+                    &config,
+                )
+                .await?;
+
+                log::info!(
+                    "Downloaded {} resources for {} pages, {} failed",
+                    summary.downloaded,
+                    summary.pages,
+                    summary.failed
+                );
+            }
+        }
+        Command::Failures { db_url } => {
+            let mut connection = sqlx::SqliteConnection::connect(&db_url).await?;
+
+            let mut writer = csv::WriterBuilder::new()
+                .has_headers(false)
+                .from_writer(std::io::stdout());
+
+            for count in aib_manager::db::entry::failure_counts(&mut connection).await? {
+                writer.serialize(count)?;
+            }
+        }
+        Command::Links {
+            db_url,
+            snapshot_id,
+        } => {
+            let mut connection = sqlx::SqliteConnection::connect(&db_url).await?;
+
+            let mut writer = csv::WriterBuilder::new()
+                .has_headers(false)
+                .from_writer(std::io::stdout());
+
+            for link in aib_manager::db::link::outbound(&mut connection, snapshot_id).await? {
+                writer.serialize(link)?;
+            }
+        }
+        Command::Backlinks {
+            db_url,
+            url,
+            surt_prefix,
+            limit,
+        } => {
+            let target = match (url, surt_prefix) {
+                (Some(url), None) => LinkTarget::url(&url)?,
+                (None, Some(surt_prefix)) => LinkTarget::SurtPrefix(surt_prefix),
+                _ => {
+                    return Err(Error::InvalidArguments(
+                        "Exactly one of --url and --surt-prefix is required".to_string(),
+                    ))
+                }
+            };
+
+            let mut connection = sqlx::SqliteConnection::connect(&db_url).await?;
+
+            let mut writer = csv::WriterBuilder::new()
+                .has_headers(false)
+                .from_writer(std::io::stdout());
+
+            for link in aib_manager::db::link::inbound(&mut connection, &target, limit).await? {
+                writer.serialize(link)?;
+            }
+        }
+        Command::LinkDomains {
+            db_url,
+            pattern,
+            limit,
+        } => {
+            let mut connection = sqlx::SqliteConnection::connect(&db_url).await?;
+
+            let mut writer = csv::WriterBuilder::new()
+                .has_headers(false)
+                .from_writer(std::io::stdout());
+
+            for count in
+                aib_manager::db::link::top_external_domains(&mut connection, &pattern, limit)
+                    .await?
+            {
+                writer.serialize(count)?;
+            }
+        }
+        Command::Save {
+            db_url,
+            pattern,
+            input,
+            access_key,
+            secret_key,
+            base_url,
+            mime_type,
+            parallelism,
+            poll_interval,
+            max_polls,
+        } => {
+            let mut connection = sqlx::SqliteConnection::connect(&db_url).await?;
+
+            let (pattern_id, mut urls) = match pattern {
+                Some(slug) => {
+                    let (pattern_id, urls) =
+                        aib_manager::save::pattern_urls(&mut connection, &slug).await?;
+
+                    (Some(pattern_id), urls)
+                }
+                None => (None, vec![]),
+            };
+
+            if let Some(input) = input {
+                for line in BufReader::new(File::open(input)?).lines() {
+                    let line = line?;
+                    let url = line.trim();
+
+                    if !url.is_empty() {
+                        urls.push(url.to_string());
+                    }
+                }
+            }
+
+            let client = aib_downloader::save::SaveClient::default()
+                .with_polling(std::time::Duration::from_secs(poll_interval), max_polls);
+            let client = match base_url {
+                Some(base_url) => client.with_base_url(&base_url)?,
+                None => client,
+            };
+            let client = match access_key.zip(secret_key) {
+                Some((access_key, secret_key)) => client.with_credentials(
+                    aib_downloader::save::Credentials::new(&access_key, &secret_key),
+                ),
+                None => client,
+            };
+
+            let summary = aib_manager::save::run(
+                &mut connection,
+                &client,
+                &urls,
+                pattern_id,
+                &mime_type,
+                parallelism,
+            )
+            .await?;
+
+            let mut writer = csv::WriterBuilder::new()
+                .has_headers(false)
+                .from_writer(std::io::stdout());
+
+            for capture in &summary.captures {
+                writer.write_record([capture.url.as_str(), &capture.timestamp.to_string()])?;
+            }
+
+            log::info!(
+                "Saved {} URLs, {} failed",
+                summary.captures.len(),
+                summary.failed
+            );
+        }
+
+        Command::Memento {
+            db_url,
+            archive,
+            input,
+            mime_type,
+            timemap_base,
+        } => {
+            let mut connection = sqlx::SqliteConnection::connect(&db_url).await?;
+            let mut urls = vec![];
+
+            for line in BufReader::new(File::open(input)?).lines() {
+                let line = line?;
+                let url = line.trim();
+
+                if !url.is_empty() {
+                    urls.push(url.to_string());
+                }
+            }
 
+            let client = aib_cdx::memento::MementoClient::new(archive)?;
+            let client = match timemap_base {
+                Some(timemap_base) => client.with_timemap_base(&timemap_base),
+                None => client,
+            };
+
+            let summary =
+                aib_manager::memento::import(&mut connection, &client, &urls, &mime_type).await?;
+
+            log::info!(
+                "Imported {} entries from {}, {} URLs failed",
+                summary.entries,
+                archive,
+                summary.failed
+            );
+        }
+        Command::Gc {
+            db_url,
+            store,
+            level,
+            allowlist,
+            delete,
+            index,
+        } => {
+            let store = aib_store::items::ItemStore::new(store, level);
+            let store = match index {
+                Some(index) => store.with_index(aib_store::items::index::DigestIndex::open(index)?),
+                None => store,
+            };
+            let mut connection = sqlx::SqliteConnection::connect(&db_url).await?;
+
+            let report = aib_manager::gc::report(&mut connection, &store).await?;
+
+            log::info!(
+                "Found {} orphaned items ({} bytes) and {} missing items",
+                report.orphaned.len(),
+                report.orphaned_size(),
+                report.missing.len()
+            );
+
+            let mut writer = csv::WriterBuilder::new()
+                .has_headers(false)
+                .from_writer(std::io::stdout());
+
+            for item in report.items() {
+                writer.serialize(item)?;
+            }
+
+            writer.flush()?;
+
+            if delete {
+                let allowlist = allowlist
+                    .map(|path| {
+                        BufReader::new(File::open(path)?)
+                            .lines()
+                            .map(|line| Ok(line?.trim().parse()?))
+                            .collect::<Result<HashSet<_>, Error>>()
+                    })
+                    .transpose()?
+                    .unwrap_or_default();
+
+                let (count, size) = aib_manager::gc::collect(&store, &report, &allowlist)?;
+
+                log::info!("Deleted {} items ({} bytes)", count, size);
+            }
+        }
         Command::InvalidDigests { db_url } => {
             let mut connection = sqlx::SqliteConnection::connect(&db_url).await?;
 
@@ -406,6 +875,12 @@ pub enum Error {
     Store(#[from] aib_store::Error),
     #[error("Item store error")]
     ItemStore(#[from] aib_store::items::Error),
+    #[error("Item metadata error")]
+    ItemMetadata(#[from] aib_store::items::metadata::Error),
+    #[error("Item ledger error")]
+    ItemLedger(#[from] aib_store::items::ledger::Error),
+    #[error("Item index error")]
+    ItemIndex(#[from] aib_store::items::index::Error),
     #[error("CDX index error")]
     Cdx(#[from] aib_cdx::client::Error),
     #[error("CDX store error")]
@@ -414,10 +889,32 @@ pub enum Error {
     Manager(#[from] aib_manager::Error),
     #[error("Manager import error")]
     ManagerImport(#[from] aib_manager::import::Error),
+    #[error("Manager GC error")]
+    ManagerGc(#[from] aib_manager::gc::Error),
+    #[error("Downloader error")]
+    Downloader(#[from] aib_downloader::Error),
+    #[error("Manager download error")]
+    ManagerDownload(#[from] aib_manager::download::Error),
+    #[error("Manager requisites error")]
+    ManagerRequisites(#[from] aib_manager::requisites::Error),
+    #[error("Manager save error")]
+    ManagerSave(#[from] aib_manager::save::Error),
+    #[error("Save Page Now error")]
+    Save(#[from] aib_downloader::save::Error),
+    #[error("Manager Memento import error")]
+    ManagerMemento(#[from] aib_manager::memento::Error),
+    #[error("Memento error")]
+    Memento(#[from] aib_cdx::memento::Error),
+    #[error("Digest error")]
+    Digest(#[from] aib_core::digest::Error),
     #[error("Index error")]
     Index(#[from] aib_indexer::Error),
     #[error("SQLx error")]
     Sqlx(#[from] sqlx::Error),
+    #[error("SURT error")]
+    Surt(#[from] aib_core::surt::Error),
+    #[error("Invalid arguments: {0}")]
+    InvalidArguments(String),
 }
 
 #[derive(Debug, Parser)]
@@ -438,6 +935,8 @@ enum Command {
         output: PathBuf,
         #[clap(long)]
         redirects: Option<PathBuf>,
+        #[clap(long)]
+        index: Option<PathBuf>,
     },
     List {
         #[clap(long)]
@@ -446,6 +945,18 @@ enum Command {
     Validate {
         #[clap(long)]
         base: PathBuf,
+        #[clap(long)]
+        ledger: Option<PathBuf>,
+        #[clap(long, requires = "ledger")]
+        max_age: Option<i64>,
+        #[clap(long)]
+        limit: Option<usize>,
+        #[clap(long)]
+        report: Option<PathBuf>,
+        #[clap(long)]
+        quarantine: Option<PathBuf>,
+        #[clap(long)]
+        index: Option<PathBuf>,
     },
     Invalid {
         #[clap(long)]
@@ -492,6 +1003,12 @@ enum Command {
         item_store: PathBuf,
         #[clap(long)]
         item_level: Option<i32>,
+        #[clap(long, default_value = "text/html")]
+        mime_type: String,
+        #[clap(long)]
+        metadata: Option<PathBuf>,
+        #[clap(long)]
+        main_content: bool,
     },
     Search {
         #[clap(long)]
@@ -512,6 +1029,34 @@ enum Command {
         pattern: Option<Vec<String>>,
         #[clap(long)]
         year: Option<Vec<u16>>,
+        #[clap(long)]
+        lang: Option<Vec<String>>,
+        #[clap(long)]
+        status_author: Option<Vec<String>>,
+        #[clap(long)]
+        status_id: Option<Vec<u64>>,
+        #[clap(long)]
+        posted_start: Option<NaiveDate>,
+        #[clap(long)]
+        posted_end: Option<NaiveDate>,
+        #[clap(long)]
+        in_reply_to: Option<Vec<String>>,
+        #[clap(long)]
+        retweeted_by: Option<Vec<String>>,
+        #[clap(long)]
+        identifier: Option<Vec<Identifier>>,
+        #[clap(long = "type")]
+        types: Option<Vec<String>>,
+        #[clap(long)]
+        author: Option<String>,
+        #[clap(long)]
+        published_start: Option<NaiveDate>,
+        #[clap(long)]
+        published_end: Option<NaiveDate>,
+        #[clap(long)]
+        modified_start: Option<NaiveDate>,
+        #[clap(long)]
+        modified_end: Option<NaiveDate>,
         #[clap(long, default_value = "100")]
         limit: usize,
         #[clap(long, default_value = "0")]
@@ -532,6 +1077,8 @@ enum Command {
         level: Option<i32>,
         #[clap(long, default_value = "text/html")]
         mime_type: String,
+        #[clap(long)]
+        index: Option<PathBuf>,
     },
     MissingSnapshots {
         #[clap(long)]
@@ -539,6 +1086,114 @@ enum Command {
         #[clap(long, default_value = "text/html")]
         mime_type: String,
     },
+    Download {
+        #[clap(long)]
+        db_url: String,
+        #[clap(long)]
+        store: PathBuf,
+        #[clap(long)]
+        level: Option<i32>,
+        #[clap(long)]
+        index: Option<PathBuf>,
+        #[clap(long, default_value = "text/html")]
+        mime_type: String,
+        #[clap(long, default_value = "4")]
+        parallelism: usize,
+        #[clap(long, default_value = "1.0")]
+        rate: f64,
+        #[clap(long)]
+        limit: Option<usize>,
+        #[clap(long, default_value = "5")]
+        max_attempts: u32,
+        #[clap(long, default_value = "3600")]
+        retry_delay: i64,
+        #[clap(long)]
+        base_url: Option<String>,
+        #[clap(long, default_value = "104857600")]
+        max_body_size: u64,
+        #[clap(long)]
+        temp_dir: Option<PathBuf>,
+        #[clap(long)]
+        metadata: Option<PathBuf>,
+        #[clap(long)]
+        requisites: bool,
+    },
+    Failures {
+        #[clap(long)]
+        db_url: String,
+    },
+    Links {
+        #[clap(long)]
+        db_url: String,
+        #[clap(long)]
+        snapshot_id: i64,
+    },
+    Backlinks {
+        #[clap(long)]
+        db_url: String,
+        #[clap(long)]
+        url: Option<String>,
+        #[clap(long)]
+        surt_prefix: Option<String>,
+        #[clap(long, default_value = "100")]
+        limit: usize,
+    },
+    LinkDomains {
+        #[clap(long)]
+        db_url: String,
+        #[clap(long)]
+        pattern: String,
+        #[clap(long, default_value = "100")]
+        limit: usize,
+    },
+    Save {
+        #[clap(long)]
+        db_url: String,
+        #[clap(long)]
+        pattern: Option<String>,
+        #[clap(long)]
+        input: Option<PathBuf>,
+        #[clap(long, requires = "secret_key")]
+        access_key: Option<String>,
+        #[clap(long, requires = "access_key")]
+        secret_key: Option<String>,
+        #[clap(long)]
+        base_url: Option<String>,
+        #[clap(long, default_value = "text/html")]
+        mime_type: String,
+        #[clap(long, default_value = "1")]
+        parallelism: usize,
+        #[clap(long, default_value = "5")]
+        poll_interval: u64,
+        #[clap(long, default_value = "120")]
+        max_polls: usize,
+    },
+    Memento {
+        #[clap(long)]
+        db_url: String,
+        #[clap(long)]
+        archive: aib_core::archive::Archive,
+        #[clap(long)]
+        input: PathBuf,
+        #[clap(long, default_value = "text/html")]
+        mime_type: String,
+        #[clap(long)]
+        timemap_base: Option<String>,
+    },
+    Gc {
+        #[clap(long)]
+        db_url: String,
+        #[clap(long)]
+        store: PathBuf,
+        #[clap(long)]
+        level: Option<i32>,
+        #[clap(long)]
+        allowlist: Option<PathBuf>,
+        #[clap(long)]
+        delete: bool,
+        #[clap(long)]
+        index: Option<PathBuf>,
+    },
     InvalidDigests {
         #[clap(long)]
         db_url: String,
@@ -0,0 +1,96 @@
+//! Web archives that provide Memento (RFC 7089) access to their captures.
+//!
+//! Captures are identified by the archive they come from. Every archive here
+//! publishes link-format TimeMaps and replays captures at
+//! `<base><timestamp><modifier>/<url>`. Some archives (e.g. archive.today)
+//! don't support replay modifiers.
+
+use std::fmt::Display;
+use std::str::FromStr;
+
+#[derive(thiserror::Error, Debug)]
+#[error("Unknown archive: {0}")]
+pub struct Error(String);
+
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Archive {
+    /// The Internet Archive's Wayback Machine.
+    #[default]
+    Wayback,
+    ArchiveToday,
+    ArquivoPt,
+    UkWebArchive,
+    LibraryOfCongress,
+}
+
+impl Archive {
+    pub const ALL: [Self; 5] = [
+        Self::Wayback,
+        Self::ArchiveToday,
+        Self::ArquivoPt,
+        Self::UkWebArchive,
+        Self::LibraryOfCongress,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Wayback => "wayback",
+            Self::ArchiveToday => "archive-today",
+            Self::ArquivoPt => "arquivo-pt",
+            Self::UkWebArchive => "ukwa",
+            Self::LibraryOfCongress => "loc",
+        }
+    }
+
+    /// The base URL for replaying captures.
+    pub fn replay_base(&self) -> &'static str {
+        match self {
+            Self::Wayback => "http://web.archive.org/web/",
+            Self::ArchiveToday => "https://archive.ph/",
+            Self::ArquivoPt => "https://arquivo.pt/wayback/",
+            Self::UkWebArchive => "https://www.webarchive.org.uk/wayback/archive/",
+            Self::LibraryOfCongress => "https://webarchive.loc.gov/all/",
+        }
+    }
+
+    /// The base URL for link-format TimeMaps (`<base><url>`).
+    pub fn timemap_base(&self) -> &'static str {
+        match self {
+            Self::Wayback => "http://web.archive.org/web/timemap/link/",
+            Self::ArchiveToday => "https://archive.ph/timemap/",
+            Self::ArquivoPt => "https://arquivo.pt/wayback/timemap/link/",
+            Self::UkWebArchive => "https://www.webarchive.org.uk/wayback/archive/timemap/link/",
+            Self::LibraryOfCongress => "https://webarchive.loc.gov/all/timemap/link/",
+        }
+    }
+
+    /// The base URL for TimeGate requests (`<base><url>`).
+    pub fn timegate_base(&self) -> &'static str {
+        match self {
+            Self::ArchiveToday => "https://archive.ph/timegate/",
+            other => other.replay_base(),
+        }
+    }
+
+    /// Whether captures can be requested with a replay modifier (such as `id_`).
+    pub fn supports_modifiers(&self) -> bool {
+        !matches!(self, Self::ArchiveToday)
+    }
+}
+
+impl Display for Archive {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Archive {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|archive| archive.as_str() == s)
+            .ok_or_else(|| Error(s.to_string()))
+    }
+}
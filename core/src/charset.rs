@@ -0,0 +1,261 @@
+//! Detection of the character encoding of archived pages.
+//!
+//! The encoding is determined (in order of precedence) from a byte order mark,
+//! the `charset` parameter of the original `Content-Type` header, an XML or
+//! `<meta>` declaration near the start of the document, and finally
+//! statistical sniffing of the content.
+
+use chardetng::EncodingDetector;
+use encoding_rs::Encoding;
+use once_cell::sync::Lazy;
+use regex::bytes::Regex;
+use std::borrow::Cow;
+use std::fmt::Display;
+
+/// The number of bytes searched for a `<meta>` declaration.
+const META_PRESCAN_LENGTH: usize = 4096;
+
+static META_CHARSET_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"(?i-u)<meta\s[^>]*?charset\s*=\s*["']?\s*(?P<label>[a-z0-9_:.\-]+)"#).unwrap()
+});
+
+static XML_ENCODING_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"(?i-u)^\s*<\?xml\s[^>]*?encoding\s*=\s*["'](?P<label>[a-z0-9_:.\-]+)"#).unwrap()
+});
+
+/// Where the encoding of a document was found.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Source {
+    Bom,
+    Header,
+    /// A `<meta>` or XML declaration.
+    Meta,
+    Sniffed,
+}
+
+impl Source {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Bom => "bom",
+            Self::Header => "header",
+            Self::Meta => "meta",
+            Self::Sniffed => "sniffed",
+        }
+    }
+}
+
+impl Display for Source {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Charset {
+    pub encoding: &'static Encoding,
+    pub source: Source,
+}
+
+impl Charset {
+    /// The canonical name of the encoding (e.g. `Shift_JIS`).
+    pub fn name(&self) -> &'static str {
+        self.encoding.name()
+    }
+}
+
+/// A document decoded to UTF-8.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Decoded<'a> {
+    pub text: Cow<'a, str>,
+    pub charset: Charset,
+    /// Whether malformed sequences were replaced.
+    pub had_errors: bool,
+}
+
+/// Determine the encoding of a document, given its original `Content-Type` header (if known).
+pub fn detect(bytes: &[u8], content_type: Option<&str>) -> Charset {
+    detect_with(bytes, content_type, true)
+}
+
+/// Determine the encoding of a document from its leading bytes.
+///
+/// Unlike [`detect`], a multi-byte sequence cut off at the end is not treated as malformed.
+pub fn detect_prefix(bytes: &[u8], content_type: Option<&str>) -> Charset {
+    detect_with(bytes, content_type, false)
+}
+
+fn detect_with(bytes: &[u8], content_type: Option<&str>, last: bool) -> Charset {
+    if let Some((encoding, _)) = Encoding::for_bom(bytes) {
+        return Charset {
+            encoding,
+            source: Source::Bom,
+        };
+    }
+
+    if let Some(encoding) = content_type.and_then(header_encoding) {
+        return Charset {
+            encoding,
+            source: Source::Header,
+        };
+    }
+
+    if let Some(encoding) = meta_encoding(bytes) {
+        return Charset {
+            encoding,
+            source: Source::Meta,
+        };
+    }
+
+    let mut detector = EncodingDetector::new();
+    detector.feed(bytes, last);
+
+    Charset {
+        encoding: detector.guess(None, true),
+        source: Source::Sniffed,
+    }
+}
+
+/// Detect the encoding of a document and decode it.
+///
+/// Malformed sequences are replaced with the replacement character.
+pub fn decode<'a>(bytes: &'a [u8], content_type: Option<&str>) -> Decoded<'a> {
+    let charset = detect(bytes, content_type);
+
+    let (text, had_errors) = match Encoding::for_bom(bytes) {
+        Some((_, bom_length)) if charset.source == Source::Bom => charset
+            .encoding
+            .decode_without_bom_handling(&bytes[bom_length..]),
+        _ => charset.encoding.decode_without_bom_handling(bytes),
+    };
+
+    Decoded {
+        text,
+        charset,
+        had_errors,
+    }
+}
+
+fn header_encoding(content_type: &str) -> Option<&'static Encoding> {
+    content_type.split(';').skip(1).find_map(|parameter| {
+        let (name, value) = parameter.split_once('=')?;
+
+        if name.trim().eq_ignore_ascii_case("charset") {
+            Encoding::for_label(value.trim().trim_matches(['"', '\'']).as_bytes())
+        } else {
+            None
+        }
+    })
+}
+
+fn meta_encoding(bytes: &[u8]) -> Option<&'static Encoding> {
+    let prefix = &bytes[..bytes.len().min(META_PRESCAN_LENGTH)];
+
+    XML_ENCODING_RE
+        .captures(prefix)
+        .into_iter()
+        .chain(META_CHARSET_RE.captures_iter(prefix))
+        .find_map(|captures| Encoding::for_label(&captures["label"]))
+        // A document can't declare a UTF-16 encoding for itself (since the
+        // declaration would not be readable as ASCII).
+        .map(|encoding| encoding.output_encoding())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use encoding_rs::{SHIFT_JIS, UTF_16LE, UTF_8, WINDOWS_1252};
+
+    #[test]
+    fn detect_precedence() {
+        let html = b"<html><head><meta charset=\"windows-1252\"></head><body>Caf\xe9</body></html>";
+
+        assert_eq!(
+            detect(html, Some("text/html; charset=ISO-8859-2")),
+            Charset {
+                encoding: encoding_rs::ISO_8859_2,
+                source: Source::Header
+            }
+        );
+        assert_eq!(
+            detect(html, Some("text/html")),
+            Charset {
+                encoding: WINDOWS_1252,
+                source: Source::Meta
+            }
+        );
+
+        let with_bom = [b"\xef\xbb\xbf".as_slice(), html].concat();
+
+        assert_eq!(
+            detect(&with_bom, Some("text/html; charset=ISO-8859-2")),
+            Charset {
+                encoding: UTF_8,
+                source: Source::Bom
+            }
+        );
+    }
+
+    #[test]
+    fn decode_declared() {
+        let html = b"<meta http-equiv=\"Content-Type\" content=\"text/html; charset=iso-8859-1\"><p>Caf\xe9</p>";
+        let decoded = decode(html, None);
+
+        assert_eq!(decoded.charset.name(), "windows-1252");
+        assert_eq!(decoded.charset.source, Source::Meta);
+        assert_eq!(
+            decoded.text,
+            r#"<meta http-equiv="Content-Type" content="text/html; charset=iso-8859-1"><p>Café</p>"#
+        );
+        assert!(!decoded.had_errors);
+
+        let utf_16 = "\u{feff}<p>Café</p>"
+            .encode_utf16()
+            .flat_map(u16::to_le_bytes)
+            .collect::<Vec<_>>();
+        let decoded = decode(&utf_16, None);
+
+        assert_eq!(decoded.charset.encoding, UTF_16LE);
+        assert_eq!(decoded.text, "<p>Café</p>");
+
+        let html = b"<meta charset=\"utf-16\"><p>Caf\xc3\xa9</p>";
+
+        assert_eq!(decode(html, None).charset.encoding, UTF_8);
+    }
+
+    #[test]
+    fn detect_xml_declaration() {
+        let xml = b"<?xml version=\"1.0\" encoding=\"Shift_JIS\"?><rss></rss>";
+
+        assert_eq!(
+            detect(xml, None),
+            Charset {
+                encoding: SHIFT_JIS,
+                source: Source::Meta
+            }
+        );
+    }
+
+    #[test]
+    fn detect_truncated_prefix() {
+        let text = "<p>日本語のページです。これは文字コードの判定のテストです。</p>";
+        let prefix = &text.as_bytes()[..text.len() - 8];
+
+        assert_eq!(detect_prefix(prefix, None).encoding, UTF_8);
+    }
+
+    #[test]
+    fn decode_sniffed() {
+        let text = "<html><body><p>日本語のページです。これは文字コードの判定のテストです。</p></body></html>";
+        let (shift_jis, _, _) = SHIFT_JIS.encode(text);
+        let decoded = decode(&shift_jis, Some("text/html"));
+
+        assert_eq!(decoded.charset.encoding, SHIFT_JIS);
+        assert_eq!(decoded.charset.source, Source::Sniffed);
+        assert_eq!(decoded.text, text);
+
+        let decoded = decode(text.as_bytes(), None);
+
+        assert_eq!(decoded.charset.encoding, UTF_8);
+        assert!(matches!(decoded.text, Cow::Borrowed(_)));
+    }
+}
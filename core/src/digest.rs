@@ -165,7 +165,59 @@ impl Serialize for Digest {
     }
 }
 
-#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+/// A writer that computes the SHA-1 digest and length of everything written
+/// through it.
+pub struct DigestWriter<W> {
+    inner: W,
+    hasher: sha1::Sha1,
+    length: u64,
+}
+
+impl<W: Write> DigestWriter<W> {
+    pub fn new(inner: W) -> Self {
+        Self {
+            inner,
+            hasher: sha1::Sha1::new(),
+            length: 0,
+        }
+    }
+
+    /// The number of bytes written so far.
+    pub fn length(&self) -> u64 {
+        self.length
+    }
+
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    /// Flush the underlying writer and return it with the digest and length.
+    pub fn finish(mut self) -> std::io::Result<(W, Sha1Digest, u64)> {
+        self.inner.flush()?;
+
+        Ok((
+            self.inner,
+            Sha1Digest(self.hasher.finalize().into()),
+            self.length,
+        ))
+    }
+}
+
+impl<W: Write> Write for DigestWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let count = self.inner.write(buf)?;
+        self.hasher.update(&buf[..count]);
+        self.length += count as u64;
+
+        Ok(count)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
 pub struct Sha1Digest(pub [u8; 20]);
 
 impl Display for Sha1Digest {
@@ -242,4 +294,25 @@ mod tests {
 
         assert_eq!(digest_str, digest_string);
     }
+
+    #[test]
+    fn digest_writer() {
+        use std::io::Write;
+
+        let content = b"Hello, world!\n".repeat(1000);
+        let mut writer = super::DigestWriter::new(vec![]);
+
+        for chunk in content.chunks(333) {
+            writer.write_all(chunk).unwrap();
+        }
+
+        let (written, digest, length) = writer.finish().unwrap();
+
+        assert_eq!(written, content);
+        assert_eq!(length, content.len() as u64);
+        assert_eq!(
+            digest,
+            super::compute_digest(&mut content.as_slice()).unwrap()
+        );
+    }
 }
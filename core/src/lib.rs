@@ -1,3 +1,5 @@
+pub mod archive;
+pub mod charset;
 pub mod digest;
 pub mod entry;
 pub mod redirect;
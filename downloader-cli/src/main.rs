@@ -37,7 +37,10 @@ async fn main() -> Result<(), Error> {
         .has_headers(false)
         .from_writer(File::create(output_invalid_digests_file)?);
 
-    let downloader = aib_downloader::Downloader::default();
+    let downloader = match opts.base_url {
+        Some(base_url) => aib_downloader::Downloader::default().with_base_url(&base_url)?,
+        None => aib_downloader::Downloader::default(),
+    };
     let sha1_computer = Sha1Computer::default();
 
     for EntryInfo {
@@ -47,7 +50,10 @@ async fn main() -> Result<(), Error> {
     {
         log::info!("Downloading {} ({})", url, timestamp);
 
-        if let Some(result) = downloader.download(&url, timestamp, true).await? {
+        if let Some(result) = downloader
+            .download(&url, timestamp, aib_downloader::Modifier::Original)
+            .await?
+        {
             for redirect in result.redirects {
                 log::warn!("Redirecting: {} ({}) to {}", url, timestamp, redirect.url);
             }
@@ -97,4 +103,6 @@ struct Opts {
     verbose: Verbosity,
     #[clap(long)]
     output: PathBuf,
+    #[clap(long)]
+    base_url: Option<String>,
 }
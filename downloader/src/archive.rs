@@ -0,0 +1,56 @@
+//! Downloading captures from more than one archive.
+
+use crate::Downloader;
+use aib_core::archive::Archive;
+use std::collections::HashMap;
+
+/// A downloader for each archive.
+///
+/// Unless a downloader is provided for an archive, one is derived from the
+/// Wayback Machine downloader (see [`Downloader::for_archive`]), so that all
+/// archives share its settings but not its rate limit.
+#[derive(Clone, Debug)]
+pub struct ArchiveDownloader {
+    wayback: Downloader,
+    downloaders: HashMap<Archive, Downloader>,
+}
+
+impl ArchiveDownloader {
+    pub fn new(wayback: Downloader) -> Self {
+        let downloaders = Archive::ALL
+            .into_iter()
+            .filter(|archive| *archive != Archive::Wayback)
+            .map(|archive| (archive, wayback.for_archive(archive)))
+            .collect();
+
+        Self {
+            wayback,
+            downloaders,
+        }
+    }
+
+    /// Use the given downloader for captures from an archive.
+    pub fn with_archive(mut self, archive: Archive, downloader: Downloader) -> Self {
+        if archive == Archive::Wayback {
+            self.wayback = downloader;
+        } else {
+            self.downloaders.insert(archive, downloader);
+        }
+
+        self
+    }
+
+    pub fn get(&self, archive: Archive) -> &Downloader {
+        self.downloaders.get(&archive).unwrap_or(&self.wayback)
+    }
+
+    pub fn wayback(&self) -> &Downloader {
+        &self.wayback
+    }
+}
+
+impl From<Downloader> for ArchiveDownloader {
+    fn from(wayback: Downloader) -> Self {
+        Self::new(wayback)
+    }
+}
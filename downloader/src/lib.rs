@@ -1,14 +1,41 @@
-use aib_core::{digest::Sha1Digest, entry::UrlParts, timestamp::Timestamp};
+use aib_core::{
+    archive::Archive,
+    digest::{DigestWriter, Sha1Digest},
+    entry::UrlParts,
+    timestamp::Timestamp,
+};
 use bytes::{Buf, Bytes};
-use futures::future::{BoxFuture, FutureExt};
-use reqwest::{header::LOCATION, redirect, Client, Response, StatusCode};
+use once_cell::sync::Lazy;
+use reqwest::{
+    header::{HeaderMap, LOCATION, RETRY_AFTER},
+    redirect, Client, Response, StatusCode, Url,
+};
+use std::future::Future;
+use std::io::Write;
+use std::sync::Arc;
 use std::time::Duration;
 use thiserror::Error;
+use tokio::{sync::Mutex, time::Instant};
+
+pub mod archive;
+pub mod modifier;
+pub mod save;
+
+pub use archive::ArchiveDownloader;
+pub use modifier::Modifier;
+
+/// The Internet Archive's Wayback Machine.
+pub const DEFAULT_BASE_URL: &str = "http://web.archive.org/web/";
+const ORIGINAL_HEADER_PREFIX: &str = "x-archive-orig-";
+const WAYBACK_PATH_PATTERN: &str = r"^(?P<timestamp>\d{14})(?:[a-z]{2}_)?/(?P<url>.+)$";
 
 const MAX_RETRIES: usize = 7;
-const RETRY_BASE_DURATION_MS: u64 = 60_000;
+const DEFAULT_MAX_REDIRECTS: usize = 10;
+const RETRY_BASE_DURATION: Duration = Duration::from_secs(60);
+const MAX_RETRY_AFTER_DURATION: Duration = Duration::from_secs(300);
 const TCP_KEEPALIVE_DURATION: Duration = Duration::from_secs(20);
 const DEFAULT_REQUEST_TIMEOUT_DURATION: Duration = Duration::from_secs(60);
+const WRITE_CHANNEL_CAPACITY: usize = 16;
 
 #[derive(Error, Debug)]
 pub enum Error {
@@ -24,6 +51,58 @@ pub enum Error {
     UnexpectedStatus(StatusCode),
     #[error("Invalid UTF-8: {0:?}")]
     InvalidUtf8(#[from] std::str::Utf8Error),
+    #[error("Invalid base URL: {0:?}")]
+    InvalidBaseUrl(#[from] url::ParseError),
+    #[error("Invalid scheme: {0:?}")]
+    InvalidScheme(String),
+    #[error("Invalid rate limit: {0} requests per second")]
+    InvalidRateLimit(f64),
+    #[error("Redirect loop: {0}")]
+    RedirectLoop(RedirectChain),
+    #[error("Too many redirects: {0}")]
+    TooManyRedirects(RedirectChain),
+    #[error("Response body exceeds the limit of {limit} bytes")]
+    BodyTooLarge { limit: u64 },
+    #[error("Truncated response body: received {received} of {expected} bytes")]
+    Truncated { expected: u64, received: u64 },
+}
+
+impl Error {
+    /// The redirects that were followed before the error, if it was caused by a redirect chain.
+    pub fn redirect_chain(&self) -> Option<&RedirectChain> {
+        match self {
+            Self::RedirectLoop(chain) | Self::TooManyRedirects(chain) => Some(chain),
+            _ => None,
+        }
+    }
+}
+
+/// A capture and the redirects that were followed from it.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RedirectChain {
+    pub start: UrlParts,
+    pub redirects: Vec<UrlParts>,
+}
+
+impl RedirectChain {
+    fn new(url: &str, timestamp: Timestamp, redirects: Vec<UrlParts>) -> Self {
+        Self {
+            start: UrlParts::new(url.to_string(), timestamp),
+            redirects,
+        }
+    }
+}
+
+impl std::fmt::Display for RedirectChain {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({})", self.start.url, self.start.timestamp)?;
+
+        for hop in &self.redirects {
+            write!(f, " -> {} ({})", hop.url, hop.timestamp)?;
+        }
+
+        Ok(())
+    }
 }
 
 #[derive(Debug, Eq, PartialEq)]
@@ -35,9 +114,52 @@ pub struct RedirectResolution {
     pub valid_digest: bool,
 }
 
+/// Spaces out requests so that they stay under a global rate.
+#[derive(Debug)]
+struct RateLimiter {
+    interval: Duration,
+    next: Mutex<Instant>,
+}
+
+impl RateLimiter {
+    /// Returns `None` if the rate isn't positive and finite, or is too small to represent.
+    fn new(requests_per_second: f64) -> Option<Self> {
+        if requests_per_second > 0.0 && requests_per_second.is_finite() {
+            Duration::try_from_secs_f64(1.0 / requests_per_second)
+                .ok()
+                .map(Self::with_interval)
+        } else {
+            None
+        }
+    }
+
+    fn with_interval(interval: Duration) -> Self {
+        Self {
+            interval,
+            next: Mutex::new(Instant::now()),
+        }
+    }
+
+    async fn wait(&self) {
+        let mut next = self.next.lock().await;
+        let scheduled = (*next).max(Instant::now());
+        *next = scheduled + self.interval;
+        drop(next);
+
+        tokio::time::sleep_until(scheduled).await;
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct Downloader {
     client: Client,
+    base_url: Url,
+    max_redirects: usize,
+    max_body_size: Option<u64>,
+    max_retries: usize,
+    retry_base_delay: Duration,
+    rate_limiter: Option<Arc<RateLimiter>>,
+    use_modifiers: bool,
 }
 
 impl Downloader {
@@ -50,16 +172,148 @@ impl Downloader {
                 .tcp_keepalive(tcp_keepalive)
                 .redirect(redirect::Policy::none())
                 .build()?,
+            // Safe because the default is a valid URL.
+            base_url: DEFAULT_BASE_URL.parse().unwrap(),
+            max_redirects: DEFAULT_MAX_REDIRECTS,
+            max_body_size: None,
+            max_retries: MAX_RETRIES,
+            retry_base_delay: RETRY_BASE_DURATION,
+            rate_limiter: None,
+            use_modifiers: true,
         })
     }
 
-    fn wayback_url(url: &str, timestamp: Timestamp, original: bool) -> String {
-        format!(
-            "http://web.archive.org/web/{}{}/{}",
-            timestamp,
-            if original { "id_" } else { "if_" },
-            url
-        )
+    /// Set the maximum number of redirects followed for a single download.
+    pub fn with_max_redirects(self, max_redirects: usize) -> Self {
+        Self {
+            max_redirects,
+            ..self
+        }
+    }
+
+    /// Set the number of times transient failures are retried, and the delay
+    /// before the first retry (which doubles for each subsequent retry).
+    pub fn with_retries(self, max_retries: usize, base_delay: Duration) -> Self {
+        Self {
+            max_retries,
+            retry_base_delay: base_delay,
+            ..self
+        }
+    }
+
+    /// Fail downloads whose response bodies are larger than the given number of bytes.
+    pub fn with_max_body_size(self, max_body_size: u64) -> Self {
+        Self {
+            max_body_size: Some(max_body_size),
+            ..self
+        }
+    }
+
+    /// Use another archive running Wayback-compatible software (such as pywb).
+    ///
+    /// Captures are requested at `<base>/<timestamp><modifier>/<url>`.
+    pub fn with_base_url(self, base_url: &str) -> Result<Self, Error> {
+        // Ensure that the last path segment is not replaced when joining.
+        let base_url = if base_url.ends_with('/') {
+            base_url.parse()?
+        } else {
+            format!("{}/", base_url).parse()?
+        };
+
+        Ok(Self { base_url, ..self })
+    }
+
+    /// A copy of this downloader for another archive, with its own rate limiter.
+    pub fn for_archive(&self, archive: Archive) -> Self {
+        Self {
+            // Safe because the archive base URLs are valid.
+            base_url: archive.replay_base().parse().unwrap(),
+            rate_limiter: self
+                .rate_limiter
+                .as_ref()
+                .map(|rate_limiter| Arc::new(RateLimiter::with_interval(rate_limiter.interval))),
+            use_modifiers: archive.supports_modifiers(),
+            ..self.clone()
+        }
+    }
+
+    pub fn with_scheme(mut self, scheme: &str) -> Result<Self, Error> {
+        self.base_url
+            .set_scheme(scheme)
+            .map_err(|_| Error::InvalidScheme(scheme.to_string()))?;
+
+        Ok(self)
+    }
+
+    pub fn base_url(&self) -> &Url {
+        &self.base_url
+    }
+
+    /// Limit the number of requests made by this downloader (and its clones).
+    ///
+    /// The rate must be positive and finite.
+    pub fn with_rate_limit(self, requests_per_second: f64) -> Result<Self, Error> {
+        let rate_limiter = RateLimiter::new(requests_per_second)
+            .ok_or(Error::InvalidRateLimit(requests_per_second))?;
+
+        Ok(Self {
+            rate_limiter: Some(Arc::new(rate_limiter)),
+            ..self
+        })
+    }
+
+    async fn get(&self, url: &str) -> reqwest::Result<Response> {
+        if let Some(rate_limiter) = &self.rate_limiter {
+            rate_limiter.wait().await;
+        }
+
+        self.client.get(url).send().await
+    }
+
+    async fn head(&self, url: &str) -> reqwest::Result<Response> {
+        if let Some(rate_limiter) = &self.rate_limiter {
+            rate_limiter.wait().await;
+        }
+
+        self.client.head(url).send().await
+    }
+
+    pub fn wayback_url(&self, url: &str, timestamp: Timestamp, modifier: Modifier) -> String {
+        if self.use_modifiers {
+            format!("{}{}{}/{}", self.base_url, timestamp, modifier, url)
+        } else {
+            format!("{}{}/{}", self.base_url, timestamp, url)
+        }
+    }
+
+    /// Parse a capture URL (possibly relative) for this downloader's archive.
+    pub fn parse_wayback_url(&self, value: &str) -> Result<UrlParts, Error> {
+        static WAYBACK_PATH_RE: Lazy<regex::Regex> =
+            Lazy::new(|| regex::Regex::new(WAYBACK_PATH_PATTERN).unwrap());
+
+        let invalid = || Error::UnexpectedRedirectUrl(value.to_string());
+
+        let path = if value.starts_with('/') {
+            value
+        } else {
+            // The scheme may differ (e.g. when the archive redirects to HTTPS).
+            let (_, rest) = value.split_once("://").ok_or_else(invalid)?;
+            let authority =
+                self.base_url[url::Position::BeforeHost..url::Position::AfterPort].to_string();
+
+            rest.strip_prefix(&authority).ok_or_else(invalid)?
+        };
+
+        let path = path
+            .strip_prefix(self.base_url.path())
+            .ok_or_else(invalid)?;
+
+        let captures = WAYBACK_PATH_RE.captures(path).ok_or_else(invalid)?;
+
+        Ok(UrlParts::new(
+            captures["url"].to_string(),
+            captures["timestamp"].parse().map_err(|_| invalid())?,
+        ))
     }
 
     pub async fn resolve_redirect(
@@ -68,8 +322,8 @@ impl Downloader {
         timestamp: Timestamp,
         expected_digest: Sha1Digest,
     ) -> Result<RedirectResolution, Error> {
-        let initial_url = Self::wayback_url(url, timestamp, true);
-        let initial_response = self.client.head(&initial_url).send().await?;
+        let initial_url = self.wayback_url(url, timestamp, Modifier::Original);
+        let initial_response = self.head(&initial_url).await?;
 
         match initial_response.status() {
             StatusCode::FOUND => {
@@ -80,9 +334,7 @@ impl Downloader {
                     .map(str::to_string)
                 {
                     Some(location) => {
-                        let info = location
-                            .parse::<UrlParts>()
-                            .map_err(|_| Error::UnexpectedRedirectUrl(location))?;
+                        let info = self.parse_wayback_url(&location)?;
 
                         let guess = aib_core::redirect::make_redirect_html(&info.url);
                         let mut guess_bytes = guess.as_bytes();
@@ -95,8 +347,7 @@ impl Downloader {
                             Bytes::from(guess)
                         } else {
                             //log::warn!("Invalid guess, re-requesting");
-                            let direct_bytes =
-                                self.client.get(&initial_url).send().await?.bytes().await?;
+                            let direct_bytes = self.get(&initial_url).await?.bytes().await?;
                             let direct_digest = aib_core::digest::compute_digest(
                                 &mut direct_bytes.clone().reader(),
                             )?;
@@ -110,9 +361,7 @@ impl Downloader {
                             .direct_resolve_redirect(&info.url, info.timestamp)
                             .await?;
 
-                        let actual_info = actual_url
-                            .parse::<UrlParts>()
-                            .map_err(|_| Error::UnexpectedRedirectUrl(actual_url))?;
+                        let actual_info = self.parse_wayback_url(&actual_url)?;
 
                         Ok(RedirectResolution {
                             url: actual_info.url,
@@ -135,9 +384,7 @@ impl Downloader {
         timestamp: Timestamp,
     ) -> Result<String, Error> {
         let response = self
-            .client
-            .head(Self::wayback_url(url, timestamp, true))
-            .send()
+            .head(&self.wayback_url(url, timestamp, Modifier::Original))
             .await?;
 
         match response.status() {
@@ -162,16 +409,14 @@ impl Downloader {
         timestamp: Timestamp,
         expected_digest: Sha1Digest,
     ) -> Result<(UrlParts, String, bool), Error> {
-        let initial_url = Self::wayback_url(url, timestamp, true);
-        let initial_response = self.client.head(&initial_url).send().await?;
+        let initial_url = self.wayback_url(url, timestamp, Modifier::Original);
+        let initial_response = self.head(&initial_url).await?;
 
         match initial_response.status() {
             StatusCode::FOUND => {
                 match redirect_location(&initial_response) {
                     Some(location) => {
-                        let info = location
-                            .parse::<UrlParts>()
-                            .map_err(|_| Error::UnexpectedRedirectUrl(location.to_string()))?;
+                        let info = self.parse_wayback_url(location)?;
 
                         let guess = aib_core::redirect::make_redirect_html(&info.url);
                         let mut guess_bytes = guess.as_bytes();
@@ -181,8 +426,7 @@ impl Downloader {
                             (guess, true)
                         } else {
                             //log::warn!("Invalid guess, re-requesting");
-                            let direct_bytes =
-                                self.client.get(&initial_url).send().await?.bytes().await?;
+                            let direct_bytes = self.get(&initial_url).await?.bytes().await?;
                             let direct_digest = aib_core::digest::compute_digest(
                                 &mut direct_bytes.clone().reader(),
                             )?;
@@ -205,28 +449,85 @@ impl Downloader {
         &'a self,
         url: &'a str,
         timestamp: Timestamp,
-        original: bool,
+        modifier: Modifier,
     ) -> Result<Option<Download>, Error> {
-        let strategy = tokio_retry::strategy::ExponentialBackoff::from_millis(2)
-            .factor(RETRY_BASE_DURATION_MS / 2)
-            .map(tokio_retry::strategy::jitter)
-            .take(MAX_RETRIES);
+        let download = tokio_retry::RetryIf::spawn(
+            self.retry_strategy(),
+            || self.download_once(url, timestamp, modifier),
+            is_transient,
+        )
+        .await;
+
+        match download {
+            Ok(download) => Ok(Some(download)),
+            Err(Error::UnexpectedStatus(StatusCode::NOT_FOUND)) => Ok(None),
+            Err(other) => Err(other),
+        }
+    }
+
+    /// Download a capture into a writer, computing its digest while it is written.
+    ///
+    /// The response body is never held in memory in full. A new writer is
+    /// created for every attempt, so a failed attempt does not leave partial
+    /// content in the writer that is returned.
+    pub async fn download_to<'a, W: Write + Send + 'static, F: FnMut() -> std::io::Result<W>>(
+        &'a self,
+        url: &'a str,
+        timestamp: Timestamp,
+        modifier: Modifier,
+        create_writer: F,
+    ) -> Result<Option<StreamedDownload<W>>, Error> {
+        self.stream_to(url, timestamp, modifier, None, create_writer)
+            .await
+    }
 
-        let mut count = 0;
+    /// Download a capture listed in a Memento TimeMap into a writer.
+    ///
+    /// Archives that support modifiers are asked for the original content, as
+    /// with [`Self::download_to`]. Others are requested at the URI-M, since
+    /// their URI-Ms can't always be built from the URL and timestamp.
+    pub async fn download_memento_to<
+        'a,
+        W: Write + Send + 'static,
+        F: FnMut() -> std::io::Result<W>,
+    >(
+        &'a self,
+        memento_url: &'a str,
+        url: &'a str,
+        timestamp: Timestamp,
+        create_writer: F,
+    ) -> Result<Option<StreamedDownload<W>>, Error> {
+        let memento_url = Some(memento_url).filter(|_| !self.use_modifiers);
 
+        self.stream_to(
+            url,
+            timestamp,
+            Modifier::Original,
+            memento_url,
+            create_writer,
+        )
+        .await
+    }
+
+    async fn stream_to<'a, W: Write + Send + 'static, F: FnMut() -> std::io::Result<W>>(
+        &'a self,
+        url: &'a str,
+        timestamp: Timestamp,
+        modifier: Modifier,
+        memento_url: Option<&'a str>,
+        mut create_writer: F,
+    ) -> Result<Option<StreamedDownload<W>>, Error> {
         let download = tokio_retry::RetryIf::spawn(
-            strategy,
+            self.retry_strategy(),
             || {
-                count += 1;
-                self.download_once(url, timestamp, original)
-            },
-            |error: &_| match error {
-                Error::UnexpectedStatus(StatusCode::TOO_MANY_REQUESTS) => true,
-                Error::UnexpectedStatus(status_code) if status_code.is_server_error() => true,
-                Error::Client(error) if error.is_body() => true,
-                Error::Client(_) => true,
-                _ => false,
+                let writer = create_writer();
+
+                async move {
+                    self.stream_once(url, timestamp, modifier, memento_url, writer?)
+                        .await
+                }
             },
+            is_transient,
         )
         .await;
 
@@ -237,44 +538,206 @@ impl Downloader {
         }
     }
 
-    fn download_once<'a>(
-        &'a self,
-        url: &'a str,
+    async fn download_once(
+        &self,
+        url: &str,
+        timestamp: Timestamp,
+        modifier: Modifier,
+    ) -> Result<Download, Error> {
+        let (response, redirects) = self.follow(url, timestamp, modifier, None).await?;
+        let headers = OriginalHeaders::from_headers(response.headers());
+        let mut bytes = Vec::with_capacity(response.content_length().unwrap_or(0) as usize);
+
+        self.read_body(response, |chunk| {
+            bytes.extend_from_slice(&chunk);
+
+            std::future::ready(true)
+        })
+        .await?;
+
+        Ok(Download {
+            bytes: bytes.into(),
+            redirects,
+            headers,
+        })
+    }
+
+    /// Stream a response body into a writer.
+    ///
+    /// Writers may compress or write to disk, so the writing is done on a
+    /// blocking thread that receives the chunks over a bounded channel.
+    async fn stream_once<W: Write + Send + 'static>(
+        &self,
+        url: &str,
+        timestamp: Timestamp,
+        modifier: Modifier,
+        memento_url: Option<&str>,
+        writer: W,
+    ) -> Result<StreamedDownload<W>, Error> {
+        let (response, redirects) = self.follow(url, timestamp, modifier, memento_url).await?;
+        let headers = OriginalHeaders::from_headers(response.headers());
+        let (sender, mut receiver) = tokio::sync::mpsc::channel::<Bytes>(WRITE_CHANNEL_CAPACITY);
+
+        let writing = tokio::task::spawn_blocking(move || {
+            let mut writer = DigestWriter::new(writer);
+
+            while let Some(chunk) = receiver.blocking_recv() {
+                writer.write_all(&chunk)?;
+            }
+
+            writer.finish()
+        });
+
+        let read = self
+            .read_body(response, |chunk| {
+                let sender = sender.clone();
+
+                async move { sender.send(chunk).await.is_ok() }
+            })
+            .await;
+
+        // Closing the channel lets the writer finish.
+        drop(sender);
+
+        // If writing failed, reading will have stopped early because of it.
+        let (writer, digest, length) = writing.await.map_err(std::io::Error::other)??;
+
+        read?;
+
+        Ok(StreamedDownload {
+            writer,
+            digest,
+            length,
+            redirects,
+            headers,
+        })
+    }
+
+    fn retry_strategy(&self) -> impl Iterator<Item = Duration> {
+        tokio_retry::strategy::ExponentialBackoff::from_millis(2)
+            .factor(self.retry_base_delay.as_millis() as u64 / 2)
+            .map(tokio_retry::strategy::jitter)
+            .take(self.max_retries)
+    }
+
+    /// Request a capture, following redirects until a successful response.
+    ///
+    /// If a URI-M is given, it is used for the first request instead of a URL
+    /// built from the URL and timestamp.
+    async fn follow(
+        &self,
+        url: &str,
         timestamp: Timestamp,
-        original: bool,
-    ) -> BoxFuture<'a, Result<Download, Error>> {
-        async move {
-            let response = self
-                .client
-                .get(Self::wayback_url(url, timestamp, original))
-                .send()
-                .await?;
+        modifier: Modifier,
+        memento_url: Option<&str>,
+    ) -> Result<(Response, Vec<UrlParts>), Error> {
+        let start = UrlParts::new(url.to_string(), timestamp);
+        let mut current = start.clone();
+        let mut redirects: Vec<UrlParts> = vec![];
+
+        loop {
+            let request_url = match memento_url {
+                Some(memento_url) if redirects.is_empty() => memento_url.to_string(),
+                _ => self.wayback_url(&current.url, current.timestamp, modifier),
+            };
+            let response = self.get(&request_url).await?;
 
             match response.status() {
-                StatusCode::OK => Ok(Download {
-                    bytes: response.bytes().await?,
-                    redirects: vec![],
-                }),
-                StatusCode::FOUND => match redirect_location(&response) {
-                    Some(location) => {
-                        let url_parts = location
-                            .parse::<UrlParts>()
-                            .map_err(|_| Error::UnexpectedRedirectUrl(location.to_string()))?;
+                StatusCode::OK => return Ok((response, redirects)),
+                StatusCode::FOUND => {
+                    let location =
+                        redirect_location(&response).ok_or(Error::UnexpectedRedirect(None))?;
+                    let next = self.parse_wayback_url(location)?;
+
+                    let is_loop = next == start || redirects.contains(&next);
+
+                    redirects.push(next.clone());
+
+                    if is_loop {
+                        return Err(Error::RedirectLoop(RedirectChain::new(
+                            url, timestamp, redirects,
+                        )));
+                    } else if redirects.len() > self.max_redirects {
+                        return Err(Error::TooManyRedirects(RedirectChain::new(
+                            url, timestamp, redirects,
+                        )));
+                    }
 
-                        let mut result = self
-                            .download_once(&url_parts.url, url_parts.timestamp, original)
-                            .await?;
+                    current = next;
+                }
+                StatusCode::TOO_MANY_REQUESTS => {
+                    // Respect the server's requested delay (within reason) before the retry backoff.
+                    if let Some(retry_after) = retry_after(&response) {
+                        tokio::time::sleep(retry_after.min(MAX_RETRY_AFTER_DURATION)).await;
+                    }
 
-                        result.redirects.push(url_parts);
+                    return Err(Error::UnexpectedStatus(StatusCode::TOO_MANY_REQUESTS));
+                }
+                other => return Err(Error::UnexpectedStatus(other)),
+            }
+        }
+    }
+
+    /// Pass a response body to a consumer chunk by chunk, enforcing the size
+    /// limit and checking the length against the `Content-Length` header.
+    ///
+    /// Reading stops early if the consumer returns `false`.
+    async fn read_body<F: FnMut(Bytes) -> Fut, Fut: Future<Output = bool>>(
+        &self,
+        mut response: Response,
+        mut consume: F,
+    ) -> Result<u64, Error> {
+        let expected = response.content_length();
+
+        if let (Some(limit), Some(expected)) = (self.max_body_size, expected) {
+            if expected > limit {
+                return Err(Error::BodyTooLarge { limit });
+            }
+        }
+
+        let mut received = 0;
 
-                        Ok(result)
+        loop {
+            match response.chunk().await {
+                Ok(Some(chunk)) => {
+                    received += chunk.len() as u64;
+
+                    if let Some(limit) = self.max_body_size {
+                        if received > limit {
+                            return Err(Error::BodyTooLarge { limit });
+                        }
                     }
-                    None => Err(Error::UnexpectedRedirect(None)),
-                },
-                other => Err(Error::UnexpectedStatus(other)),
+
+                    if !consume(chunk).await {
+                        break;
+                    }
+                }
+                Ok(None) => break,
+                Err(error) => {
+                    return Err(match expected {
+                        Some(expected) if error.is_body() || error.is_decode() => {
+                            Error::Truncated { expected, received }
+                        }
+                        _ => error.into(),
+                    });
+                }
             }
         }
-        .boxed()
+
+        match expected {
+            Some(expected) if received < expected => Err(Error::Truncated { expected, received }),
+            _ => Ok(received),
+        }
+    }
+}
+
+fn is_transient(error: &Error) -> bool {
+    match error {
+        Error::UnexpectedStatus(StatusCode::TOO_MANY_REQUESTS) => true,
+        Error::UnexpectedStatus(status_code) if status_code.is_server_error() => true,
+        Error::Client(_) => true,
+        Error::Truncated { .. } => true,
+        _ => false,
     }
 }
 
@@ -287,7 +750,67 @@ impl Default for Downloader {
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct Download {
     pub bytes: Bytes,
+    /// The redirects that were followed, in order.
+    pub redirects: Vec<UrlParts>,
+    pub headers: OriginalHeaders,
+}
+
+/// A download that was written to a writer instead of being held in memory.
+#[derive(Debug)]
+pub struct StreamedDownload<W> {
+    pub writer: W,
+    pub digest: Sha1Digest,
+    /// The length of the response body.
+    pub length: u64,
+    /// The redirects that were followed, in order.
     pub redirects: Vec<UrlParts>,
+    pub headers: OriginalHeaders,
+}
+
+/// Headers of the archived response, as returned in `x-archive-orig-*` headers.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct OriginalHeaders {
+    pub content_type: Option<String>,
+    pub date: Option<String>,
+    pub last_modified: Option<String>,
+    pub server: Option<String>,
+    pub set_cookie: bool,
+}
+
+impl OriginalHeaders {
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        let get = |name: &str| {
+            headers
+                .get(format!("{}{}", ORIGINAL_HEADER_PREFIX, name))
+                .and_then(|value| value.to_str().ok())
+                .map(|value| value.trim().to_string())
+        };
+
+        Self {
+            content_type: get("content-type"),
+            date: get("date"),
+            last_modified: get("last-modified"),
+            server: get("server"),
+            set_cookie: headers.contains_key(format!("{}set-cookie", ORIGINAL_HEADER_PREFIX)),
+        }
+    }
+
+    /// The charset parameter of the original content type, if any.
+    pub fn charset(&self) -> Option<String> {
+        self.content_type.as_ref().and_then(|content_type| {
+            content_type.split(';').skip(1).find_map(|parameter| {
+                let (name, value) = parameter.split_once('=')?;
+
+                name.trim()
+                    .eq_ignore_ascii_case("charset")
+                    .then(|| value.trim().trim_matches('"').to_ascii_lowercase())
+            })
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self == &Self::default()
+    }
 }
 
 fn redirect_location(response: &Response) -> Option<&str> {
@@ -296,3 +819,117 @@ fn redirect_location(response: &Response) -> Option<&str> {
         .get(LOCATION)
         .and_then(|value| value.to_str().ok())
 }
+
+/// The delay requested by a `Retry-After` header, if it is given in seconds.
+fn retry_after(response: &Response) -> Option<Duration> {
+    response
+        .headers()
+        .get(RETRY_AFTER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().parse::<u64>().ok())
+        .map(Duration::from_secs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wayback_urls() {
+        let timestamp: Timestamp = "20160508215503".parse().unwrap();
+        let url = "https://twitter.com/foo/status/725877225686454272";
+        let default = Downloader::default();
+        let local = Downloader::default()
+            .with_base_url("http://localhost:8080/pywb/")
+            .unwrap();
+        let https = Downloader::default().with_scheme("https").unwrap();
+
+        assert_eq!(
+            default.wayback_url(url, timestamp, Modifier::Original),
+            format!("http://web.archive.org/web/20160508215503id_/{}", url)
+        );
+        assert_eq!(
+            local.wayback_url(url, timestamp, Modifier::Css),
+            format!("http://localhost:8080/pywb/20160508215503cs_/{}", url)
+        );
+        assert_eq!(
+            default
+                .for_archive(Archive::ArquivoPt)
+                .wayback_url(url, timestamp, Modifier::Original),
+            format!("https://arquivo.pt/wayback/20160508215503id_/{}", url)
+        );
+        assert_eq!(
+            default.for_archive(Archive::ArchiveToday).wayback_url(
+                url,
+                timestamp,
+                Modifier::Original
+            ),
+            format!("https://archive.ph/20160508215503/{}", url)
+        );
+
+        let expected = UrlParts::new(url.to_string(), timestamp);
+
+        for location in [
+            format!("https://web.archive.org/web/20160508215503/{}", url),
+            format!("http://web.archive.org/web/20160508215503id_/{}", url),
+            format!("/web/20160508215503if_/{}", url),
+        ] {
+            assert_eq!(default.parse_wayback_url(&location).unwrap(), expected);
+            assert_eq!(https.parse_wayback_url(&location).unwrap(), expected);
+        }
+
+        assert_eq!(
+            local
+                .parse_wayback_url(&format!("/pywb/20160508215503im_/{}", url))
+                .unwrap(),
+            expected
+        );
+        assert!(local
+            .parse_wayback_url(&format!("/web/20160508215503/{}", url))
+            .is_err());
+    }
+
+    #[test]
+    fn original_headers() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "x-archive-orig-content-type",
+            "text/html; charset=\"ISO-8859-1\"".parse().unwrap(),
+        );
+        headers.insert(
+            "x-archive-orig-date",
+            "Sun, 08 May 2016 21:55:03 GMT".parse().unwrap(),
+        );
+        headers.insert("x-archive-orig-set-cookie", "a=b".parse().unwrap());
+        headers.insert("server", "nginx".parse().unwrap());
+
+        let original = OriginalHeaders::from_headers(&headers);
+
+        assert_eq!(original.charset(), Some("iso-8859-1".to_string()));
+        assert_eq!(
+            original.date.as_deref(),
+            Some("Sun, 08 May 2016 21:55:03 GMT")
+        );
+        assert_eq!(original.server, None);
+        assert!(original.set_cookie);
+        assert!(OriginalHeaders::from_headers(&HeaderMap::new()).is_empty());
+    }
+
+    #[test]
+    fn redirect_chain_display() {
+        let timestamp: Timestamp = "20160508215503".parse().unwrap();
+        let chain = RedirectChain::new(
+            "http://a.com/",
+            timestamp,
+            vec![
+                UrlParts::new("http://b.com/".to_string(), timestamp),
+                UrlParts::new("http://a.com/".to_string(), timestamp),
+            ],
+        );
+
+        assert_eq!(
+            Error::RedirectLoop(chain).to_string(),
+            "Redirect loop: http://a.com/ (20160508215503) -> http://b.com/ (20160508215503) -> http://a.com/ (20160508215503)"
+        );
+    }
+}
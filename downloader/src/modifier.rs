@@ -0,0 +1,58 @@
+use std::fmt::Display;
+use std::str::FromStr;
+
+#[derive(thiserror::Error, Debug)]
+#[error("Invalid modifier: {0}")]
+pub struct Error(String);
+
+/// A replay modifier, which determines how the archive serves a capture.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Modifier {
+    /// The original content, without any rewriting (`id_`).
+    Original,
+    /// Rewritten content without the archive's banner (`if_`).
+    Iframe,
+    /// An image (`im_`).
+    Image,
+    /// A script (`js_`).
+    JavaScript,
+    /// A stylesheet (`cs_`).
+    Css,
+}
+
+impl Modifier {
+    pub const ALL: [Self; 5] = [
+        Self::Original,
+        Self::Iframe,
+        Self::Image,
+        Self::JavaScript,
+        Self::Css,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Original => "id_",
+            Self::Iframe => "if_",
+            Self::Image => "im_",
+            Self::JavaScript => "js_",
+            Self::Css => "cs_",
+        }
+    }
+}
+
+impl Display for Modifier {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Modifier {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|modifier| modifier.as_str() == s)
+            .ok_or_else(|| Error(s.to_string()))
+    }
+}
@@ -0,0 +1,287 @@
+//! A client for the Wayback Machine's Save Page Now API.
+//!
+//! A capture is requested by submitting a job, whose status is then polled
+//! until the archive reports that it has succeeded or failed. Requests are
+//! authenticated with Internet Archive S3-style keys when they are provided.
+
+use aib_core::timestamp::Timestamp;
+use reqwest::{header::AUTHORIZATION, Client, StatusCode, Url};
+use serde::Deserialize;
+use std::time::Duration;
+use thiserror::Error;
+
+/// The Internet Archive's Save Page Now endpoint.
+pub const DEFAULT_BASE_URL: &str = "https://web.archive.org/save/";
+
+const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(5);
+const DEFAULT_MAX_POLLS: usize = 120;
+const DEFAULT_REQUEST_TIMEOUT_DURATION: Duration = Duration::from_secs(60);
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("HTTP client error: {0:?}")]
+    Client(#[from] reqwest::Error),
+    #[error("Invalid base URL: {0:?}")]
+    InvalidBaseUrl(#[from] url::ParseError),
+    #[error("Unexpected status code: {0:?}")]
+    UnexpectedStatus(StatusCode),
+    #[error("Submission rejected: {message}")]
+    Rejected {
+        status_ext: Option<String>,
+        message: String,
+    },
+    #[error("Capture failed: {message}")]
+    Failed {
+        job_id: String,
+        status_ext: Option<String>,
+        message: String,
+    },
+    #[error("Capture job did not complete: {job_id}")]
+    Timeout { job_id: String },
+    #[error("Invalid job status: {0}")]
+    InvalidStatus(String),
+}
+
+impl Error {
+    /// The archive's machine-readable error code (e.g. `error:too-many-daily-captures`).
+    pub fn status_ext(&self) -> Option<&str> {
+        match self {
+            Self::Rejected { status_ext, .. } | Self::Failed { status_ext, .. } => {
+                status_ext.as_deref()
+            }
+            _ => None,
+        }
+    }
+
+    /// Whether the request may succeed if it is repeated.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            Self::Client(_) => true,
+            Self::UnexpectedStatus(status) => {
+                status.is_server_error() || *status == StatusCode::TOO_MANY_REQUESTS
+            }
+            _ => false,
+        }
+    }
+
+    /// The ID of the job, if the error occurred after it was submitted.
+    pub fn job_id(&self) -> Option<&str> {
+        match self {
+            Self::Failed { job_id, .. } | Self::Timeout { job_id } => Some(job_id),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Credentials {
+    pub access_key: String,
+    pub secret_key: String,
+}
+
+impl Credentials {
+    pub fn new(access_key: &str, secret_key: &str) -> Self {
+        Self {
+            access_key: access_key.to_string(),
+            secret_key: secret_key.to_string(),
+        }
+    }
+
+    fn header_value(&self) -> String {
+        format!("LOW {}:{}", self.access_key, self.secret_key)
+    }
+}
+
+/// A completed capture.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Capture {
+    pub job_id: String,
+    pub url: String,
+    pub timestamp: Timestamp,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum JobStatus {
+    Pending,
+    Success(Capture),
+    Error {
+        status_ext: Option<String>,
+        message: String,
+    },
+}
+
+#[derive(Deserialize)]
+struct SubmitResponse {
+    job_id: Option<String>,
+    status_ext: Option<String>,
+    message: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct StatusResponse {
+    status: String,
+    job_id: Option<String>,
+    original_url: Option<String>,
+    timestamp: Option<Timestamp>,
+    status_ext: Option<String>,
+    message: Option<String>,
+}
+
+#[derive(Clone, Debug)]
+pub struct SaveClient {
+    client: Client,
+    base_url: Url,
+    credentials: Option<Credentials>,
+    poll_interval: Duration,
+    max_polls: usize,
+}
+
+impl SaveClient {
+    pub fn new(request_timeout: Duration) -> reqwest::Result<Self> {
+        Ok(Self {
+            client: Client::builder().timeout(request_timeout).build()?,
+            // Safe because the default is a valid URL.
+            base_url: DEFAULT_BASE_URL.parse().unwrap(),
+            credentials: None,
+            poll_interval: DEFAULT_POLL_INTERVAL,
+            max_polls: DEFAULT_MAX_POLLS,
+        })
+    }
+
+    /// Use another Save Page Now endpoint.
+    ///
+    /// Jobs are submitted to `<base>` and their status is read from `<base>/status/<job_id>`.
+    pub fn with_base_url(self, base_url: &str) -> Result<Self, Error> {
+        // Ensure that the last path segment is not replaced when joining.
+        let base_url = if base_url.ends_with('/') {
+            base_url.parse()?
+        } else {
+            format!("{}/", base_url).parse()?
+        };
+
+        Ok(Self { base_url, ..self })
+    }
+
+    pub fn with_credentials(self, credentials: Credentials) -> Self {
+        Self {
+            credentials: Some(credentials),
+            ..self
+        }
+    }
+
+    /// Set the delay between status requests, and the number of requests made
+    /// before a job is considered to have timed out.
+    pub fn with_polling(self, poll_interval: Duration, max_polls: usize) -> Self {
+        Self {
+            poll_interval,
+            max_polls,
+            ..self
+        }
+    }
+
+    /// Submit a capture job for a URL, returning the job ID.
+    pub async fn submit(&self, url: &str) -> Result<String, Error> {
+        let request = self
+            .client
+            .post(self.base_url.clone())
+            .header("Accept", "application/json")
+            .form(&[("url", url)]);
+
+        let response = self.authorize(request).send().await?;
+
+        match response.status() {
+            StatusCode::OK => {
+                let response = response.json::<SubmitResponse>().await?;
+
+                response.job_id.ok_or_else(|| Error::Rejected {
+                    status_ext: response.status_ext,
+                    message: response.message.unwrap_or_default(),
+                })
+            }
+            other => Err(Error::UnexpectedStatus(other)),
+        }
+    }
+
+    pub async fn status(&self, job_id: &str) -> Result<JobStatus, Error> {
+        let url = self.base_url.join("status/")?.join(job_id)?;
+        let response = self
+            .authorize(self.client.get(url).header("Accept", "application/json"))
+            .send()
+            .await?;
+
+        match response.status() {
+            StatusCode::OK => {
+                let response = response.json::<StatusResponse>().await?;
+
+                match response.status.as_str() {
+                    "pending" => Ok(JobStatus::Pending),
+                    "success" => match (response.original_url, response.timestamp) {
+                        (Some(url), Some(timestamp)) => Ok(JobStatus::Success(Capture {
+                            job_id: response.job_id.unwrap_or_else(|| job_id.to_string()),
+                            url,
+                            timestamp,
+                        })),
+                        _ => Err(Error::InvalidStatus(response.status)),
+                    },
+                    "error" => Ok(JobStatus::Error {
+                        status_ext: response.status_ext,
+                        message: response.message.unwrap_or_default(),
+                    }),
+                    _ => Err(Error::InvalidStatus(response.status)),
+                }
+            }
+            other => Err(Error::UnexpectedStatus(other)),
+        }
+    }
+
+    /// Submit a capture job for a URL and wait for it to complete.
+    ///
+    /// Transient errors while checking the job's status are logged and count as
+    /// a poll, since the job may still complete.
+    pub async fn save(&self, url: &str) -> Result<Capture, Error> {
+        let job_id = self.submit(url).await?;
+
+        for _ in 0..self.max_polls {
+            tokio::time::sleep(self.poll_interval).await;
+
+            let status = match self.status(&job_id).await {
+                Ok(status) => status,
+                Err(error) if error.is_transient() => {
+                    log::warn!("Failed to check status of job {}: {}", job_id, error);
+                    continue;
+                }
+                Err(error) => return Err(error),
+            };
+
+            match status {
+                JobStatus::Pending => {}
+                JobStatus::Success(capture) => return Ok(capture),
+                JobStatus::Error {
+                    status_ext,
+                    message,
+                } => {
+                    return Err(Error::Failed {
+                        job_id,
+                        status_ext,
+                        message,
+                    })
+                }
+            }
+        }
+
+        Err(Error::Timeout { job_id })
+    }
+
+    fn authorize(&self, request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        match &self.credentials {
+            Some(credentials) => request.header(AUTHORIZATION, credentials.header_value()),
+            None => request,
+        }
+    }
+}
+
+impl Default for SaveClient {
+    fn default() -> Self {
+        Self::new(DEFAULT_REQUEST_TIMEOUT_DURATION).unwrap()
+    }
+}
@@ -0,0 +1,325 @@
+use aib_core::{digest::compute_digest, entry::UrlParts, timestamp::Timestamp};
+use aib_downloader::{Downloader, Error, Modifier};
+use aib_mock_wayback::MockWayback;
+use std::time::{Duration, Instant};
+
+const TIMESTAMP: &str = "20200101000000";
+
+fn timestamp(value: &str) -> Timestamp {
+    value.parse().unwrap()
+}
+
+fn downloader(server: &MockWayback) -> Downloader {
+    Downloader::new(Duration::from_secs(1))
+        .unwrap()
+        .with_base_url(&server.base_url())
+        .unwrap()
+        .with_retries(2, Duration::from_millis(10))
+}
+
+#[tokio::test]
+async fn download_original_and_iframe() {
+    let server = MockWayback::start_bundled().await.unwrap();
+    let downloader = downloader(&server);
+
+    let original = downloader
+        .download(
+            "https://example.com/",
+            timestamp(TIMESTAMP),
+            Modifier::Original,
+        )
+        .await
+        .unwrap()
+        .unwrap();
+
+    assert_eq!(
+        original.bytes.as_ref(),
+        include_bytes!("../../mock-wayback/fixtures/captures/example.html")
+    );
+    assert!(original.redirects.is_empty());
+    assert_eq!(original.headers.charset(), Some("utf-8".to_string()));
+    assert_eq!(original.headers.server.as_deref(), Some("ECS (nyb/1D2E)"));
+
+    let iframe = downloader
+        .download(
+            "https://example.com/",
+            timestamp(TIMESTAMP),
+            Modifier::Iframe,
+        )
+        .await
+        .unwrap()
+        .unwrap();
+
+    assert_eq!(
+        iframe.bytes.as_ref(),
+        include_bytes!("../../mock-wayback/fixtures/captures/example.if_.html")
+    );
+}
+
+#[tokio::test]
+async fn download_missing_and_blocked() {
+    let server = MockWayback::start_bundled().await.unwrap();
+    let downloader = downloader(&server);
+
+    assert_eq!(
+        downloader
+            .download(
+                "https://example.com/missing",
+                timestamp(TIMESTAMP),
+                Modifier::Original
+            )
+            .await
+            .unwrap(),
+        None
+    );
+    assert!(matches!(
+        downloader
+            .download(
+                "https://example.com/blocked",
+                timestamp(TIMESTAMP),
+                Modifier::Original
+            )
+            .await,
+        Err(Error::UnexpectedStatus(status_code)) if status_code.as_u16() == 403
+    ));
+}
+
+#[tokio::test]
+async fn download_follows_redirects() {
+    let server = MockWayback::start_bundled().await.unwrap();
+    let downloader = downloader(&server);
+
+    let download = downloader
+        .download(
+            "https://example.com/old",
+            timestamp(TIMESTAMP),
+            Modifier::Original,
+        )
+        .await
+        .unwrap()
+        .unwrap();
+
+    assert_eq!(
+        download.redirects,
+        vec![
+            UrlParts::new(
+                "https://example.com/new".to_string(),
+                timestamp("20200101000010")
+            ),
+            UrlParts::new("https://example.com/".to_string(), timestamp(TIMESTAMP)),
+        ]
+    );
+    assert_eq!(
+        download.bytes.as_ref(),
+        include_bytes!("../../mock-wayback/fixtures/captures/example.html")
+    );
+
+    let result = downloader
+        .with_max_redirects(1)
+        .download(
+            "https://example.com/old",
+            timestamp(TIMESTAMP),
+            Modifier::Original,
+        )
+        .await;
+
+    assert!(matches!(result, Err(Error::TooManyRedirects(_))));
+}
+
+#[tokio::test]
+async fn download_detects_redirect_loops() {
+    let server = MockWayback::start_bundled().await.unwrap();
+    let downloader = downloader(&server);
+
+    let error = downloader
+        .download(
+            "https://example.com/loop-a",
+            timestamp(TIMESTAMP),
+            Modifier::Original,
+        )
+        .await
+        .unwrap_err();
+
+    assert!(matches!(error, Error::RedirectLoop(_)));
+    assert_eq!(
+        error.redirect_chain().unwrap().redirects,
+        vec![
+            UrlParts::new(
+                "https://example.com/loop-b".to_string(),
+                timestamp(TIMESTAMP)
+            ),
+            UrlParts::new(
+                "https://example.com/loop-a".to_string(),
+                timestamp(TIMESTAMP)
+            ),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn download_retries_transient_failures() {
+    let server = MockWayback::start_bundled().await.unwrap();
+    let downloader = downloader(&server);
+    let started = Instant::now();
+
+    let download = downloader
+        .download(
+            "https://example.com/rate-limited",
+            timestamp(TIMESTAMP),
+            Modifier::Original,
+        )
+        .await
+        .unwrap();
+
+    assert!(download.is_some());
+    // The server asks for a one second delay after rate limiting.
+    assert!(started.elapsed() >= Duration::from_secs(1));
+
+    let download = downloader
+        .download(
+            "https://example.com/unavailable",
+            timestamp(TIMESTAMP),
+            Modifier::Original,
+        )
+        .await
+        .unwrap();
+
+    assert!(download.is_some());
+    assert_eq!(server.requests().len(), 5);
+}
+
+#[tokio::test]
+async fn download_times_out() {
+    let server = MockWayback::start_bundled().await.unwrap();
+    let downloader = downloader(&server).with_retries(0, Duration::ZERO);
+
+    let result = downloader
+        .download(
+            "https://example.com/slow",
+            timestamp(TIMESTAMP),
+            Modifier::Original,
+        )
+        .await;
+
+    assert!(matches!(result, Err(Error::Client(error)) if error.is_timeout()));
+}
+
+#[tokio::test]
+async fn download_to_writer() {
+    let server = MockWayback::start_bundled().await.unwrap();
+    let downloader = downloader(&server);
+    let expected = include_bytes!("../../mock-wayback/fixtures/captures/example.html");
+
+    let download = downloader
+        .download_to(
+            "https://example.com/",
+            timestamp(TIMESTAMP),
+            Modifier::Original,
+            || Ok(vec![]),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+
+    assert_eq!(download.writer, expected);
+    assert_eq!(download.length, expected.len() as u64);
+    assert_eq!(
+        download.digest,
+        compute_digest(&mut expected.as_slice()).unwrap()
+    );
+
+    let too_large = downloader
+        .clone()
+        .with_max_body_size(100)
+        .download_to(
+            "https://example.com/",
+            timestamp(TIMESTAMP),
+            Modifier::Original,
+            || Ok(vec![]),
+        )
+        .await;
+
+    assert!(matches!(too_large, Err(Error::BodyTooLarge { limit: 100 })));
+
+    let truncated = downloader
+        .download_to(
+            "https://example.com/truncated",
+            timestamp(TIMESTAMP),
+            Modifier::Original,
+            || Ok(vec![]),
+        )
+        .await;
+
+    assert!(matches!(
+        truncated,
+        Err(Error::Truncated { expected: length, received: 64 }) if length == expected.len() as u64
+    ));
+}
+
+struct FailingWriter;
+
+impl std::io::Write for FailingWriter {
+    fn write(&mut self, _: &[u8]) -> std::io::Result<usize> {
+        Err(std::io::Error::other("disk full"))
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn download_to_failing_writer() {
+    let server = MockWayback::start_bundled().await.unwrap();
+    let downloader = downloader(&server);
+
+    let result = downloader
+        .download_to(
+            "https://example.com/",
+            timestamp(TIMESTAMP),
+            Modifier::Original,
+            || Ok(FailingWriter),
+        )
+        .await;
+
+    assert!(matches!(result, Err(Error::Io(_))));
+}
+
+#[tokio::test]
+async fn resolve_redirect() {
+    let server = MockWayback::start_bundled().await.unwrap();
+    let downloader = downloader(&server);
+    let content = include_str!("../../mock-wayback/fixtures/captures/old.html");
+    let digest = compute_digest(&mut content.as_bytes()).unwrap();
+
+    let resolution = downloader
+        .resolve_redirect("https://example.com/old", timestamp(TIMESTAMP), digest)
+        .await
+        .unwrap();
+
+    assert_eq!(resolution.url, "https://example.com/");
+    assert_eq!(resolution.timestamp, timestamp(TIMESTAMP));
+    assert_eq!(resolution.content.as_ref(), content.as_bytes());
+    assert!(resolution.valid_initial_content);
+    assert!(resolution.valid_digest);
+
+    let (info, _, valid_digest) = downloader
+        .resolve_redirect_shallow("https://example.com/old", timestamp(TIMESTAMP), digest)
+        .await
+        .unwrap();
+
+    assert_eq!(info.url, "https://example.com/new");
+    assert!(valid_digest);
+}
+
+#[test]
+fn invalid_rate_limits() {
+    for rate in [0.0, -1.0, f64::NAN, f64::INFINITY, f64::MIN_POSITIVE] {
+        assert!(matches!(
+            Downloader::default().with_rate_limit(rate),
+            Err(Error::InvalidRateLimit(_))
+        ));
+    }
+
+    assert!(Downloader::default().with_rate_limit(0.5).is_ok());
+}
@@ -0,0 +1,85 @@
+use aib_downloader::save::{Error, JobStatus, SaveClient};
+use aib_mock_wayback::MockWayback;
+use std::time::Duration;
+
+fn client(server: &MockWayback) -> SaveClient {
+    SaveClient::new(Duration::from_secs(1))
+        .unwrap()
+        .with_base_url(&server.save_base())
+        .unwrap()
+        .with_polling(Duration::from_millis(10), 5)
+}
+
+#[tokio::test]
+async fn save_after_polling() {
+    let server = MockWayback::start_bundled().await.unwrap();
+    let client = client(&server);
+
+    let capture = client.save("https://example.com/new").await.unwrap();
+
+    assert_eq!(capture.url, "https://example.com/new");
+    assert_eq!(capture.timestamp.to_string(), "20240601120000");
+
+    let requests = server.requests();
+    assert_eq!(requests[0].method, "POST");
+    assert_eq!(
+        requests
+            .iter()
+            .filter(|request| request.target.starts_with("/save/status/"))
+            .count(),
+        3
+    );
+}
+
+#[tokio::test]
+async fn save_after_status_errors() {
+    let server = MockWayback::start_bundled().await.unwrap();
+    let client = client(&server);
+
+    let capture = client.save("https://example.com/flaky").await.unwrap();
+
+    assert_eq!(capture.url, "https://example.com/flaky");
+    assert_eq!(capture.timestamp.to_string(), "20240601130000");
+}
+
+#[tokio::test]
+async fn job_status() {
+    let server = MockWayback::start_bundled().await.unwrap();
+    let client = client(&server);
+
+    let job_id = client.submit("https://example.com/new").await.unwrap();
+
+    assert_eq!(client.status(&job_id).await.unwrap(), JobStatus::Pending);
+    assert!(matches!(
+        client.status("unknown").await,
+        Err(Error::UnexpectedStatus(status)) if status.as_u16() == 404
+    ));
+}
+
+#[tokio::test]
+async fn save_failures() {
+    let server = MockWayback::start_bundled().await.unwrap();
+    let client = client(&server);
+
+    let failed = client
+        .save("https://example.com/blocked")
+        .await
+        .unwrap_err();
+    assert!(matches!(failed, Error::Failed { .. }));
+    assert_eq!(failed.status_ext(), Some("error:blocked-url"));
+
+    let rejected = client
+        .save("https://example.com/limited")
+        .await
+        .unwrap_err();
+    assert!(matches!(rejected, Error::Rejected { .. }));
+    assert_eq!(rejected.status_ext(), Some("error:too-many-daily-captures"));
+
+    let timed_out = client
+        .clone()
+        .with_polling(Duration::from_millis(10), 1)
+        .save("https://example.com/new")
+        .await
+        .unwrap_err();
+    assert!(matches!(timed_out, Error::Timeout { .. }));
+}
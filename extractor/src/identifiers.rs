@@ -0,0 +1,404 @@
+//! Identity and contact identifiers: email addresses, social handles and profile URLs, Libravatar
+//! hashes and PGP fingerprints.
+//!
+//! Identifiers are normalized (e.g. lowercased, with spaces removed from fingerprints) and written
+//! as `<kind>:<value>` (e.g. `email:jack@example.com` or `twitter:jack`), so that they can be
+//! matched exactly.
+
+use crate::Metadata;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use scraper::{Html, Selector};
+use std::collections::BTreeSet;
+use std::fmt::Display;
+use std::str::FromStr;
+use url::Url;
+
+static HREF_SEL: Lazy<Selector> = Lazy::new(|| Selector::parse(r#"a[href], link[href]"#).unwrap());
+static LIBRAVATAR_IMG_SEL: Lazy<Selector> =
+    Lazy::new(|| Selector::parse(r#"img[src *= "libravatar.org"]"#).unwrap());
+
+static EMAIL_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)\b[a-z0-9][a-z0-9._%+-]*@(?:[a-z0-9](?:[a-z0-9-]*[a-z0-9])?\.)+([a-z]{2,})\b")
+        .unwrap()
+});
+static HANDLE_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?:^|[^\w@./])@([A-Za-z0-9_]{2,30})\b").unwrap());
+static PGP_FINGERPRINT_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"\b[0-9A-Fa-f]{4}(?: {1,2}[0-9A-Fa-f]{4}){9}\b").unwrap());
+static PGP_FINGERPRINT_HREF_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)^(?:openpgp4fpr:|https?://[^/]+/vks/v1/by-fingerprint/)([0-9a-f]{40})$")
+        .unwrap()
+});
+static LIBRAVATAR_SRC_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"libravatar\.org/avatar/([0-9a-f]{64}|[0-9a-f]{32})\b").unwrap());
+
+/// File extensions that look like top-level domains in image names such as `logo@2x.png`.
+const FILE_EXTENSIONS: [&str; 6] = ["gif", "jpeg", "jpg", "png", "svg", "webp"];
+
+/// Path segments that are not user names on platforms that use `/<user>` profile URLs.
+const RESERVED_NAMES: [&str; 24] = [
+    "about",
+    "account",
+    "accounts",
+    "explore",
+    "groups",
+    "hashtag",
+    "help",
+    "home",
+    "i",
+    "intent",
+    "login",
+    "marketplace",
+    "orgs",
+    "pages",
+    "privacy",
+    "search",
+    "settings",
+    "share",
+    "sharer.php",
+    "signup",
+    "sponsors",
+    "topics",
+    "tos",
+    "watch",
+];
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("Invalid identifier kind: {0}")]
+    InvalidKind(String),
+    #[error("Invalid identifier: {0}")]
+    InvalidIdentifier(String),
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Kind {
+    Email,
+    /// An `@user` handle in text, for an unknown platform.
+    Handle,
+    Twitter,
+    GitHub,
+    Facebook,
+    Instagram,
+    LinkedIn,
+    YouTube,
+    TikTok,
+    Reddit,
+    Medium,
+    Libravatar,
+    Pgp,
+}
+
+impl Kind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Email => "email",
+            Self::Handle => "handle",
+            Self::Twitter => "twitter",
+            Self::GitHub => "github",
+            Self::Facebook => "facebook",
+            Self::Instagram => "instagram",
+            Self::LinkedIn => "linkedin",
+            Self::YouTube => "youtube",
+            Self::TikTok => "tiktok",
+            Self::Reddit => "reddit",
+            Self::Medium => "medium",
+            Self::Libravatar => "libravatar",
+            Self::Pgp => "pgp",
+        }
+    }
+}
+
+impl Display for Kind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Kind {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "email" => Ok(Self::Email),
+            "handle" => Ok(Self::Handle),
+            "twitter" => Ok(Self::Twitter),
+            "github" => Ok(Self::GitHub),
+            "facebook" => Ok(Self::Facebook),
+            "instagram" => Ok(Self::Instagram),
+            "linkedin" => Ok(Self::LinkedIn),
+            "youtube" => Ok(Self::YouTube),
+            "tiktok" => Ok(Self::TikTok),
+            "reddit" => Ok(Self::Reddit),
+            "medium" => Ok(Self::Medium),
+            "libravatar" => Ok(Self::Libravatar),
+            "pgp" => Ok(Self::Pgp),
+            other => Err(Error::InvalidKind(other.to_string())),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Identifier {
+    pub kind: Kind,
+    pub value: String,
+}
+
+impl Identifier {
+    /// Create a normalized identifier.
+    pub fn new(kind: Kind, value: &str) -> Result<Self, Error> {
+        let value = match kind {
+            Kind::Pgp => value
+                .trim()
+                .trim_start_matches("0x")
+                .chars()
+                .filter(|c| !c.is_whitespace())
+                .collect::<String>()
+                .to_ascii_uppercase(),
+            Kind::Email | Kind::Libravatar => value.trim().to_lowercase(),
+            _ => value.trim().trim_start_matches('@').to_lowercase(),
+        };
+
+        let valid = match kind {
+            Kind::Email => EMAIL_RE
+                .find(&value)
+                .is_some_and(|found| found.len() == value.len()),
+            Kind::Pgp => value.len() == 40 && value.chars().all(|c| c.is_ascii_hexdigit()),
+            Kind::Libravatar => {
+                (value.len() == 32 || value.len() == 64)
+                    && value.chars().all(|c| c.is_ascii_hexdigit())
+            }
+            _ => !value.is_empty() && !value.contains(char::is_whitespace),
+        };
+
+        if valid {
+            Ok(Self { kind, value })
+        } else {
+            Err(Error::InvalidIdentifier(value))
+        }
+    }
+}
+
+impl Display for Identifier {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.kind, self.value)
+    }
+}
+
+impl FromStr for Identifier {
+    type Err = Error;
+
+    /// Parse an identifier written as `<kind>:<value>`, or a bare email address or `@user` handle.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+
+        match s.split_once(':') {
+            Some((kind, value)) => Self::new(kind.to_ascii_lowercase().parse()?, value),
+            None if s.starts_with('@') => Self::new(Kind::Handle, s),
+            None => Self::new(Kind::Email, s),
+        }
+    }
+}
+
+/// Find the identifiers in a page (its links, avatar images, Twitter card fields and text).
+pub fn extract<'a, I: IntoIterator<Item = &'a str>>(
+    html: &Html,
+    metadata: &Metadata,
+    text: I,
+) -> BTreeSet<Identifier> {
+    let mut identifiers = BTreeSet::new();
+
+    for href in html
+        .select(&HREF_SEL)
+        .filter_map(|element| element.attr("href"))
+    {
+        let href = href.trim();
+
+        match crate::wayback::original_url(href) {
+            Some(original) => from_href(&original, &mut identifiers),
+            None => from_href(href, &mut identifiers),
+        }
+    }
+
+    for capture in html
+        .select(&LIBRAVATAR_IMG_SEL)
+        .filter_map(|element| element.attr("src"))
+        .filter_map(|src| LIBRAVATAR_SRC_RE.captures(src))
+    {
+        identifiers.extend(Identifier::new(Kind::Libravatar, &capture[1]).ok());
+    }
+
+    for (name, value) in &metadata.twitter {
+        if name == "site" || name == "creator" {
+            identifiers.extend(Identifier::new(Kind::Twitter, value).ok());
+        }
+    }
+
+    for value in text {
+        from_text(value, &mut identifiers);
+    }
+
+    identifiers
+}
+
+/// Find email addresses, `@user` handles and PGP fingerprints in text.
+pub fn from_text(text: &str, identifiers: &mut BTreeSet<Identifier>) {
+    for found in EMAIL_RE.captures_iter(text) {
+        if !FILE_EXTENSIONS.contains(&found[1].to_ascii_lowercase().as_str()) {
+            identifiers.extend(Identifier::new(Kind::Email, &found[0]).ok());
+        }
+    }
+
+    for capture in HANDLE_RE.captures_iter(text) {
+        identifiers.extend(Identifier::new(Kind::Handle, &capture[1]).ok());
+    }
+
+    for found in PGP_FINGERPRINT_RE.find_iter(text) {
+        identifiers.extend(Identifier::new(Kind::Pgp, found.as_str()).ok());
+    }
+}
+
+fn from_href(href: &str, identifiers: &mut BTreeSet<Identifier>) {
+    if let Some(addresses) = href
+        .get(..7)
+        .filter(|scheme| scheme.eq_ignore_ascii_case("mailto:"))
+        .map(|_| &href[7..])
+    {
+        let addresses = addresses.split('?').next().unwrap_or_default();
+
+        for address in addresses.replace("%40", "@").split(',') {
+            identifiers.extend(Identifier::new(Kind::Email, address).ok());
+        }
+    } else if let Some(capture) = PGP_FINGERPRINT_HREF_RE.captures(href) {
+        identifiers.extend(Identifier::new(Kind::Pgp, &capture[1]).ok());
+    } else if let Some(identifier) = profile(href) {
+        identifiers.insert(identifier);
+    }
+}
+
+/// The user identified by a profile URL on a major platform.
+pub fn profile(url: &str) -> Option<Identifier> {
+    let url = Url::parse(url).ok()?;
+    let host = url.host_str()?.to_ascii_lowercase();
+    let host = ["www.", "mobile.", "m."]
+        .iter()
+        .find_map(|prefix| host.strip_prefix(prefix))
+        .unwrap_or(&host);
+    let segments = url
+        .path_segments()?
+        .filter(|segment| !segment.is_empty())
+        .collect::<Vec<_>>();
+
+    if host == "facebook.com" && segments == ["profile.php"] {
+        let (_, id) = url.query_pairs().find(|(key, _)| key == "id")?;
+
+        return Some(id)
+            .filter(|id| id.chars().all(|c| c.is_ascii_digit()))
+            .and_then(|id| Identifier::new(Kind::Facebook, &id).ok());
+    }
+
+    let (kind, name) = match (host, segments.as_slice()) {
+        ("twitter.com" | "x.com", [name]) => (Kind::Twitter, *name),
+        ("github.com", [name]) => (Kind::GitHub, *name),
+        ("facebook.com", [name]) => (Kind::Facebook, *name),
+        ("instagram.com", [name]) => (Kind::Instagram, *name),
+        ("linkedin.com", ["in", name]) => (Kind::LinkedIn, *name),
+        ("youtube.com", [name]) if name.starts_with('@') => (Kind::YouTube, *name),
+        ("youtube.com", ["user", name]) => (Kind::YouTube, *name),
+        ("tiktok.com", [name]) if name.starts_with('@') => (Kind::TikTok, *name),
+        ("reddit.com", ["user" | "u", name]) => (Kind::Reddit, *name),
+        ("medium.com", [name]) if name.starts_with('@') => (Kind::Medium, *name),
+        _ => return None,
+    };
+
+    let name = name.trim_start_matches('@');
+
+    if RESERVED_NAMES.contains(&name.to_ascii_lowercase().as_str())
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-' || c == '.')
+    {
+        None
+    } else {
+        Identifier::new(kind, name).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn identifiers(values: &[&str]) -> BTreeSet<Identifier> {
+        values.iter().map(|value| value.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn extract_identifiers() {
+        let html = Html::parse_document(
+            r#"<html><head>
+<meta name="twitter:creator" content="@Jack">
+<link rel="me" href="https://github.com/Octocat">
+</head><body>
+<p>Contact: <a href="mailto:Jack@Example.com?subject=Hi">email</a> or jill@example.org.</p>
+<p>Follow @jill_example, not the logo@2x.png file.</p>
+<a href="https://web.archive.org/web/2016/https://www.instagram.com/jill.example/">Instagram</a>
+<a href="https://twitter.com/intent/tweet">Share</a>
+<a href="https://twitter.com/jack/status/20">A tweet</a>
+<a href="https://www.linkedin.com/in/jill-example/">LinkedIn</a>
+<a href="https://www.facebook.com/profile.php?id=12345">Facebook</a>
+<a href="openpgp4fpr:0123456789abcdef0123456789abcdef01234567">Key</a>
+<img src="https://seccdn.libravatar.org/avatar/0bc83cb571cd1c50ba6f3e8a78ef1346?s=80">
+<pre>ABCD EF01 2345 6789 ABCD  EF01 2345 6789 ABCD EF01</pre>
+</body></html>"#,
+        );
+        let metadata = Metadata::extract(&html);
+        let text = html.root_element().text().collect::<Vec<_>>();
+
+        assert_eq!(
+            extract(&html, &metadata, text),
+            identifiers(&[
+                "email:jack@example.com",
+                "email:jill@example.org",
+                "handle:jill_example",
+                "twitter:jack",
+                "github:octocat",
+                "facebook:12345",
+                "instagram:jill.example",
+                "linkedin:jill-example",
+                "libravatar:0bc83cb571cd1c50ba6f3e8a78ef1346",
+                "pgp:0123456789ABCDEF0123456789ABCDEF01234567",
+                "pgp:ABCDEF0123456789ABCDEF0123456789ABCDEF01",
+            ])
+        );
+    }
+
+    #[test]
+    fn parse_identifiers() {
+        assert_eq!(
+            "Jack@Example.com"
+                .parse::<Identifier>()
+                .unwrap()
+                .to_string(),
+            "email:jack@example.com"
+        );
+        assert_eq!(
+            "@Jack".parse::<Identifier>().unwrap().to_string(),
+            "handle:jack"
+        );
+        assert_eq!(
+            "TWITTER:@Jack".parse::<Identifier>().unwrap().to_string(),
+            "twitter:jack"
+        );
+        assert_eq!(
+            "pgp:0xabcd ef01 2345 6789 abcd ef01 2345 6789 abcd ef01"
+                .parse::<Identifier>()
+                .unwrap()
+                .to_string(),
+            "pgp:ABCDEF0123456789ABCDEF0123456789ABCDEF01"
+        );
+        assert!("pgp:abcd".parse::<Identifier>().is_err());
+        assert!("myspace:jack".parse::<Identifier>().is_err());
+        assert!("not an email".parse::<Identifier>().is_err());
+    }
+}
@@ -0,0 +1,65 @@
+//! JSON documents, flattened to their string values.
+
+use serde_json::Value;
+
+/// The non-empty string values in a JSON value, with their key paths (e.g. `data.items[0].text`).
+///
+/// Values are returned in document order (object key order is preserved).
+pub fn strings(value: &Value) -> Vec<(String, &str)> {
+    let mut strings = vec![];
+    collect(value, String::new(), &mut strings);
+    strings
+}
+
+fn collect<'a>(value: &'a Value, path: String, strings: &mut Vec<(String, &'a str)>) {
+    match value {
+        Value::String(value) => {
+            let value = value.trim();
+
+            if !value.is_empty() {
+                strings.push((path, value));
+            }
+        }
+        Value::Array(values) => {
+            for (index, value) in values.iter().enumerate() {
+                collect(value, format!("{path}[{index}]"), strings);
+            }
+        }
+        Value::Object(fields) => {
+            for (key, value) in fields {
+                let path = if path.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{path}.{key}")
+                };
+
+                collect(value, path, strings);
+            }
+        }
+        Value::Null | Value::Bool(_) | Value::Number(_) => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flatten_strings() {
+        let value = serde_json::json!({
+            "title": "Example",
+            "count": 2,
+            "items": [{"text": "First"}, {"text": " "}, {"text": "Second", "tags": ["a", null]}],
+        });
+
+        assert_eq!(
+            strings(&value),
+            vec![
+                ("title".to_string(), "Example"),
+                ("items[0].text".to_string(), "First"),
+                ("items[2].text".to_string(), "Second"),
+                ("items[2].tags[0]".to_string(), "a"),
+            ]
+        );
+    }
+}
@@ -2,11 +2,26 @@ use once_cell::sync::Lazy;
 use regex::Regex;
 use scraper::{Html, Selector};
 use std::borrow::Cow;
-use std::collections::HashSet;
+use std::collections::{BTreeSet, HashSet};
+
+pub use aib_core::charset;
+pub use identifiers::Identifier;
+pub use links::Link;
+pub use metadata::Metadata;
+
+pub mod identifiers;
+pub mod json;
+pub mod links;
+pub mod main_content;
+pub mod metadata;
+pub mod pdf;
+pub mod requisites;
+pub mod site;
+pub mod text;
+pub mod wayback;
 
 static TITLE_SEL: Lazy<Selector> = Lazy::new(|| Selector::parse(r#"head title"#).unwrap());
 static BODY_PARA_SEL: Lazy<Selector> = Lazy::new(|| Selector::parse(r#"body"#).unwrap());
-static LINK_SEL: Lazy<Selector> = Lazy::new(|| Selector::parse(r#"a"#).unwrap());
 
 static GRAVATAR_IMG_SEL: Lazy<Selector> =
     Lazy::new(|| Selector::parse(r#"img[src *= "gravatar.com"]"#).unwrap());
@@ -20,27 +35,177 @@ pub enum Error {
     Io(#[from] std::io::Error),
     #[error("Invalid UTF-8: {0:?}")]
     InvalidUtf8(#[from] std::str::Utf8Error),
+    #[error("JSON error")]
+    Json(#[from] serde_json::Error),
+    #[error("PDF error")]
+    Pdf(#[from] pdf::Error),
+}
+
+/// The document formats that can be extracted.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Format {
+    Html,
+    Json,
+    Text,
+    Pdf,
+}
+
+impl Format {
+    /// The format for a MIME type (any parameters are ignored).
+    pub fn from_mime_type(mime_type: &str) -> Option<Self> {
+        let essence = mime_type.split(';').next().unwrap_or_default().trim();
+
+        match essence.to_ascii_lowercase().as_str() {
+            "text/html" | "application/xhtml+xml" => Some(Self::Html),
+            "application/json" | "text/json" => Some(Self::Json),
+            "text/plain" => Some(Self::Text),
+            "application/pdf" => Some(Self::Pdf),
+            _ => None,
+        }
+    }
+
+    /// Whether documents in this format are text that needs to be decoded before extraction.
+    pub fn is_text(&self) -> bool {
+        !matches!(self, Self::Pdf)
+    }
+
+    /// Whether content sniffed as the given MIME type can be extracted in this format.
+    ///
+    /// Text formats accept any textual content, since sniffing can't reliably
+    /// distinguish them (an HTML fragment may look like plain text).
+    pub fn accepts_sniffed(&self, sniffed_mime_type: &str) -> bool {
+        match self {
+            Self::Pdf => sniffed_mime_type == "application/pdf",
+            _ => {
+                sniffed_mime_type == "application/xml"
+                    || Self::from_mime_type(sniffed_mime_type)
+                        .is_some_and(|format| format.is_text())
+            }
+        }
+    }
+}
+
+/// How the text of HTML documents is extracted.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Mode {
+    /// Only the full text of the page.
+    #[default]
+    Full,
+    /// The full text, plus the main content identified by boilerplate removal (see
+    /// [`main_content`]).
+    MainContent,
 }
 
 #[derive(Debug)]
 pub struct Document<'a> {
     pub title: Cow<'a, str>,
     pub content: Vec<Cow<'a, str>>,
-    pub links: Vec<Cow<'a, str>>,
+    /// Text blocks of the main content (with boilerplate such as navigation removed).
+    pub main_content: Vec<Cow<'a, str>>,
+    pub links: Vec<Link>,
     pub gravatar_hashes: HashSet<Cow<'a, str>>,
+    /// Normalized identity and contact identifiers (see [`identifiers`]).
+    pub identifiers: BTreeSet<Identifier>,
+    pub metadata: Metadata<'a>,
+    /// Fields from a site-specific extractor (see [`Document::extract_for_url`]).
+    pub site: Option<site::SiteData>,
 }
 
 impl Document<'static> {
-    pub fn parse(contents: &str) -> Result<Document<'static>, Error> {
-        let html = Html::parse_document(contents);
-        let doc = Document::extract(&html)?;
+    pub fn parse(contents: &str, page_url: Option<&str>) -> Result<Document<'static>, Error> {
+        let html = Html::parse_document(&wayback::clean(contents));
+        let doc = Document::extract(&html, page_url, Mode::default())?;
 
         Ok(doc.into_owned())
     }
+
+    /// Extract a document from JSON, with one content block per string value (prefixed by its
+    /// key path).
+    ///
+    /// A top-level `title` string is used as the title.
+    pub fn parse_json(contents: &str) -> Result<Document<'static>, Error> {
+        let value = serde_json::from_str::<serde_json::Value>(contents)?;
+        let strings = json::strings(&value);
+
+        let title = strings
+            .iter()
+            .find(|(path, _)| path == "title")
+            .map(|(_, value)| value.to_string().into())
+            .unwrap_or_default();
+
+        let content = strings
+            .into_iter()
+            .map(|(path, value)| format!("{path}: {value}").into())
+            .collect();
+
+        Ok(Self::from_text_blocks(title, content, Mode::Full))
+    }
+
+    /// Extract a document from plain text, with one content block per paragraph.
+    ///
+    /// In [`Mode::MainContent`] the whole text is treated as the main content.
+    pub fn parse_text(contents: &str, mode: Mode) -> Document<'static> {
+        let paragraphs = text::paragraphs(contents)
+            .into_iter()
+            .map(Cow::Owned)
+            .collect::<Vec<_>>();
+
+        Self::from_text_blocks(Cow::default(), paragraphs, mode)
+    }
+
+    /// Extract a document from a PDF, with one content block per paragraph.
+    ///
+    /// The title is taken from the document information dictionary. In [`Mode::MainContent`] the
+    /// whole text is treated as the main content.
+    pub fn parse_pdf(bytes: &[u8], mode: Mode) -> Result<Document<'static>, Error> {
+        let text = pdf::extract(bytes)?;
+        let paragraphs = text
+            .paragraphs
+            .into_iter()
+            .map(Cow::Owned)
+            .collect::<Vec<_>>();
+
+        Ok(Self::from_text_blocks(
+            text.title.map(Cow::Owned).unwrap_or_default(),
+            paragraphs,
+            mode,
+        ))
+    }
+
+    fn from_text_blocks(
+        title: Cow<'static, str>,
+        content: Vec<Cow<'static, str>>,
+        mode: Mode,
+    ) -> Document<'static> {
+        let mut identifiers = BTreeSet::new();
+
+        for value in &content {
+            identifiers::from_text(value, &mut identifiers);
+        }
+
+        let main_content = match mode {
+            Mode::Full => vec![],
+            Mode::MainContent => content.clone(),
+        };
+
+        Document {
+            title,
+            content,
+            main_content,
+            links: vec![],
+            gravatar_hashes: HashSet::new(),
+            identifiers,
+            metadata: Metadata::default(),
+            site: None,
+        }
+    }
 }
 
 impl<'a> Document<'a> {
-    pub fn extract(html: &'a Html) -> Result<Self, Error> {
+    /// Extract a document, resolving relative links against the page's URL (if known).
+    ///
+    /// The main content is only identified in [`Mode::MainContent`].
+    pub fn extract(html: &'a Html, page_url: Option<&str>, mode: Mode) -> Result<Self, Error> {
         let title = html
             .select(&TITLE_SEL)
             .flat_map(|body| body.text())
@@ -55,15 +220,7 @@ impl<'a> Document<'a> {
             .flat_map(|body| body.text())
             .map(|value| value.trim())
             .filter(|value| !value.is_empty())
-            .map(|value| value.into())
-            .collect::<Vec<_>>();
-
-        let links = html
-            .select(&LINK_SEL)
-            .filter_map(|element| element.attr("href"))
-            .map(|value| value.trim())
-            .filter(|value| value.starts_with("http"))
-            .map(|value| value.into())
+            .map(Cow::Borrowed)
             .collect::<Vec<_>>();
 
         let matches = html
@@ -77,14 +234,50 @@ impl<'a> Document<'a> {
             .map(|hash_match| hash_match.as_str().into())
             .collect::<HashSet<_>>();
 
+        let metadata = Metadata::extract(html);
+        let identifiers =
+            identifiers::extract(html, &metadata, content.iter().map(|value| value.as_ref()));
+
         Ok(Self {
             title,
             content,
-            links,
+            main_content: match mode {
+                Mode::Full => vec![],
+                Mode::MainContent => main_content::extract(html),
+            },
+            links: links::links(html, page_url),
             gravatar_hashes: matches,
+            identifiers,
+            metadata,
+            site: None,
         })
     }
 
+    /// Extract a document, also using the first site-specific extractor that handles the page's
+    /// URL.
+    ///
+    /// In [`Mode::MainContent`], if the site-specific extractor identifies the main text of the
+    /// page, it replaces the generic main content.
+    pub fn extract_for_url(
+        html: &'a Html,
+        page_url: &str,
+        extractors: &site::Extractors,
+        mode: Mode,
+    ) -> Result<Self, Error> {
+        let mut document = Self::extract(html, Some(page_url), mode)?;
+        document.site = url::Url::parse(page_url)
+            .ok()
+            .and_then(|url| extractors.extract(&url, html));
+
+        if mode == Mode::MainContent {
+            if let Some(text) = document.site.as_ref().and_then(|site| site.text()) {
+                document.main_content = vec![text.to_string().into()];
+            }
+        }
+
+        Ok(document)
+    }
+
     pub fn into_owned(self) -> Document<'static> {
         Document {
             title: self.title.into_owned().into(),
@@ -93,16 +286,69 @@ impl<'a> Document<'a> {
                 .into_iter()
                 .map(|value| value.into_owned().into())
                 .collect(),
-            links: self
-                .links
+            main_content: self
+                .main_content
                 .into_iter()
                 .map(|value| value.into_owned().into())
                 .collect(),
+            links: self.links,
             gravatar_hashes: self
                 .gravatar_hashes
                 .into_iter()
                 .map(|value| value.into_owned().into())
                 .collect(),
+            identifiers: self.identifiers,
+            metadata: self.metadata.into_owned(),
+            site: self.site,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STATUS_PAGE: &str = r#"<html><head><meta property="og:description" content="“Just setting up my twttr”"></head><body><p>Home</p></body></html>"#;
+
+    #[test]
+    fn extract_status_full() {
+        let html = Html::parse_document(STATUS_PAGE);
+        let document = Document::extract_for_url(
+            &html,
+            "https://twitter.com/Jack/status/20",
+            &site::Extractors::default(),
+            Mode::Full,
+        )
+        .unwrap();
+
+        assert!(document.site.is_some());
+        assert!(document.main_content.is_empty());
+    }
+
+    #[test]
+    fn extract_status_main_content() {
+        let html = Html::parse_document(STATUS_PAGE);
+        let document = Document::extract_for_url(
+            &html,
+            "https://twitter.com/Jack/status/20",
+            &site::Extractors::default(),
+            Mode::MainContent,
+        )
+        .unwrap();
+
+        assert_eq!(document.main_content, vec!["Just setting up my twttr"]);
+    }
+
+    #[test]
+    fn parse_text_modes() {
+        let contents = "The first paragraph.\n\nThe second paragraph.";
+
+        assert!(Document::parse_text(contents, Mode::Full)
+            .main_content
+            .is_empty());
+        assert_eq!(
+            Document::parse_text(contents, Mode::MainContent).main_content,
+            vec!["The first paragraph.", "The second paragraph."]
+        );
+    }
+}
@@ -0,0 +1,178 @@
+//! Outbound links, resolved to absolute URLs and unwrapped from the archive.
+
+use aib_core::surt::Surt;
+use once_cell::sync::Lazy;
+use scraper::{Html, Selector};
+use url::Url;
+
+static BASE_SEL: Lazy<Selector> = Lazy::new(|| Selector::parse(r#"base[href]"#).unwrap());
+static LINK_SEL: Lazy<Selector> = Lazy::new(|| Selector::parse(r#"a[href], area[href]"#).unwrap());
+static IMG_ALT_SEL: Lazy<Selector> = Lazy::new(|| Selector::parse(r#"img[alt]"#).unwrap());
+
+/// A link from a page, with its URL resolved to an absolute one.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Link {
+    pub url: String,
+    /// The SURT of the URL (if it has a domain name and no port).
+    pub surt: Option<String>,
+    /// The anchor text (or image alternative text), with whitespace normalized.
+    pub text: String,
+    /// Whether the link points to the page's own host (ignoring `www`).
+    pub internal: bool,
+}
+
+/// Find the links in a page.
+///
+/// Absolute archive links (`https://web.archive.org/web/<timestamp>/<url>`) are unwrapped to their
+/// targets (relative ones are restored by [`crate::wayback::clean`]), and other relative URLs are
+/// resolved against the page's URL (or its `<base>` element). Without a page URL only
+/// absolute links are returned. Only HTTP(S) URLs are returned, without fragments.
+pub fn links(html: &Html, page_url: Option<&str>) -> Vec<Link> {
+    let page_url = page_url.and_then(|page_url| Url::parse(page_url).ok());
+    let base_url = html
+        .select(&BASE_SEL)
+        .next()
+        .and_then(|element| element.attr("href"))
+        .and_then(|href| match &page_url {
+            Some(page_url) => page_url.join(href.trim()).ok(),
+            None => Url::parse(href.trim()).ok(),
+        })
+        .or_else(|| page_url.clone());
+
+    let page_domain = page_url
+        .as_ref()
+        .and_then(|page_url| Surt::from_url(page_url.as_str()).ok())
+        .map(|surt| without_www(surt.domain));
+
+    html.select(&LINK_SEL)
+        .filter_map(|element| {
+            // Safe because of the selector.
+            let href = element.attr("href").unwrap().trim();
+
+            let mut url = match crate::wayback::original_url(href) {
+                Some(original) => Url::parse(&original).ok()?,
+                None => match &base_url {
+                    Some(base_url) => base_url.join(href).ok()?,
+                    None => Url::parse(href).ok()?,
+                },
+            };
+            url.set_fragment(None);
+
+            if url.scheme() != "http" && url.scheme() != "https" {
+                return None;
+            }
+
+            let surt = Surt::from_url(url.as_str()).ok();
+            let internal =
+                surt.as_ref()
+                    .zip(page_domain.as_ref())
+                    .is_some_and(|(surt, page_domain)| {
+                        &without_www(surt.domain.clone()) == page_domain
+                    });
+
+            let mut text = element
+                .text()
+                .flat_map(str::split_whitespace)
+                .collect::<Vec<_>>()
+                .join(" ");
+
+            if text.is_empty() {
+                text = element
+                    .select(&IMG_ALT_SEL)
+                    .filter_map(|image| image.attr("alt"))
+                    .flat_map(str::split_whitespace)
+                    .collect::<Vec<_>>()
+                    .join(" ");
+            }
+
+            Some(Link {
+                url: url.to_string(),
+                surt: surt.map(|surt| surt.to_string()),
+                text,
+                internal,
+            })
+        })
+        .collect()
+}
+
+fn without_www(mut domain: Vec<String>) -> Vec<String> {
+    if domain.last().map(String::as_str) == Some("www") {
+        domain.pop();
+    }
+
+    domain
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn find_links() {
+        let html = Html::parse_document(
+            r#"<html><body>
+<a href="/about#team">About
+  us</a>
+<a href="post.html">A post</a>
+<a href="https://web.archive.org/web/20160101000000/https://other.example.org/x">Other</a>
+<a href="//web.archive.org/web/20160101000000/http://www.example.com/">Home</a>
+<a href="https://Example.com/page"><img src="i.png" alt="Image link"></a>
+<a href="mailto:someone@example.com">Email</a>
+<a href="javascript:void(0)">Nothing</a>
+</body></html>"#,
+        );
+
+        assert_eq!(
+            links(&html, Some("http://www.example.com/blog/")),
+            vec![
+                Link {
+                    url: "http://www.example.com/about".to_string(),
+                    surt: Some("com,example,www)/about".to_string()),
+                    text: "About us".to_string(),
+                    internal: true,
+                },
+                Link {
+                    url: "http://www.example.com/blog/post.html".to_string(),
+                    surt: Some("com,example,www)/blog/post.html".to_string()),
+                    text: "A post".to_string(),
+                    internal: true,
+                },
+                Link {
+                    url: "https://other.example.org/x".to_string(),
+                    surt: Some("org,example,other)/x".to_string()),
+                    text: "Other".to_string(),
+                    internal: false,
+                },
+                Link {
+                    url: "http://www.example.com/".to_string(),
+                    surt: Some("com,example,www)/".to_string()),
+                    text: "Home".to_string(),
+                    internal: true,
+                },
+                Link {
+                    url: "https://example.com/page".to_string(),
+                    surt: Some("com,example)/page".to_string()),
+                    text: "Image link".to_string(),
+                    internal: true,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn find_links_without_page_url() {
+        let html = Html::parse_document(
+            r#"<html><body><a href="/about">About</a><a href="https://example.com/">Home</a></body></html>"#,
+        );
+
+        assert_eq!(
+            links(&html, None),
+            vec![Link {
+                url: "https://example.com/".to_string(),
+                surt: Some("com,example)/".to_string()),
+                text: "Home".to_string(),
+                internal: false,
+            }]
+        );
+    }
+}
@@ -0,0 +1,241 @@
+//! Main-content extraction (boilerplate removal) in the style of Readability.
+//!
+//! Paragraph-like blocks are scored by their text length and punctuation, and their scores are
+//! propagated to their parent and grandparent. The container with the highest score (adjusted
+//! for link density and class or ID hints) is taken to be the main content, and its headings and
+//! paragraphs are returned in document order.
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+use scraper::{ElementRef, Html, Selector};
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+static PARAGRAPH_SEL: Lazy<Selector> =
+    Lazy::new(|| Selector::parse(r#"body p, body pre, body td, body blockquote"#).unwrap());
+static BLOCK_SEL: Lazy<Selector> = Lazy::new(|| {
+    Selector::parse(r#"h1, h2, h3, h4, h5, h6, p, pre, blockquote, li, dd, dt, figcaption"#)
+        .unwrap()
+});
+static LINK_SEL: Lazy<Selector> = Lazy::new(|| Selector::parse(r#"a"#).unwrap());
+
+static POSITIVE_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?i)article|body|content|entry|main|page|post|story|text").unwrap());
+static NEGATIVE_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"(?i)\bad-|advert|banner|breadcrumb|comment|cookie|footer|menu|nav|popup|promo|related|share|sidebar|social|sponsor|subscribe|trending|widget",
+    )
+    .unwrap()
+});
+
+/// Elements whose contents are never part of the main content.
+const EXCLUDED_NAMES: [&str; 10] = [
+    "aside", "footer", "form", "header", "nav", "noscript", "script", "style", "template", "button",
+];
+const HEADING_NAMES: [&str; 6] = ["h1", "h2", "h3", "h4", "h5", "h6"];
+
+/// Paragraphs shorter than this (in characters) do not contribute to container scores.
+const MIN_PARAGRAPH_LEN: usize = 25;
+/// Blocks other than headings with a higher proportion of link text are skipped.
+const MAX_BLOCK_LINK_DENSITY: f64 = 0.5;
+const CLASS_WEIGHT: f64 = 25.0;
+
+/// The text blocks of the main content of a document, in document order.
+///
+/// Returns an empty list if no container could be identified (for example if the document has no
+/// paragraphs).
+pub fn extract(html: &Html) -> Vec<Cow<'static, str>> {
+    match top_candidate(html) {
+        Some(candidate) => blocks(candidate),
+        None => vec![],
+    }
+}
+
+fn top_candidate(html: &Html) -> Option<ElementRef<'_>> {
+    let mut scores = HashMap::new();
+    let mut candidates = vec![];
+
+    for paragraph in html.select(&PARAGRAPH_SEL) {
+        if is_excluded(&paragraph) {
+            continue;
+        }
+
+        let text = text(&paragraph);
+        let length = text.chars().count();
+
+        if length < MIN_PARAGRAPH_LEN {
+            continue;
+        }
+
+        let score = 1.0 + text.matches(',').count() as f64 + (length as f64 / 100.0).min(3.0);
+
+        let mut ancestors = paragraph.ancestors().filter_map(ElementRef::wrap);
+
+        for divisor in [1.0, 2.0] {
+            if let Some(ancestor) = ancestors.next() {
+                let entry = scores.entry(ancestor.id()).or_insert_with(|| {
+                    candidates.push(ancestor);
+                    class_weight(&ancestor)
+                });
+                *entry += score / divisor;
+            }
+        }
+    }
+
+    candidates
+        .into_iter()
+        .map(|candidate| {
+            let score = scores.get(&candidate.id()).copied().unwrap_or_default();
+
+            (candidate, score * (1.0 - link_density(&candidate)))
+        })
+        .max_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map(|(candidate, _)| candidate)
+}
+
+fn blocks(candidate: ElementRef<'_>) -> Vec<Cow<'static, str>> {
+    candidate
+        .select(&BLOCK_SEL)
+        .filter(|block| !is_excluded(block) && !has_block_ancestor(block, &candidate))
+        .filter(|block| {
+            HEADING_NAMES.contains(&block.value().name())
+                || link_density(block) <= MAX_BLOCK_LINK_DENSITY
+        })
+        .map(|block| text(&block))
+        .filter(|text| !text.is_empty())
+        .map(Cow::Owned)
+        .collect()
+}
+
+/// Whether the element is in excluded markup or a container whose class or ID suggests boilerplate.
+fn is_excluded(element: &ElementRef) -> bool {
+    std::iter::once(*element)
+        .chain(element.ancestors().filter_map(ElementRef::wrap))
+        .any(|element| {
+            EXCLUDED_NAMES.contains(&element.value().name())
+                || (hints(&element)
+                    .any(|hint| NEGATIVE_RE.is_match(hint) && !POSITIVE_RE.is_match(hint)))
+        })
+}
+
+/// Whether a block is nested in another block below the candidate (in which case the outer block
+/// already includes its text).
+fn has_block_ancestor(block: &ElementRef, candidate: &ElementRef) -> bool {
+    block
+        .ancestors()
+        .filter_map(ElementRef::wrap)
+        .take_while(|ancestor| ancestor.id() != candidate.id())
+        .any(|ancestor| BLOCK_SEL.matches(&ancestor))
+}
+
+fn class_weight(element: &ElementRef) -> f64 {
+    hints(element)
+        .map(|hint| {
+            let mut weight = 0.0;
+
+            if POSITIVE_RE.is_match(hint) {
+                weight += CLASS_WEIGHT;
+            }
+
+            if NEGATIVE_RE.is_match(hint) {
+                weight -= CLASS_WEIGHT;
+            }
+
+            weight
+        })
+        .sum()
+}
+
+fn hints<'a>(element: &ElementRef<'a>) -> impl Iterator<Item = &'a str> {
+    let value = element.value();
+
+    value.attr("class").into_iter().chain(value.attr("id"))
+}
+
+/// The proportion of the element's text that is in links.
+fn link_density(element: &ElementRef) -> f64 {
+    let length = text(element).chars().count();
+
+    if length == 0 {
+        0.0
+    } else {
+        let link_length = element
+            .select(&LINK_SEL)
+            .map(|link| text(&link).chars().count())
+            .sum::<usize>();
+
+        link_length as f64 / length as f64
+    }
+}
+
+fn text(element: &ElementRef) -> String {
+    element
+        .text()
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extract_main_content() {
+        let html = Html::parse_document(
+            r#"<html><head><title>Example</title></head><body>
+<nav><ul><li><a href="/">Home</a></li><li><a href="/about">About us and our team</a></li></ul></nav>
+<div class="cookie-banner"><p>We use cookies to improve your experience, please accept them.</p></div>
+<div id="wrapper">
+  <div class="sidebar trending">
+    <h3>Trending</h3>
+    <p><a href="/a">Something else that people are reading today</a></p>
+  </div>
+  <article class="post-body">
+    <h1>The headline</h1>
+    <p>The first paragraph, which has enough text to count, and some commas, too.</p>
+    <h2>A section</h2>
+    <p>The second paragraph, with <a href="/link">a link</a> in the middle of some text.</p>
+    <ul><li>A list item that is part of the article</li></ul>
+    <p><a href="/more">Read more articles like this one</a></p>
+  </article>
+</div>
+<footer><p>Copyright notice and various other legal information, all rights reserved.</p></footer>
+</body></html>"#,
+        );
+
+        assert_eq!(
+            extract(&html),
+            vec![
+                "The headline",
+                "The first paragraph, which has enough text to count, and some commas, too.",
+                "A section",
+                "The second paragraph, with a link in the middle of some text.",
+                "A list item that is part of the article",
+            ]
+        );
+    }
+
+    #[test]
+    fn class_weights() {
+        let html = Html::parse_fragment(
+            r#"<div class="lead-story"></div><div class="thread-list"></div><div class="ad-slot"></div><div class="top-ad-unit"></div>"#,
+        );
+        let selector = Selector::parse("div").unwrap();
+
+        assert_eq!(
+            html.select(&selector)
+                .map(|element| class_weight(&element))
+                .collect::<Vec<_>>(),
+            vec![CLASS_WEIGHT, 0.0, -CLASS_WEIGHT, -CLASS_WEIGHT]
+        );
+    }
+
+    #[test]
+    fn extract_without_paragraphs() {
+        let html = Html::parse_document(r#"<html><body><div>Short</div></body></html>"#);
+
+        assert!(extract(&html).is_empty());
+    }
+}
@@ -0,0 +1,541 @@
+//! Structured metadata from `<meta>` and `<link>` tags, JSON-LD and microdata.
+//!
+//! The description, canonical URL, author and dates are taken from the most
+//! specific source available: dedicated meta tags first, then OpenGraph
+//! properties, then JSON-LD and microdata items.
+
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use once_cell::sync::Lazy;
+use scraper::{ElementRef, Html, Selector};
+use std::borrow::Cow;
+use std::collections::BTreeSet;
+
+static META_SEL: Lazy<Selector> = Lazy::new(|| Selector::parse(r#"meta[content]"#).unwrap());
+static CANONICAL_SEL: Lazy<Selector> =
+    Lazy::new(|| Selector::parse(r#"link[rel ~= "canonical" i][href]"#).unwrap());
+static JSON_LD_SEL: Lazy<Selector> =
+    Lazy::new(|| Selector::parse(r#"script[type = "application/ld+json" i]"#).unwrap());
+static ITEMSCOPE_SEL: Lazy<Selector> = Lazy::new(|| Selector::parse(r#"[itemscope]"#).unwrap());
+static ITEMPROP_SEL: Lazy<Selector> = Lazy::new(|| Selector::parse(r#"[itemprop]"#).unwrap());
+
+const AUTHOR_NAMES: [&str; 3] = ["author", "article:author", "dc.creator"];
+const PUBLISHED_NAMES: [&str; 7] = [
+    "article:published_time",
+    "og:published_time",
+    "date",
+    "dc.date",
+    "dc.date.issued",
+    "dcterms.created",
+    "pubdate",
+];
+const MODIFIED_NAMES: [&str; 5] = [
+    "article:modified_time",
+    "og:updated_time",
+    "last-modified",
+    "dc.date.modified",
+    "dcterms.modified",
+];
+
+/// An item marked up with microdata attributes (`itemscope`, `itemprop`).
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct MicrodataItem<'a> {
+    pub item_type: Option<Cow<'a, str>>,
+    /// Property names and values, in document order.
+    pub properties: Vec<(Cow<'a, str>, Cow<'a, str>)>,
+}
+
+impl MicrodataItem<'_> {
+    pub fn property(&self, name: &str) -> Option<&str> {
+        self.properties
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_ref())
+    }
+
+    pub fn into_owned(self) -> MicrodataItem<'static> {
+        MicrodataItem {
+            item_type: self.item_type.map(|value| value.into_owned().into()),
+            properties: into_owned_pairs(self.properties),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Metadata<'a> {
+    pub description: Option<Cow<'a, str>>,
+    pub canonical_url: Option<Cow<'a, str>>,
+    /// The language of the document (from `<html lang>`).
+    pub lang: Option<Cow<'a, str>>,
+    pub author: Option<Cow<'a, str>>,
+    pub published: Option<DateTime<Utc>>,
+    pub modified: Option<DateTime<Utc>>,
+    /// OpenGraph properties (`og:*`), without the prefix.
+    pub open_graph: Vec<(Cow<'a, str>, Cow<'a, str>)>,
+    /// Twitter card fields (`twitter:*`), without the prefix.
+    pub twitter: Vec<(Cow<'a, str>, Cow<'a, str>)>,
+    /// JSON-LD blocks that could be parsed.
+    pub json_ld: Vec<serde_json::Value>,
+    pub microdata: Vec<MicrodataItem<'a>>,
+}
+
+impl<'a> Metadata<'a> {
+    pub fn extract(html: &'a Html) -> Self {
+        let mut metadata = Self {
+            lang: html
+                .root_element()
+                .attr("lang")
+                .map(str::trim)
+                .filter(|value| !value.is_empty())
+                .map(|value| value.into()),
+            canonical_url: html
+                .select(&CANONICAL_SEL)
+                .filter_map(|element| element.attr("href"))
+                .map(str::trim)
+                .find(|value| !value.is_empty())
+                .map(|value| value.into()),
+            ..Default::default()
+        };
+
+        let mut og_description = None;
+        let mut og_url = None;
+
+        for element in html.select(&META_SEL) {
+            let Some(name) = element
+                .attr("property")
+                .or_else(|| element.attr("name"))
+                .map(|value| value.trim())
+            else {
+                continue;
+            };
+            // Safe because of the selector.
+            let content = element.attr("content").unwrap().trim();
+
+            if content.is_empty() {
+                continue;
+            }
+
+            let lowercase_name = name.to_ascii_lowercase();
+            let name = lowercase_name.as_str();
+
+            if name == "description" {
+                metadata.description.get_or_insert(content.into());
+            } else if AUTHOR_NAMES.contains(&name) {
+                metadata.author.get_or_insert(content.into());
+            } else if PUBLISHED_NAMES.contains(&name) {
+                metadata.published = metadata.published.or_else(|| parse_date(content));
+            } else if MODIFIED_NAMES.contains(&name) {
+                metadata.modified = metadata.modified.or_else(|| parse_date(content));
+            }
+
+            if let Some(property) = name.strip_prefix("og:") {
+                match property {
+                    "description" => og_description = og_description.or(Some(content)),
+                    "url" => og_url = og_url.or(Some(content)),
+                    _ => {}
+                }
+
+                metadata
+                    .open_graph
+                    .push((property.to_string().into(), content.into()));
+            } else if let Some(field) = name.strip_prefix("twitter:") {
+                metadata
+                    .twitter
+                    .push((field.to_string().into(), content.into()));
+            }
+        }
+
+        metadata.json_ld = html
+            .select(&JSON_LD_SEL)
+            .filter_map(|element| {
+                let text = element.text().collect::<String>();
+
+                serde_json::from_str::<serde_json::Value>(text.trim()).ok()
+            })
+            .collect();
+
+        metadata.microdata = html
+            .select(&ITEMSCOPE_SEL)
+            .map(|scope| MicrodataItem {
+                item_type: scope.attr("itemtype").map(|value| value.trim().into()),
+                properties: scope
+                    .select(&ITEMPROP_SEL)
+                    .filter(|property| {
+                        nearest_scope(property).map(|ancestor| ancestor.id()) == Some(scope.id())
+                    })
+                    .flat_map(|property| {
+                        let value = property_value(&property);
+
+                        // Safe because of the selector.
+                        property
+                            .attr("itemprop")
+                            .unwrap()
+                            .split_whitespace()
+                            .map(move |name| (Cow::Borrowed(name), value.clone()))
+                    })
+                    .collect(),
+            })
+            .collect();
+
+        if metadata.description.is_none() {
+            metadata.description = og_description.map(|value| value.into());
+        }
+
+        if metadata.canonical_url.is_none() {
+            metadata.canonical_url = og_url.map(|value| value.into());
+        }
+
+        if metadata.author.is_none() {
+            let author = metadata
+                .json_ld_values("author")
+                .find_map(json_ld_name)
+                .or_else(|| metadata.microdata_value("author"));
+
+            metadata.author = author.map(Cow::Owned);
+        }
+
+        if metadata.published.is_none() {
+            metadata.published = metadata.structured_date("datePublished");
+        }
+
+        if metadata.modified.is_none() {
+            metadata.modified = metadata.structured_date("dateModified");
+        }
+
+        metadata
+    }
+
+    pub fn into_owned(self) -> Metadata<'static> {
+        Metadata {
+            description: self.description.map(|value| value.into_owned().into()),
+            canonical_url: self.canonical_url.map(|value| value.into_owned().into()),
+            lang: self.lang.map(|value| value.into_owned().into()),
+            author: self.author.map(|value| value.into_owned().into()),
+            published: self.published,
+            modified: self.modified,
+            open_graph: into_owned_pairs(self.open_graph),
+            twitter: into_owned_pairs(self.twitter),
+            json_ld: self.json_ld,
+            microdata: self
+                .microdata
+                .into_iter()
+                .map(MicrodataItem::into_owned)
+                .collect(),
+        }
+    }
+
+    /// The types the document declares for itself or its items, in lowercase: the
+    /// OpenGraph type, JSON-LD `@type` values and microdata item types (without
+    /// the vocabulary URL).
+    pub fn types(&self) -> BTreeSet<String> {
+        let open_graph = self
+            .open_graph
+            .iter()
+            .filter(|(property, _)| property == "type")
+            .map(|(_, value)| value.to_string());
+        let json_ld = self
+            .json_ld_values("@type")
+            .flat_map(|value| match value {
+                serde_json::Value::String(value) => vec![value.to_string()],
+                serde_json::Value::Array(values) => values
+                    .iter()
+                    .filter_map(|value| value.as_str())
+                    .map(str::to_string)
+                    .collect(),
+                _ => vec![],
+            })
+            .collect::<Vec<_>>();
+        let microdata = self
+            .microdata
+            .iter()
+            .filter_map(|item| item.item_type.as_deref())
+            .flat_map(str::split_whitespace)
+            .map(|item_type| {
+                item_type
+                    .rsplit(['/', '#'])
+                    .next()
+                    .unwrap_or(item_type)
+                    .to_string()
+            });
+
+        open_graph
+            .chain(json_ld)
+            .chain(microdata)
+            .map(|value| value.trim().to_lowercase())
+            .filter(|value| !value.is_empty())
+            .collect()
+    }
+
+    /// The preview image URL from the OpenGraph properties or Twitter card.
+    pub fn image(&self) -> Option<&str> {
+        pair_value(&self.open_graph, "image")
+            .or_else(|| pair_value(&self.open_graph, "image:url"))
+            .or_else(|| pair_value(&self.twitter, "image"))
+    }
+
+    /// The values of a key in every JSON-LD object (including those in `@graph` lists).
+    fn json_ld_values<'b>(&'b self, key: &'b str) -> impl Iterator<Item = &'b serde_json::Value> {
+        let mut objects = vec![];
+
+        for value in &self.json_ld {
+            collect_json_ld_objects(value, &mut objects);
+        }
+
+        objects
+            .into_iter()
+            .filter_map(move |object| object.get(key))
+    }
+
+    fn microdata_value(&self, name: &str) -> Option<String> {
+        self.microdata
+            .iter()
+            .find_map(|item| item.property(name))
+            .map(str::to_string)
+    }
+
+    fn structured_date(&self, key: &str) -> Option<DateTime<Utc>> {
+        self.json_ld_values(key)
+            .filter_map(|value| value.as_str())
+            .find_map(parse_date)
+            .or_else(|| self.microdata_value(key).as_deref().and_then(parse_date))
+    }
+}
+
+/// Parse the date formats used in metadata (RFC 3339, with or without an offset, or a date).
+pub fn parse_date(value: &str) -> Option<DateTime<Utc>> {
+    let value = value.trim();
+
+    DateTime::parse_from_rfc3339(value)
+        .or_else(|_| DateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S%.f%z"))
+        .map(|value| value.with_timezone(&Utc))
+        .ok()
+        .or_else(|| {
+            NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S%.f")
+                .ok()
+                .map(|value| value.and_utc())
+        })
+        .or_else(|| {
+            NaiveDate::parse_from_str(value, "%Y-%m-%d")
+                .ok()
+                .and_then(|value| value.and_hms_opt(0, 0, 0))
+                .map(|value| value.and_utc())
+        })
+}
+
+fn into_owned_pairs(
+    pairs: Vec<(Cow<'_, str>, Cow<'_, str>)>,
+) -> Vec<(Cow<'static, str>, Cow<'static, str>)> {
+    pairs
+        .into_iter()
+        .map(|(key, value)| (key.into_owned().into(), value.into_owned().into()))
+        .collect()
+}
+
+fn pair_value<'a>(pairs: &'a [(Cow<'_, str>, Cow<'_, str>)], name: &str) -> Option<&'a str> {
+    pairs
+        .iter()
+        .find(|(key, _)| key == name)
+        .map(|(_, value)| value.as_ref())
+}
+
+fn collect_json_ld_objects<'a>(
+    value: &'a serde_json::Value,
+    objects: &mut Vec<&'a serde_json::Map<String, serde_json::Value>>,
+) {
+    match value {
+        serde_json::Value::Array(values) => {
+            for value in values {
+                collect_json_ld_objects(value, objects);
+            }
+        }
+        serde_json::Value::Object(object) => {
+            objects.push(object);
+
+            if let Some(graph) = object.get("@graph") {
+                collect_json_ld_objects(graph, objects);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// The name of a JSON-LD person or organization (or the first of a list).
+fn json_ld_name(value: &serde_json::Value) -> Option<String> {
+    match value {
+        serde_json::Value::String(name) => Some(name.trim().to_string()),
+        serde_json::Value::Object(object) => object
+            .get("name")
+            .and_then(|name| name.as_str())
+            .map(|name| name.trim().to_string()),
+        serde_json::Value::Array(values) => values.iter().find_map(json_ld_name),
+        _ => None,
+    }
+    .filter(|name| !name.is_empty())
+}
+
+fn nearest_scope<'a>(element: &ElementRef<'a>) -> Option<ElementRef<'a>> {
+    element
+        .ancestors()
+        .filter_map(ElementRef::wrap)
+        .find(|ancestor| ancestor.value().attr("itemscope").is_some())
+}
+
+/// The value of a microdata property, as defined by the HTML standard.
+fn property_value<'a>(element: &ElementRef<'a>) -> Cow<'a, str> {
+    let value = element.value();
+
+    if value.attr("itemscope").is_some() {
+        return value.attr("itemtype").unwrap_or_default().trim().into();
+    }
+
+    let attribute = match value.name() {
+        "meta" => value.attr("content"),
+        "a" | "area" | "link" => value.attr("href"),
+        "audio" | "embed" | "iframe" | "img" | "source" | "track" | "video" => value.attr("src"),
+        "object" => value.attr("data"),
+        "data" | "meter" => value.attr("value"),
+        "time" => value.attr("datetime"),
+        _ => None,
+    };
+
+    match attribute {
+        Some(attribute) => attribute.trim().into(),
+        None => element
+            .text()
+            .map(str::trim)
+            .filter(|text| !text.is_empty())
+            .collect::<Vec<_>>()
+            .join(" ")
+            .into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extract_meta_tags() {
+        let html = Html::parse_document(
+            r#"<html lang="en-GB"><head>
+<meta name="Description" content="A page about things.">
+<meta property="og:description" content="Things.">
+<meta property="og:url" content="https://example.com/og">
+<meta property="og:type" content="article">
+<link rel="canonical" href="https://example.com/things">
+<meta name="twitter:card" content="summary">
+<meta name="twitter:creator" content="@example">
+<meta name="twitter:image" content="https://example.com/card.png">
+<meta name="author" content="Jane Doe">
+<meta property="article:published_time" content="2020-01-02T03:04:05+01:00">
+<meta name="dcterms.modified" content="2020-02-01">
+</head><body></body></html>"#,
+        );
+
+        let metadata = Metadata::extract(&html);
+
+        assert_eq!(metadata.lang.as_deref(), Some("en-GB"));
+        assert_eq!(
+            metadata.description.as_deref(),
+            Some("A page about things.")
+        );
+        assert_eq!(
+            metadata.canonical_url.as_deref(),
+            Some("https://example.com/things")
+        );
+        assert_eq!(metadata.author.as_deref(), Some("Jane Doe"));
+        assert_eq!(
+            metadata.published,
+            Some("2020-01-02T02:04:05Z".parse().unwrap())
+        );
+        assert_eq!(
+            metadata.modified,
+            Some("2020-02-01T00:00:00Z".parse().unwrap())
+        );
+        assert_eq!(
+            metadata.open_graph,
+            vec![
+                ("description".into(), "Things.".into()),
+                ("url".into(), "https://example.com/og".into()),
+                ("type".into(), "article".into())
+            ]
+        );
+        assert_eq!(
+            metadata.twitter,
+            vec![
+                ("card".into(), "summary".into()),
+                ("creator".into(), "@example".into()),
+                ("image".into(), "https://example.com/card.png".into())
+            ]
+        );
+        assert_eq!(metadata.types(), BTreeSet::from(["article".to_string()]));
+        assert_eq!(metadata.image(), Some("https://example.com/card.png"));
+    }
+
+    #[test]
+    fn extract_structured_data() {
+        let html = Html::parse_document(
+            r#"<html><head>
+<meta property="og:description" content="Fallback description">
+<script type="application/ld+json">
+{"@context": "https://schema.org", "@graph": [
+  {"@type": "WebSite", "name": "Example"},
+  {"@type": "NewsArticle", "author": [{"@type": "Person", "name": "John Roe"}], "datePublished": "2021-05-06T07:08:09Z"}
+]}
+</script>
+<script type="application/ld+json">{ not json</script>
+</head><body>
+<div itemscope itemtype="https://schema.org/Article">
+  <h1 itemprop="headline">Headline</h1>
+  <time itemprop="dateModified" datetime="2021-06-01T00:00:00">June</time>
+  <div itemprop="publisher" itemscope itemtype="https://schema.org/Organization">
+    <span itemprop="name">Publisher</span>
+  </div>
+</div>
+</body></html>"#,
+        );
+
+        let metadata = Metadata::extract(&html);
+
+        assert_eq!(
+            metadata.description.as_deref(),
+            Some("Fallback description")
+        );
+        assert_eq!(metadata.json_ld.len(), 1);
+        assert_eq!(metadata.author.as_deref(), Some("John Roe"));
+        assert_eq!(
+            metadata.published,
+            Some("2021-05-06T07:08:09Z".parse().unwrap())
+        );
+        assert_eq!(
+            metadata.modified,
+            Some("2021-06-01T00:00:00Z".parse().unwrap())
+        );
+        assert_eq!(
+            metadata.microdata,
+            vec![
+                MicrodataItem {
+                    item_type: Some("https://schema.org/Article".into()),
+                    properties: vec![
+                        ("headline".into(), "Headline".into()),
+                        ("dateModified".into(), "2021-06-01T00:00:00".into()),
+                        ("publisher".into(), "https://schema.org/Organization".into())
+                    ],
+                },
+                MicrodataItem {
+                    item_type: Some("https://schema.org/Organization".into()),
+                    properties: vec![("name".into(), "Publisher".into())],
+                }
+            ]
+        );
+        assert_eq!(
+            metadata.types(),
+            BTreeSet::from([
+                "article".to_string(),
+                "newsarticle".to_string(),
+                "organization".to_string(),
+                "website".to_string()
+            ])
+        );
+        assert_eq!(metadata.image(), None);
+    }
+}
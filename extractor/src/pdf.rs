@@ -0,0 +1,131 @@
+//! PDF documents.
+
+use pdf_extract::{Document, OutputError, PlainTextOutput};
+use std::panic::AssertUnwindSafe;
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("PDF error")]
+    Pdf(#[from] pdf_extract::Error),
+    #[error("PDF output error")]
+    Output(#[from] OutputError),
+    #[error("PDF extraction panicked")]
+    Panic,
+}
+
+/// The text of a PDF document.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Text {
+    /// The `Title` entry of the document information dictionary.
+    pub title: Option<String>,
+    pub paragraphs: Vec<String>,
+}
+
+/// Extract the text of a PDF document (which must not require a password).
+///
+/// The parser panics on some malformed documents, so panics are caught and returned as errors.
+pub fn extract(bytes: &[u8]) -> Result<Text, Error> {
+    std::panic::catch_unwind(AssertUnwindSafe(|| extract_unchecked(bytes)))
+        .unwrap_or(Err(Error::Panic))
+}
+
+fn extract_unchecked(bytes: &[u8]) -> Result<Text, Error> {
+    let mut document = Document::load_mem(bytes)?;
+
+    if document.is_encrypted() {
+        document.decrypt("")?;
+    }
+
+    let title = document
+        .trailer
+        .get(b"Info")
+        .and_then(|info| document.dereference(info))
+        .and_then(|(_, info)| info.as_dict())
+        .and_then(|info| info.get_deref(b"Title", &document))
+        .and_then(pdf_extract::decode_text_string)
+        .ok()
+        .map(|title| title.trim().to_string())
+        .filter(|title| !title.is_empty());
+
+    let mut contents = String::new();
+    pdf_extract::output_doc(&document, &mut PlainTextOutput::new(&mut contents))?;
+
+    Ok(Text {
+        title,
+        paragraphs: crate::text::paragraphs(&contents),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pdf_extract::{
+        content::{Content, Operation},
+        dictionary, Object, Stream, StringFormat,
+    };
+
+    fn document(title: &str, text: &str) -> Vec<u8> {
+        let mut document = Document::with_version("1.5");
+        let pages_id = document.new_object_id();
+        let font_id = document.add_object(dictionary! {
+            "Type" => "Font",
+            "Subtype" => "Type1",
+            "BaseFont" => "Helvetica",
+        });
+        let resources_id = document.add_object(dictionary! {
+            "Font" => dictionary! { "F1" => font_id },
+        });
+        let content = Content {
+            operations: vec![
+                Operation::new("BT", vec![]),
+                Operation::new("Tf", vec!["F1".into(), 12.into()]),
+                Operation::new("Td", vec![72.into(), 720.into()]),
+                Operation::new("Tj", vec![Object::string_literal(text)]),
+                Operation::new("ET", vec![]),
+            ],
+        };
+        let content_id =
+            document.add_object(Stream::new(dictionary! {}, content.encode().unwrap()));
+        let page_id = document.add_object(dictionary! {
+            "Type" => "Page",
+            "Parent" => pages_id,
+            "Contents" => content_id,
+            "Resources" => resources_id,
+            "MediaBox" => vec![0.into(), 0.into(), 612.into(), 792.into()],
+        });
+        document.objects.insert(
+            pages_id,
+            Object::Dictionary(dictionary! {
+                "Type" => "Pages",
+                "Kids" => vec![page_id.into()],
+                "Count" => 1,
+            }),
+        );
+        let catalog_id = document.add_object(dictionary! {
+            "Type" => "Catalog",
+            "Pages" => pages_id,
+        });
+        let info_id = document.add_object(dictionary! {
+            "Title" => Object::String(title.as_bytes().to_vec(), StringFormat::Literal),
+        });
+        document.trailer.set("Root", catalog_id);
+        document.trailer.set("Info", info_id);
+
+        let mut bytes = vec![];
+        document.save_to(&mut bytes).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn extract_text() {
+        let text = extract(&document("A report", "Hello from a PDF")).unwrap();
+
+        assert_eq!(text.title.as_deref(), Some("A report"));
+        assert_eq!(text.paragraphs, vec!["Hello from a PDF"]);
+    }
+
+    #[test]
+    fn extract_invalid() {
+        assert!(extract(b"not a PDF").is_err());
+    }
+}
@@ -0,0 +1,201 @@
+//! Embedded resources (images, stylesheets and scripts) needed to render a page.
+
+use once_cell::sync::Lazy;
+use scraper::{Html, Selector};
+use std::collections::HashSet;
+use std::fmt::Display;
+use std::str::FromStr;
+use url::Url;
+
+static BASE_SEL: Lazy<Selector> = Lazy::new(|| Selector::parse(r#"base[href]"#).unwrap());
+static IMG_SEL: Lazy<Selector> = Lazy::new(|| Selector::parse(r#"img, source"#).unwrap());
+static LINK_SEL: Lazy<Selector> = Lazy::new(|| Selector::parse(r#"link[href]"#).unwrap());
+static SCRIPT_SEL: Lazy<Selector> = Lazy::new(|| Selector::parse(r#"script[src]"#).unwrap());
+
+#[derive(thiserror::Error, Debug)]
+#[error("Invalid requisite kind: {0}")]
+pub struct Error(String);
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum RequisiteKind {
+    Image,
+    Stylesheet,
+    Script,
+}
+
+impl RequisiteKind {
+    pub const ALL: [Self; 3] = [Self::Image, Self::Stylesheet, Self::Script];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Image => "image",
+            Self::Stylesheet => "stylesheet",
+            Self::Script => "script",
+        }
+    }
+}
+
+impl Display for RequisiteKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for RequisiteKind {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|kind| kind.as_str() == s)
+            .ok_or_else(|| Error(s.to_string()))
+    }
+}
+
+/// A resource referenced by a page, with its URL resolved to an absolute one.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Requisite {
+    pub url: String,
+    pub kind: RequisiteKind,
+}
+
+/// Find the images, stylesheets and scripts referenced by a page.
+///
+/// Relative URLs are resolved against the page's URL (or its `<base>`
+/// element), only HTTP(S) URLs are returned, and each URL appears once.
+pub fn requisites(html: &Html, page_url: &str) -> Vec<Requisite> {
+    let page_url = match Url::parse(page_url) {
+        Ok(url) => url,
+        Err(_) => return vec![],
+    };
+
+    let base_url = html
+        .select(&BASE_SEL)
+        .next()
+        .and_then(|element| element.attr("href"))
+        .and_then(|href| page_url.join(href.trim()).ok())
+        .unwrap_or(page_url);
+
+    let images = html.select(&IMG_SEL).flat_map(|element| {
+        element
+            .attr("src")
+            .into_iter()
+            .chain(element.attr("srcset").into_iter().flat_map(srcset_urls))
+            .map(|url| (url, RequisiteKind::Image))
+    });
+
+    let links = html.select(&LINK_SEL).filter_map(|element| {
+        let rel = element.attr("rel").unwrap_or_default().to_ascii_lowercase();
+        let kind = if rel.split_whitespace().any(|value| value == "stylesheet") {
+            Some(RequisiteKind::Stylesheet)
+        } else if rel.split_whitespace().any(|value| value == "icon") {
+            Some(RequisiteKind::Image)
+        } else {
+            None
+        };
+
+        kind.zip(element.attr("href"))
+            .map(|(kind, href)| (href, kind))
+    });
+
+    let scripts = html
+        .select(&SCRIPT_SEL)
+        .filter_map(|element| element.attr("src"))
+        .map(|src| (src, RequisiteKind::Script));
+
+    let mut seen = HashSet::new();
+
+    links
+        .chain(scripts)
+        .chain(images)
+        .filter_map(|(value, kind)| {
+            let mut url = base_url.join(value.trim()).ok()?;
+            url.set_fragment(None);
+
+            if url.scheme() == "http" || url.scheme() == "https" {
+                Some(Requisite {
+                    url: url.to_string(),
+                    kind,
+                })
+            } else {
+                None
+            }
+        })
+        .filter(|requisite| seen.insert(requisite.url.clone()))
+        .collect()
+}
+
+/// The URLs in a `srcset` attribute value (ignoring width and density descriptors).
+fn srcset_urls(value: &str) -> impl Iterator<Item = &str> {
+    value
+        .split(',')
+        .filter_map(|candidate| candidate.split_whitespace().next())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn find_requisites() {
+        let html = Html::parse_document(
+            r#"<html><head>
+            <link rel="stylesheet" href="/style.css">
+            <link rel="shortcut icon" href="favicon.ico">
+            <link rel="alternate" href="/feed.xml">
+            <script src="https://cdn.example.com/app.js"></script>
+            <script>var inline = true;</script>
+            </head><body>
+            <img src="images/logo.png#top">
+            <img src="data:image/png;base64,AAAA">
+            <picture><source srcset="a.png 1x, /b.png 2x"></picture>
+            <img src="/style.css">
+            </body></html>"#,
+        );
+
+        let found = requisites(&html, "https://example.com/dir/page")
+            .into_iter()
+            .map(|requisite| (requisite.url, requisite.kind))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            found,
+            vec![
+                (
+                    "https://example.com/style.css".to_string(),
+                    RequisiteKind::Stylesheet
+                ),
+                (
+                    "https://example.com/dir/favicon.ico".to_string(),
+                    RequisiteKind::Image
+                ),
+                (
+                    "https://cdn.example.com/app.js".to_string(),
+                    RequisiteKind::Script
+                ),
+                (
+                    "https://example.com/dir/images/logo.png".to_string(),
+                    RequisiteKind::Image
+                ),
+                (
+                    "https://example.com/dir/a.png".to_string(),
+                    RequisiteKind::Image
+                ),
+                (
+                    "https://example.com/b.png".to_string(),
+                    RequisiteKind::Image
+                ),
+            ]
+        );
+
+        let with_base = Html::parse_document(
+            r#"<html><head><base href="https://static.example.com/"></head>
+            <body><img src="x.gif"></body></html>"#,
+        );
+
+        assert_eq!(
+            requisites(&with_base, "https://example.com/")[0].url,
+            "https://static.example.com/x.gif"
+        );
+    }
+}
@@ -0,0 +1,57 @@
+//! Site-specific extractors for pages whose generic extraction loses important fields.
+//!
+//! Extractors are chosen by the captured page's URL (e.g. `https://twitter.com/jack/status/20`).
+
+use scraper::Html;
+use url::Url;
+
+pub mod twitter;
+
+/// Typed fields extracted by a site-specific extractor.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum SiteData {
+    TwitterStatus(twitter::Status),
+}
+
+impl SiteData {
+    /// The main text of the page, if the extractor identified one.
+    pub fn text(&self) -> Option<&str> {
+        match self {
+            Self::TwitterStatus(status) => status.text.as_deref(),
+        }
+    }
+}
+
+pub trait SiteExtractor: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// Whether this extractor handles pages captured for the given URL.
+    fn handles(&self, url: &Url) -> bool;
+
+    /// Extract fields from a page (which may still fail for unrecognized layouts).
+    fn extract(&self, url: &Url, html: &Html) -> Option<SiteData>;
+}
+
+/// A list of site-specific extractors, tried in order.
+pub struct Extractors {
+    extractors: Vec<Box<dyn SiteExtractor>>,
+}
+
+impl Default for Extractors {
+    fn default() -> Self {
+        Self::new(vec![Box::new(twitter::Twitter)])
+    }
+}
+
+impl Extractors {
+    pub fn new(extractors: Vec<Box<dyn SiteExtractor>>) -> Self {
+        Self { extractors }
+    }
+
+    pub fn extract(&self, url: &Url, html: &Html) -> Option<SiteData> {
+        self.extractors
+            .iter()
+            .filter(|extractor| extractor.handles(url))
+            .find_map(|extractor| extractor.extract(url, html))
+    }
+}
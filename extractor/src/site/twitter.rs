@@ -0,0 +1,353 @@
+//! Twitter status pages, in both the legacy (server-rendered) and modern (React) layouts.
+//!
+//! The author and status ID are taken from the page where possible and from the URL otherwise,
+//! since the modern layout is often captured with little more than its meta tags.
+
+use super::{SiteData, SiteExtractor};
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use scraper::{ElementRef, Html, Selector};
+use url::Url;
+
+const HOSTS: [&str; 4] = ["twitter.com", "mobile.twitter.com", "x.com", "mobile.x.com"];
+
+static STATUS_PATH_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^/([A-Za-z0-9_]{1,15})/status(?:es)?/(\d+)").unwrap());
+static STATUS_HREF_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^(?:https?://(?:www\.|mobile\.)?(?:twitter|x)\.com)?/([A-Za-z0-9_]{1,15})/status(?:es)?/(\d+)")
+        .unwrap()
+});
+static SCREEN_NAME_HREF_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^(?:https?://(?:www\.|mobile\.)?(?:twitter|x)\.com)?/([A-Za-z0-9_]{1,15})/?$")
+        .unwrap()
+});
+
+// Legacy layout (roughly 2012 to 2020).
+static LEGACY_TWEET_SEL: Lazy<Selector> =
+    Lazy::new(|| Selector::parse(r#".permalink-tweet[data-tweet-id]"#).unwrap());
+static LEGACY_TEXT_SEL: Lazy<Selector> =
+    Lazy::new(|| Selector::parse(r#".js-tweet-text, .tweet-text"#).unwrap());
+static LEGACY_TIME_SEL: Lazy<Selector> =
+    Lazy::new(|| Selector::parse(r#"._timestamp[data-time]"#).unwrap());
+static LEGACY_REPLY_SEL: Lazy<Selector> = Lazy::new(|| {
+    Selector::parse(
+        r#".ReplyingToContextBelowAuthor a[href], .ReplyingToContextAboveAuthor a[href]"#,
+    )
+    .unwrap()
+});
+
+// Modern layout (from 2019).
+static MODERN_TWEET_SEL: Lazy<Selector> =
+    Lazy::new(|| Selector::parse(r#"article[data-testid="tweet"]"#).unwrap());
+static MODERN_TEXT_SEL: Lazy<Selector> =
+    Lazy::new(|| Selector::parse(r#"[data-testid="tweetText"]"#).unwrap());
+static MODERN_TIME_SEL: Lazy<Selector> =
+    Lazy::new(|| Selector::parse(r#"time[datetime]"#).unwrap());
+static MODERN_SOCIAL_CONTEXT_SEL: Lazy<Selector> =
+    Lazy::new(|| Selector::parse(r#"[data-testid="socialContext"]"#).unwrap());
+static LINK_SEL: Lazy<Selector> = Lazy::new(|| Selector::parse(r#"a[href]"#).unwrap());
+static DIV_SEL: Lazy<Selector> = Lazy::new(|| Selector::parse(r#"div"#).unwrap());
+static OG_DESCRIPTION_SEL: Lazy<Selector> =
+    Lazy::new(|| Selector::parse(r#"meta[property="og:description"][content]"#).unwrap());
+
+/// A single status, as shown on its own page.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Status {
+    pub status_id: u64,
+    /// The screen name of the author (as given, not lowercased).
+    pub author: String,
+    pub text: Option<String>,
+    pub posted_at: Option<DateTime<Utc>>,
+    /// The screen name of the account being replied to.
+    pub in_reply_to: Option<String>,
+    /// The screen name of the account that retweeted the status, if it was shown as a retweet.
+    pub retweeted_by: Option<String>,
+}
+
+pub struct Twitter;
+
+impl SiteExtractor for Twitter {
+    fn name(&self) -> &'static str {
+        "twitter"
+    }
+
+    fn handles(&self, url: &Url) -> bool {
+        status_from_url(url).is_some()
+    }
+
+    fn extract(&self, url: &Url, html: &Html) -> Option<SiteData> {
+        let (url_author, url_status_id) = status_from_url(url)?;
+
+        let status = extract_legacy(html, url_status_id)
+            .or_else(|| extract_modern(html, &url_author, url_status_id))
+            .unwrap_or_else(|| Status {
+                status_id: url_status_id,
+                author: url_author,
+                text: og_description(html),
+                posted_at: None,
+                in_reply_to: None,
+                retweeted_by: None,
+            });
+
+        Some(SiteData::TwitterStatus(status))
+    }
+}
+
+/// The screen name and status ID of a status page, including old `#!/` URLs.
+fn status_from_url(url: &Url) -> Option<(String, u64)> {
+    let host = url.host_str()?.to_ascii_lowercase();
+
+    if !HOSTS.contains(&host.strip_prefix("www.").unwrap_or(&host)) {
+        return None;
+    }
+
+    let path = match url
+        .fragment()
+        .and_then(|fragment| fragment.strip_prefix('!'))
+    {
+        Some(path) if url.path() == "/" => path,
+        _ => url.path(),
+    };
+    let captures = STATUS_PATH_RE.captures(path)?;
+
+    Some((captures[1].to_string(), captures[2].parse().ok()?))
+}
+
+fn extract_legacy(html: &Html, status_id: u64) -> Option<Status> {
+    let tweet = html.select(&LEGACY_TWEET_SEL).find(|tweet| {
+        tweet
+            .value()
+            .attr("data-tweet-id")
+            .and_then(|id| id.parse::<u64>().ok())
+            == Some(status_id)
+    })?;
+    let value = tweet.value();
+
+    let author = value.attr("data-screen-name")?.to_string();
+    let text = tweet
+        .select(&LEGACY_TEXT_SEL)
+        .next()
+        .map(|element| element_text(&element));
+    let posted_at = tweet
+        .select(&LEGACY_TIME_SEL)
+        .filter_map(|element| element.value().attr("data-time"))
+        .find_map(|value| value.parse::<i64>().ok())
+        .and_then(|value| DateTime::from_timestamp(value, 0));
+    let in_reply_to = tweet
+        .select(&LEGACY_REPLY_SEL)
+        .filter_map(|element| element.value().attr("href"))
+        .find_map(screen_name_from_href);
+    let retweeted_by = value.attr("data-retweeter").map(str::to_string);
+
+    Some(Status {
+        status_id,
+        author,
+        text,
+        posted_at,
+        in_reply_to,
+        retweeted_by,
+    })
+}
+
+fn extract_modern(html: &Html, url_author: &str, status_id: u64) -> Option<Status> {
+    let status_id_str = status_id.to_string();
+
+    // The first article linking to its own status is the focal tweet (earlier ones are the
+    // conversation it replies to).
+    let tweet = html
+        .select(&MODERN_TWEET_SEL)
+        .find(|tweet| status_link(tweet).is_some_and(|(_, id)| id == status_id_str))?;
+
+    let author = status_link(&tweet)
+        .map(|(author, _)| author)
+        .unwrap_or_else(|| url_author.to_string());
+    let text = tweet
+        .select(&MODERN_TEXT_SEL)
+        .next()
+        .map(|element| element_text(&element));
+    let posted_at = tweet
+        .select(&MODERN_TIME_SEL)
+        .filter_map(|element| element.value().attr("datetime"))
+        .find_map(|value| DateTime::parse_from_rfc3339(value).ok())
+        .map(|value| value.with_timezone(&Utc));
+    let in_reply_to = tweet
+        .select(&DIV_SEL)
+        .find(|element| {
+            element
+                .text()
+                .next()
+                .is_some_and(|text| text.trim_start().starts_with("Replying to"))
+        })
+        .and_then(|element| {
+            element
+                .select(&LINK_SEL)
+                .filter_map(|link| link.value().attr("href"))
+                .find_map(screen_name_from_href)
+        });
+    let retweeted_by = tweet
+        .select(&MODERN_SOCIAL_CONTEXT_SEL)
+        .next()
+        .filter(|element| {
+            let text = element_text(element);
+
+            text.ends_with("Retweeted") || text.ends_with("reposted")
+        })
+        .and_then(|element| {
+            element
+                .ancestors()
+                .filter_map(ElementRef::wrap)
+                .find(|ancestor| ancestor.value().name() == "a")
+                .or_else(|| element.select(&LINK_SEL).next())
+                .and_then(|link| link.value().attr("href"))
+                .and_then(screen_name_from_href)
+        });
+
+    Some(Status {
+        status_id,
+        author,
+        text,
+        posted_at,
+        in_reply_to,
+        retweeted_by,
+    })
+}
+
+/// The author and status ID from the first status link in a tweet (the timestamp link).
+fn status_link(tweet: &ElementRef) -> Option<(String, String)> {
+    tweet
+        .select(&LINK_SEL)
+        .filter_map(|link| link.value().attr("href"))
+        .find_map(|href| {
+            let captures = STATUS_HREF_RE.captures(href)?;
+
+            Some((
+                captures.get(1)?.as_str().to_string(),
+                captures.get(2)?.as_str().to_string(),
+            ))
+        })
+}
+
+fn screen_name_from_href(href: &str) -> Option<String> {
+    SCREEN_NAME_HREF_RE
+        .captures(href)
+        .and_then(|captures| captures.get(1))
+        .map(|value| value.as_str().to_string())
+}
+
+fn og_description(html: &Html) -> Option<String> {
+    html.select(&OG_DESCRIPTION_SEL)
+        .filter_map(|element| element.value().attr("content"))
+        .map(|value| {
+            value
+                .trim()
+                .trim_matches(|ch| ch == '“' || ch == '”')
+                .trim()
+        })
+        .find(|value| !value.is_empty())
+        .map(str::to_string)
+}
+
+fn element_text(element: &ElementRef) -> String {
+    element
+        .text()
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn url(value: &str) -> Url {
+        Url::parse(value).unwrap()
+    }
+
+    #[test]
+    fn handles() {
+        assert!(Twitter.handles(&url("https://twitter.com/jack/status/20")));
+        assert!(Twitter.handles(&url("http://mobile.twitter.com/jack/status/20")));
+        assert!(Twitter.handles(&url("https://www.twitter.com/jack/statuses/20")));
+        assert!(Twitter.handles(&url("http://twitter.com/#!/jack/status/20")));
+        assert!(!Twitter.handles(&url("https://twitter.com/jack")));
+        assert!(!Twitter.handles(&url("https://example.com/jack/status/20")));
+    }
+
+    #[test]
+    fn extract_legacy_layout() {
+        let html = Html::parse_document(
+            r#"<html><body>
+<div class="tweet permalink-tweet js-original-tweet" data-tweet-id="1234567890" data-screen-name="Example" data-name="Example User" data-conversation-id="1234567800">
+  <div class="ReplyingToContextBelowAuthor">Replying to <a class="pretty-link" href="/Other"><b>@Other</b></a></div>
+  <p class="TweetTextSize js-tweet-text tweet-text">Hello, <a href="/hashtag/world">#world</a></p>
+  <span class="_timestamp js-short-timestamp" data-time="1500000000">14 Jul 2017</span>
+</div>
+</body></html>"#,
+        );
+
+        assert_eq!(
+            Twitter.extract(&url("https://twitter.com/example/status/1234567890"), &html),
+            Some(SiteData::TwitterStatus(Status {
+                status_id: 1234567890,
+                author: "Example".to_string(),
+                text: Some("Hello, #world".to_string()),
+                posted_at: DateTime::from_timestamp(1500000000, 0),
+                in_reply_to: Some("Other".to_string()),
+                retweeted_by: None,
+            }))
+        );
+    }
+
+    #[test]
+    fn extract_modern_layout() {
+        let html = Html::parse_document(
+            r#"<html><body>
+<article data-testid="tweet">
+  <a href="/Other/status/1000">
+    <time datetime="2020-01-01T00:00:00.000Z">Jan 1, 2020</time>
+  </a>
+  <div data-testid="tweetText">The original</div>
+</article>
+<article data-testid="tweet">
+  <a href="/Example"><span>Example User</span></a>
+  <div><div>Replying to <a href="/Other">@Other</a></div></div>
+  <div data-testid="tweetText"><span>A reply</span> <span>in two parts</span></div>
+  <a href="/Example/status/2000"><time datetime="2021-02-03T04:05:06.000Z">Feb 3, 2021</time></a>
+</article>
+</body></html>"#,
+        );
+
+        assert_eq!(
+            Twitter.extract(&url("https://twitter.com/example/status/2000"), &html),
+            Some(SiteData::TwitterStatus(Status {
+                status_id: 2000,
+                author: "Example".to_string(),
+                text: Some("A reply in two parts".to_string()),
+                posted_at: Some("2021-02-03T04:05:06Z".parse().unwrap()),
+                in_reply_to: Some("Other".to_string()),
+                retweeted_by: None,
+            }))
+        );
+    }
+
+    #[test]
+    fn extract_meta_only() {
+        let html = Html::parse_document(
+            r#"<html><head><meta property="og:description" content="“Just setting up my twttr”"></head><body></body></html>"#,
+        );
+
+        assert_eq!(
+            Twitter.extract(&url("https://twitter.com/Jack/status/20"), &html),
+            Some(SiteData::TwitterStatus(Status {
+                status_id: 20,
+                author: "Jack".to_string(),
+                text: Some("Just setting up my twttr".to_string()),
+                posted_at: None,
+                in_reply_to: None,
+                retweeted_by: None,
+            }))
+        );
+    }
+}
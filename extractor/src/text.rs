@@ -0,0 +1,40 @@
+//! Plain text documents.
+
+/// Split text into paragraphs (separated by blank lines), with whitespace normalized.
+pub fn paragraphs(text: &str) -> Vec<String> {
+    let mut paragraphs = vec![];
+    let mut current: Vec<&str> = vec![];
+
+    for line in text.lines() {
+        let mut words = line.split_whitespace().peekable();
+
+        if words.peek().is_none() {
+            if !current.is_empty() {
+                paragraphs.push(current.join(" "));
+                current.clear();
+            }
+        } else {
+            current.extend(words);
+        }
+    }
+
+    if !current.is_empty() {
+        paragraphs.push(current.join(" "));
+    }
+
+    paragraphs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_paragraphs() {
+        assert_eq!(
+            paragraphs("\n  First line\nwrapped   here.\n\n\n\tSecond\r\n \nThird\n"),
+            vec!["First line wrapped here.", "Second", "Third"]
+        );
+        assert!(paragraphs(" \n\n").is_empty());
+    }
+}
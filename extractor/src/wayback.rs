@@ -0,0 +1,183 @@
+//! Removal of the Wayback Machine's additions to rewritten (`if_`) captures.
+//!
+//! Rewritten content includes injected scripts and stylesheets, the toolbar,
+//! trailing archive comments, and URLs that point into the archive
+//! (`/web/<timestamp>/<url>`). Original (`id_`) content contains none of these,
+//! so cleaning it is a no-op.
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::borrow::Cow;
+
+const REWRITE_INCLUDE_END_MARKER: &str = "<!-- End Wayback Rewrite JS Include -->";
+const TOOLBAR_START_MARKER: &str = "<!-- BEGIN WAYBACK TOOLBAR INSERT -->";
+const ARCHIVED_COMMENT_MARKER: &str = "FILE ARCHIVED ON ";
+
+static REWRITE_INCLUDE_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r#"(?s)<script[^>]*src="[^"]*(?:archive\.org/|/_static/)[^"]*"[^>]*>.*?<!-- End Wayback Rewrite JS Include -->\s*"#,
+    )
+    .unwrap()
+});
+
+static TOOLBAR_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"(?s)<!-- BEGIN WAYBACK TOOLBAR INSERT -->.*?<!-- END WAYBACK TOOLBAR INSERT -->\s*",
+    )
+    .unwrap()
+});
+
+static COMMENT_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?s)\s*<!--\s*(?:FILE ARCHIVED ON |playback timings \(ms\):).*?-->").unwrap()
+});
+
+static ARCHIVED_URL_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"(?:(?:https?:)?//web\.archive\.org)?/web/\d{1,14}(?:[a-z]{2}_|\*)?/(?P<url>(?:https?:)?//)",
+    )
+    .unwrap()
+});
+
+/// Relative archive links in attributes whose targets have no scheme (`/web/<timestamp>/example.com/`).
+static RELATIVE_ARCHIVED_ATTR_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r#"(?i)(?P<prefix>\b(?:href|src)\s*=\s*["']?)/web/\d{1,14}(?:[a-z]{2}_)?/(?P<url>[a-z0-9-]+(?:\.[a-z0-9-]+)+(?:[:/?#][^"'\s>]*)?)"#,
+    )
+    .unwrap()
+});
+
+static ARCHIVED_LINK_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^(?:https?:)?//web\.archive\.org/web/\d{1,14}(?:[a-z]{2}_|\*)?/(?P<url>.+)$")
+        .unwrap()
+});
+
+/// Whether the content appears to have been rewritten by the Wayback Machine.
+pub fn is_rewritten(contents: &str) -> bool {
+    contents.contains(REWRITE_INCLUDE_END_MARKER)
+        || contents.contains(TOOLBAR_START_MARKER)
+        || contents.contains(ARCHIVED_COMMENT_MARKER)
+}
+
+/// Remove injected content and restore archived URLs to their originals.
+///
+/// Content that does not appear to have been rewritten is returned unchanged.
+pub fn clean(contents: &str) -> Cow<'_, str> {
+    if !is_rewritten(contents) {
+        return Cow::Borrowed(contents);
+    }
+
+    let cleaned = REWRITE_INCLUDE_RE.replace_all(contents, "");
+    let cleaned = TOOLBAR_RE.replace_all(&cleaned, "");
+    let cleaned = COMMENT_RE.replace_all(&cleaned, "");
+    let cleaned = ARCHIVED_URL_RE.replace_all(&cleaned, "${url}");
+    let cleaned = RELATIVE_ARCHIVED_ATTR_RE.replace_all(&cleaned, "${prefix}http://${url}");
+
+    Cow::Owned(cleaned.into_owned())
+}
+
+/// The original URL of an absolute link into the archive
+/// (`https://web.archive.org/web/<timestamp>/<url>`), if it is one.
+///
+/// Relative archive links are only meaningful in rewritten captures, where
+/// [`clean`] has already restored them. Targets without a scheme (which the
+/// Wayback Machine accepts) are assumed to be HTTP, and targets without a host
+/// are ignored.
+pub fn original_url(value: &str) -> Option<Cow<'_, str>> {
+    let url = ARCHIVED_LINK_RE
+        .captures(value.trim())?
+        .name("url")?
+        .as_str();
+
+    let url = if url.starts_with("http://") || url.starts_with("https://") {
+        Cow::Borrowed(url)
+    } else {
+        Cow::Owned(format!("http://{}", url.trim_start_matches('/')))
+    };
+
+    url::Url::parse(&url)
+        .ok()
+        .filter(|parsed| parsed.host_str().is_some_and(|host| !host.is_empty()))
+        .map(|_| url)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clean_rewritten() {
+        let rewritten = r#"<html><head>
+<script src="//archive.org/includes/athena.js" type="text/javascript"></script>
+<script type="text/javascript">window.addEventListener('DOMContentLoaded',function(){});</script>
+<script type="text/javascript" src="/_static/js/bundle-playback.js?v=1WaXNDFE" charset="utf-8"></script>
+<link rel="stylesheet" type="text/css" href="/_static/css/banner-styles.css?v=S1zqJCYt" />
+<!-- End Wayback Rewrite JS Include -->
+<title>Example</title>
+<link rel="stylesheet" href="https://web.archive.org/web/20200101000000cs_/https://example.com/style.css">
+</head><body>
+<!-- BEGIN WAYBACK TOOLBAR INSERT -->
+<div id="wm-ipp-base">Toolbar</div>
+<!-- END WAYBACK TOOLBAR INSERT -->
+<a href="/web/20200101000000/https://www.iana.org/domains/example">More</a>
+<img src="//web.archive.org/web/20200101000000im_/http://example.com/logo.png">
+<a href="/web/20200101000000/example.com/about?x=1">About</a>
+<a href="/web/2020*/example.com">Not a capture</a>
+</body></html>
+<!--
+     FILE ARCHIVED ON 00:00:00 Jan 01, 2020 AND RETRIEVED FROM THE
+     INTERNET ARCHIVE ON 12:00:00 Jun 01, 2024.
+-->
+<!--
+playback timings (ms):
+  captures_list: 0.5
+-->"#;
+
+        assert!(is_rewritten(rewritten));
+
+        assert_eq!(
+            clean(rewritten),
+            r#"<html><head>
+<title>Example</title>
+<link rel="stylesheet" href="https://example.com/style.css">
+</head><body>
+<a href="https://www.iana.org/domains/example">More</a>
+<img src="http://example.com/logo.png">
+<a href="http://example.com/about?x=1">About</a>
+<a href="/web/2020*/example.com">Not a capture</a>
+</body></html>"#
+        );
+    }
+
+    #[test]
+    fn original_urls() {
+        assert_eq!(
+            original_url("https://web.archive.org/web/20160101000000/https://example.com/a?b=c")
+                .as_deref(),
+            Some("https://example.com/a?b=c")
+        );
+        assert_eq!(
+            original_url("https://web.archive.org/web/2016id_/example.com/").as_deref(),
+            Some("http://example.com/")
+        );
+        assert_eq!(
+            original_url("//web.archive.org/web/2016*///example.com/").as_deref(),
+            Some("http://example.com/")
+        );
+        assert_eq!(original_url("/about"), None);
+        assert_eq!(original_url("https://example.com/web/about"), None);
+        // Relative links may be a site's own paths in original captures.
+        assert_eq!(original_url("/web/2016/about"), None);
+        assert_eq!(
+            original_url("https://web.archive.org/web/2016/https:///"),
+            None
+        );
+    }
+
+    #[test]
+    fn clean_original() {
+        let original = r#"<html><body><a href="/web/about">About</a><a href="/web/2016/news.html">News</a></body></html>"#;
+
+        assert!(!is_rewritten(original));
+        assert!(matches!(clean(original), Cow::Borrowed(_)));
+    }
+}
@@ -1,15 +1,20 @@
-use aib_extractor::Document;
+use aib_extractor::{site::SiteData, Document};
 use chrono::{DateTime, Datelike, Utc};
 use indexmap::IndexMap;
-use std::collections::HashMap;
-use std::path::Path;
+use query::Range;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use tantivy::{
     collector::{FacetCollector, FacetCounts},
     directory::MmapDirectory,
     doc,
-    query::{BooleanQuery, Occur, QueryParser, RangeQuery, TermQuery, TermSetQuery},
-    schema::{Facet, IndexRecordOption, Term, Value},
+    query::{
+        BooleanQuery, EmptyQuery, Occur, PhraseQuery, QueryParser, RangeQuery, TermQuery,
+        TermSetQuery,
+    },
+    schema::{Facet, Field, IndexRecordOption, Term, Value},
+    tokenizer::{TextAnalyzer, TokenStream},
     DocAddress, IndexReader, IndexWriter, SnippetGenerator,
 };
 
@@ -24,6 +29,8 @@ pub use query::Query;
 pub use snippet::Snippet;
 
 const WRITER_BUFFER_SIZE: usize = 100_000_000;
+/// Boost for matches in the main content (relative to the full content and title).
+const MAIN_CONTENT_BOOST: f32 = 2.0;
 
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
@@ -35,6 +42,8 @@ pub enum Error {
     TantivyQuery(#[from] tantivy::query::QueryParserError),
     #[error("Tantivy directory error")]
     TantivyDirectory(#[from] tantivy::directory::error::OpenDirectoryError),
+    #[error("Tantivy read error")]
+    TantivyRead(#[from] tantivy::directory::error::OpenReadError),
     #[error("Missing snapshot ID")]
     MissingSnapshotId(DocAddress),
     #[error("Missing SURT ID")]
@@ -45,6 +54,8 @@ pub enum Error {
     MissingPattern(DocAddress),
     #[error("Missing title")]
     MissingTitle(DocAddress),
+    #[error("Index at {0:?} has an outdated schema and must be rebuilt")]
+    OutdatedSchema(PathBuf),
 }
 
 #[derive(Debug)]
@@ -62,6 +73,20 @@ pub struct SearchHit {
     pub address: DocAddress,
     pub title: String,
     pub snippet: Snippet,
+    pub description: Option<String>,
+    pub canonical_url: Option<String>,
+    pub lang: Option<String>,
+    pub author: Option<String>,
+    pub published: Option<DateTime<Utc>>,
+    pub modified: Option<DateTime<Utc>>,
+    pub status_id: Option<u64>,
+    pub status_author: Option<String>,
+    pub posted_at: Option<DateTime<Utc>>,
+    pub in_reply_to: Option<String>,
+    pub retweeted_by: Option<String>,
+    pub identifiers: Vec<String>,
+    pub types: Vec<String>,
+    pub image: Option<String>,
 }
 
 pub struct Index {
@@ -70,6 +95,8 @@ pub struct Index {
     writer: IndexWriter,
     reader: IndexReader,
     query_parser: QueryParser,
+    /// The tokenizer for text fields, for building phrase filters.
+    tokenizer: TextAnalyzer,
     pattern_slugs: Vec<String>,
     years: Vec<u16>,
 }
@@ -81,15 +108,32 @@ impl Index {
         first_year: u16,
     ) -> Result<Self, Error> {
         let schema = schema::Schema::default();
-        let index =
-            tantivy::Index::open_or_create(MmapDirectory::open(path)?, schema.schema.clone())?;
+        let directory = MmapDirectory::open(&path)?;
+
+        // Tantivy can't migrate an index to a new schema, so it has to be rebuilt.
+        if tantivy::Index::exists(&directory)?
+            && tantivy::Index::open(directory.clone())?.schema() != schema.schema
+        {
+            return Err(Error::OutdatedSchema(path.as_ref().to_path_buf()));
+        }
+
+        let index = tantivy::Index::open_or_create(directory, schema.schema.clone())?;
         let writer = index.writer(WRITER_BUFFER_SIZE)?;
         let reader = index
             .reader_builder()
             .reload_policy(tantivy::ReloadPolicy::OnCommitWithDelay)
             .try_into()?;
-        let query_parser =
-            QueryParser::for_index(&index, vec![schema.fields.title, schema.fields.content]);
+        let mut query_parser = QueryParser::for_index(
+            &index,
+            vec![
+                schema.fields.title,
+                schema.fields.main_content,
+                schema.fields.content,
+            ],
+        );
+        query_parser.set_field_boost(schema.fields.main_content, MAIN_CONTENT_BOOST);
+        // Safe because the default tokenizer is always registered.
+        let tokenizer = index.tokenizers().get("default").unwrap();
 
         let pattern_slugs = pattern_slugs
             .iter()
@@ -105,6 +149,7 @@ impl Index {
             writer,
             reader,
             query_parser,
+            tokenizer,
             pattern_slugs,
             years,
         })
@@ -189,7 +234,9 @@ impl Index {
         let mut gravatar_hashes = document.gravatar_hashes.iter().cloned().collect::<Vec<_>>();
         gravatar_hashes.sort();
 
-        let document = doc!(
+        let metadata = &document.metadata;
+
+        let mut tantivy_document = doc!(
             self.schema.fields.snapshot_id => snapshot_id,
             self.schema.fields.surt_id => surt_id,
             self.schema.fields.pattern => Facet::from(&format!("/{}", pattern_slug)),
@@ -197,10 +244,82 @@ impl Index {
             self.schema.fields.timestamp => Self::to_tantivy_date_time(timestamp),
             self.schema.fields.title => document.title.to_string(),
             self.schema.fields.content => document.content.join(" "),
+            self.schema.fields.main_content => document.main_content.join(" "),
             self.schema.fields.gravatar_hashes => gravatar_hashes.join(" ")
         );
 
-        self.writer.add_document(document)?;
+        if let Some(description) = &metadata.description {
+            tantivy_document.add_text(self.schema.fields.description, description);
+        }
+
+        if let Some(canonical_url) = &metadata.canonical_url {
+            tantivy_document.add_text(self.schema.fields.canonical_url, canonical_url);
+        }
+
+        if let Some(lang) = &metadata.lang {
+            tantivy_document.add_text(self.schema.fields.lang, lang.to_ascii_lowercase());
+        }
+
+        if let Some(author) = &metadata.author {
+            tantivy_document.add_text(self.schema.fields.author, author);
+        }
+
+        if let Some(published) = metadata.published {
+            tantivy_document.add_date(
+                self.schema.fields.published,
+                Self::to_tantivy_date_time(published),
+            );
+        }
+
+        if let Some(modified) = metadata.modified {
+            tantivy_document.add_date(
+                self.schema.fields.modified,
+                Self::to_tantivy_date_time(modified),
+            );
+        }
+
+        if let Some(SiteData::TwitterStatus(status)) = &document.site {
+            tantivy_document.add_u64(self.schema.fields.status_id, status.status_id);
+            tantivy_document.add_text(
+                self.schema.fields.status_author,
+                status.author.to_ascii_lowercase(),
+            );
+
+            if let Some(posted_at) = status.posted_at {
+                tantivy_document.add_date(
+                    self.schema.fields.posted_at,
+                    Self::to_tantivy_date_time(posted_at),
+                );
+            }
+
+            if let Some(in_reply_to) = &status.in_reply_to {
+                tantivy_document.add_text(
+                    self.schema.fields.in_reply_to,
+                    in_reply_to.to_ascii_lowercase(),
+                );
+            }
+
+            if let Some(retweeted_by) = &status.retweeted_by {
+                tantivy_document.add_text(
+                    self.schema.fields.retweeted_by,
+                    retweeted_by.to_ascii_lowercase(),
+                );
+            }
+        }
+
+        for identifier in &document.identifiers {
+            tantivy_document.add_text(self.schema.fields.identifiers, identifier.to_string());
+        }
+
+        for value in metadata.types() {
+            tantivy_document.add_text(self.schema.fields.types, value);
+        }
+
+        if let Some(image) = metadata.image() {
+            tantivy_document.add_text(self.schema.fields.image, image);
+        }
+
+        self.writer.add_document(tantivy_document)?;
 
         Ok(())
     }
@@ -220,6 +339,9 @@ impl Index {
     ) -> Result<SearchResults, Error> {
         let query = self.to_tantivy_query(query)?;
         let searcher = self.reader.searcher();
+        let mut main_content_snippet_generator =
+            SnippetGenerator::create(&searcher, &*query, self.schema.fields.main_content)?;
+        main_content_snippet_generator.set_max_num_chars(snippet_max_chars);
         let mut snippet_generator =
             SnippetGenerator::create(&searcher, &*query, self.schema.fields.content)?;
         snippet_generator.set_max_num_chars(snippet_max_chars);
@@ -243,7 +365,13 @@ impl Index {
                     .map(|(score, address)| {
                         let retrieved_document: tantivy::schema::document::TantivyDocument =
                             searcher.doc(address)?;
-                        let snippet = snippet_generator.snippet_from_doc(&retrieved_document);
+                        // Prefer snippets from the main content, falling back to the full content.
+                        let mut snippet =
+                            main_content_snippet_generator.snippet_from_doc(&retrieved_document);
+
+                        if snippet.is_empty() {
+                            snippet = snippet_generator.snippet_from_doc(&retrieved_document);
+                        }
 
                         let snapshot_id = retrieved_document
                             .get_first(self.schema.fields.snapshot_id)
@@ -266,6 +394,21 @@ impl Index {
                                 .and_then(|field| field.as_str())
                                 .ok_or_else(|| Error::MissingTitle(address))?
                                 .to_string();
+                            let text = |field| {
+                                retrieved_document
+                                    .get_first(field)
+                                    .and_then(|field| field.as_str())
+                                    .map(|value| value.to_string())
+                            };
+                            let date = |field| {
+                                retrieved_document
+                                    .get_first(field)
+                                    .and_then(|field| field.as_datetime())
+                                    .and_then(|value| {
+                                        DateTime::from_timestamp(value.into_timestamp_secs(), 0)
+                                    })
+                            };
+
                             Ok(SearchHit {
                                 score,
                                 snapshot_id,
@@ -273,6 +416,30 @@ impl Index {
                                 address,
                                 title,
                                 snippet: (&snippet).into(),
+                                description: text(self.schema.fields.description),
+                                canonical_url: text(self.schema.fields.canonical_url),
+                                lang: text(self.schema.fields.lang),
+                                author: text(self.schema.fields.author),
+                                published: date(self.schema.fields.published),
+                                modified: date(self.schema.fields.modified),
+                                status_id: retrieved_document
+                                    .get_first(self.schema.fields.status_id)
+                                    .and_then(|field| field.as_u64()),
+                                status_author: text(self.schema.fields.status_author),
+                                posted_at: date(self.schema.fields.posted_at),
+                                in_reply_to: text(self.schema.fields.in_reply_to),
+                                retweeted_by: text(self.schema.fields.retweeted_by),
+                                identifiers: retrieved_document
+                                    .get_all(self.schema.fields.identifiers)
+                                    .filter_map(|field| field.as_str())
+                                    .map(|value| value.to_string())
+                                    .collect(),
+                                types: retrieved_document
+                                    .get_all(self.schema.fields.types)
+                                    .filter_map(|field| field.as_str())
+                                    .map(|value| value.to_string())
+                                    .collect(),
+                                image: text(self.schema.fields.image),
                             })
                         }
                     })
@@ -291,89 +458,157 @@ impl Index {
 
     pub fn to_tantivy_query(&self, query: &Query) -> Result<Box<dyn tantivy::query::Query>, Error> {
         let content_query = self.query_parser.parse_query(&query.content)?;
+        let mut filters: Vec<Box<dyn tantivy::query::Query>> = vec![];
 
-        let gravatar_hash_query = query.gravatar_hash.as_ref().map(|gravatar_hash| {
-            TermQuery::new(
+        if let Some(gravatar_hash) = &query.gravatar_hash {
+            filters.push(Box::new(TermQuery::new(
                 Term::from_field_text(self.schema.fields.gravatar_hashes, gravatar_hash),
                 IndexRecordOption::Basic,
-            )
-        });
-
-        let date_range_query = query.date_range.as_ref().map(|date_range| {
-            let terms = date_range.map(|value| {
-                Term::from_field_date(
-                    self.schema.fields.timestamp,
-                    Self::to_tantivy_date_time(*value),
-                )
-            });
-
-            let (lower_bound, upper_bound) = terms.bounds(
-                || Term::from_field_date(self.schema.fields.timestamp, tantivy::DateTime::MIN),
-                || Term::from_field_date(self.schema.fields.timestamp, tantivy::DateTime::MAX),
-            );
+            )));
+        }
 
-            RangeQuery::new_term_bounds(
-                schema::TIMESTAMP_FIELD_NAME.to_string(),
-                tantivy::schema::Type::Date,
-                &lower_bound,
-                &upper_bound,
-            )
-        });
+        if let Some(date_range) = &query.date_range {
+            filters.push(Box::new(
+                self.date_range_query(self.schema.fields.timestamp, date_range),
+            ));
+        }
 
-        let pattern_query = query.pattern_slugs.as_ref().map(|pattern_slugs| {
-            let terms = pattern_slugs
-                .iter()
-                .map(|pattern_slug| {
+        if let Some(pattern_slugs) = &query.pattern_slugs {
+            filters.push(Box::new(TermSetQuery::new(pattern_slugs.iter().map(
+                |pattern_slug| {
                     Term::from_facet(
                         self.schema.fields.pattern,
                         &Facet::from(&format!("/{}", pattern_slug)),
                     )
-                })
-                .collect::<Vec<_>>();
+                },
+            ))));
+        }
 
-            TermSetQuery::new(terms)
-        });
+        if let Some(years) = &query.years {
+            filters.push(Box::new(TermSetQuery::new(years.iter().map(|year| {
+                Term::from_facet(self.schema.fields.year, &Facet::from(&format!("/{}", year)))
+            }))));
+        }
 
-        let year_query = query.years.as_ref().map(|years| {
-            let terms = years
-                .iter()
-                .map(|year| {
-                    Term::from_facet(self.schema.fields.year, &Facet::from(&format!("/{}", year)))
-                })
-                .collect::<Vec<_>>();
-
-            TermSetQuery::new(terms)
-        });
-
-        if gravatar_hash_query.is_none()
-            && date_range_query.is_none()
-            && pattern_query.is_none()
-            && year_query.is_none()
-        {
+        if let Some(langs) = &query.langs {
+            filters.push(Box::new(
+                self.text_set_query(self.schema.fields.lang, langs),
+            ));
+        }
+
+        if let Some(status_authors) = &query.status_authors {
+            filters.push(Box::new(
+                self.text_set_query(self.schema.fields.status_author, status_authors),
+            ));
+        }
+
+        if let Some(status_ids) = &query.status_ids {
+            filters.push(Box::new(TermSetQuery::new(status_ids.iter().map(
+                |status_id| Term::from_field_u64(self.schema.fields.status_id, *status_id),
+            ))));
+        }
+
+        if let Some(posted_range) = &query.posted_range {
+            filters.push(Box::new(
+                self.date_range_query(self.schema.fields.posted_at, posted_range),
+            ));
+        }
+
+        if let Some(in_reply_to) = &query.in_reply_to {
+            filters.push(Box::new(
+                self.text_set_query(self.schema.fields.in_reply_to, in_reply_to),
+            ));
+        }
+
+        if let Some(retweeted_by) = &query.retweeted_by {
+            filters.push(Box::new(
+                self.text_set_query(self.schema.fields.retweeted_by, retweeted_by),
+            ));
+        }
+
+        if let Some(identifiers) = &query.identifiers {
+            filters.push(Box::new(
+                self.text_set_query(self.schema.fields.identifiers, identifiers),
+            ));
+        }
+
+        if let Some(types) = &query.types {
+            filters.push(Box::new(
+                self.text_set_query(self.schema.fields.types, types),
+            ));
+        }
+
+        if let Some(author) = &query.author {
+            filters.push(self.phrase_query(self.schema.fields.author, author));
+        }
+
+        if let Some(published_range) = &query.published_range {
+            filters.push(Box::new(
+                self.date_range_query(self.schema.fields.published, published_range),
+            ));
+        }
+
+        if let Some(modified_range) = &query.modified_range {
+            filters.push(Box::new(
+                self.date_range_query(self.schema.fields.modified, modified_range),
+            ));
+        }
+
+        if filters.is_empty() {
             Ok(content_query)
         } else {
             let mut parts = vec![(Occur::Must, content_query)];
+            parts.extend(filters.into_iter().map(|filter| (Occur::Must, filter)));
 
-            if let Some(query) = gravatar_hash_query {
-                parts.push((Occur::Must, Box::new(query)));
-            }
+            Ok(Box::new(BooleanQuery::new(parts)))
+        }
+    }
 
-            if let Some(query) = date_range_query {
-                parts.push((Occur::Must, Box::new(query)));
-            }
+    /// Match documents with any of the given values in a raw text field.
+    fn text_set_query(&self, field: Field, values: &HashSet<String>) -> TermSetQuery {
+        TermSetQuery::new(
+            values
+                .iter()
+                .map(|value| Term::from_field_text(field, value)),
+        )
+    }
 
-            if let Some(query) = pattern_query {
-                parts.push((Occur::Must, Box::new(query)));
-            }
+    /// Match documents containing the given text as a phrase in a tokenized text field.
+    ///
+    /// Text without any tokens matches nothing.
+    fn phrase_query(&self, field: Field, text: &str) -> Box<dyn tantivy::query::Query> {
+        let mut tokenizer = self.tokenizer.clone();
+        let mut stream = tokenizer.token_stream(text);
+        let mut terms = vec![];
 
-            if let Some(query) = year_query {
-                parts.push((Occur::Must, Box::new(query)));
-            }
+        while let Some(token) = stream.next() {
+            terms.push(Term::from_field_text(field, &token.text));
+        }
 
-            Ok(Box::new(BooleanQuery::new(parts)))
+        match terms.len() {
+            0 => Box::new(EmptyQuery),
+            1 => Box::new(TermQuery::new(terms.remove(0), IndexRecordOption::Basic)),
+            _ => Box::new(PhraseQuery::new(terms)),
         }
     }
 
+    fn date_range_query(&self, field: Field, range: &Range<DateTime<Utc>>) -> RangeQuery {
+        let terms =
+            range.map(|value| Term::from_field_date(field, Self::to_tantivy_date_time(*value)));
+
+        let (lower_bound, upper_bound) = terms.bounds(
+            || Term::from_field_date(field, tantivy::DateTime::MIN),
+            || Term::from_field_date(field, tantivy::DateTime::MAX),
+        );
+
+        RangeQuery::new_term_bounds(
+            self.schema.schema.get_field_name(field).to_string(),
+            tantivy::schema::Type::Date,
+            &lower_bound,
+            &upper_bound,
+        )
+    }
+
     fn to_tantivy_date_time(value: DateTime<Utc>) -> tantivy::DateTime {
         tantivy::DateTime::from_timestamp_secs(value.timestamp())
     }
@@ -1,5 +1,7 @@
+use aib_extractor::Identifier;
 use chrono::{DateTime, Utc};
 use std::collections::HashSet;
+use std::hash::Hash;
 use std::ops::Bound;
 
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
@@ -61,34 +63,169 @@ pub struct Query {
     pub date_range: Option<Range<DateTime<Utc>>>,
     pub pattern_slugs: Option<HashSet<String>>,
     pub years: Option<HashSet<u16>>,
+    /// Document languages (lowercase, as given in `<html lang>`).
+    pub langs: Option<HashSet<String>>,
+    /// Screen names of status authors (lowercase).
+    pub status_authors: Option<HashSet<String>>,
+    pub status_ids: Option<HashSet<u64>>,
+    pub posted_range: Option<Range<DateTime<Utc>>>,
+    /// Screen names of accounts replied to (lowercase).
+    pub in_reply_to: Option<HashSet<String>>,
+    /// Screen names of accounts that retweeted the status (lowercase).
+    pub retweeted_by: Option<HashSet<String>>,
+    /// Normalized identifiers (as `<kind>:<value>`).
+    pub identifiers: Option<HashSet<String>>,
+    /// Structured data types (lowercase).
+    pub types: Option<HashSet<String>>,
+    /// A phrase that must appear in the author metadata.
+    pub author: Option<String>,
+    pub published_range: Option<Range<DateTime<Utc>>>,
+    pub modified_range: Option<Range<DateTime<Utc>>>,
 }
 
 impl Query {
-    pub fn new(
-        content: &str,
-        gravatar_email: Option<&str>,
-        date_range: Option<Range<DateTime<Utc>>>,
-        pattern_slugs: Vec<String>,
-        years: Vec<u16>,
-    ) -> Self {
-        let pattern_slugs = if pattern_slugs.is_empty() {
-            None
-        } else {
-            Some(pattern_slugs.into_iter().collect())
-        };
-
-        let years = if years.is_empty() {
-            None
-        } else {
-            Some(years.into_iter().collect())
-        };
-
+    /// A query for the given content, with no filters.
+    pub fn new(content: &str) -> Self {
         Self {
             content: content.to_string(),
-            gravatar_hash: gravatar_email.map(Self::hash_email),
-            date_range,
-            pattern_slugs,
-            years,
+            gravatar_hash: None,
+            date_range: None,
+            pattern_slugs: None,
+            years: None,
+            langs: None,
+            status_authors: None,
+            status_ids: None,
+            posted_range: None,
+            in_reply_to: None,
+            retweeted_by: None,
+            identifiers: None,
+            types: None,
+            author: None,
+            published_range: None,
+            modified_range: None,
+        }
+    }
+
+    /// Only match documents that include a Gravatar hash for this email address.
+    pub fn with_gravatar_email(self, email: Option<&str>) -> Self {
+        Self {
+            gravatar_hash: email.map(Self::hash_email),
+            ..self
+        }
+    }
+
+    pub fn with_date_range(self, date_range: Option<Range<DateTime<Utc>>>) -> Self {
+        Self { date_range, ..self }
+    }
+
+    /// Only match documents for any of these patterns (or all patterns if empty).
+    pub fn with_pattern_slugs<I: IntoIterator<Item = String>>(self, pattern_slugs: I) -> Self {
+        Self {
+            pattern_slugs: non_empty(pattern_slugs),
+            ..self
+        }
+    }
+
+    /// Only match documents captured in any of these years (or all years if empty).
+    pub fn with_years<I: IntoIterator<Item = u16>>(self, years: I) -> Self {
+        Self {
+            years: non_empty(years),
+            ..self
+        }
+    }
+
+    /// Only match documents in any of these languages (or all languages if empty).
+    pub fn with_langs<I: IntoIterator<Item = String>>(self, langs: I) -> Self {
+        Self {
+            langs: non_empty(langs.into_iter().map(|lang| lang.to_ascii_lowercase())),
+            ..self
+        }
+    }
+
+    /// Only match statuses by any of these screen names (with or without `@`).
+    pub fn with_status_authors<I: IntoIterator<Item = String>>(self, status_authors: I) -> Self {
+        Self {
+            status_authors: screen_names(status_authors),
+            ..self
+        }
+    }
+
+    /// Only match statuses with any of these IDs.
+    pub fn with_status_ids<I: IntoIterator<Item = u64>>(self, status_ids: I) -> Self {
+        Self {
+            status_ids: non_empty(status_ids),
+            ..self
+        }
+    }
+
+    /// Only match statuses posted in this range.
+    pub fn with_posted_range(self, posted_range: Option<Range<DateTime<Utc>>>) -> Self {
+        Self {
+            posted_range,
+            ..self
+        }
+    }
+
+    /// Only match statuses replying to any of these screen names (with or without `@`).
+    pub fn with_in_reply_to<I: IntoIterator<Item = String>>(self, in_reply_to: I) -> Self {
+        Self {
+            in_reply_to: screen_names(in_reply_to),
+            ..self
+        }
+    }
+
+    /// Only match statuses retweeted by any of these screen names (with or without `@`).
+    pub fn with_retweeted_by<I: IntoIterator<Item = String>>(self, retweeted_by: I) -> Self {
+        Self {
+            retweeted_by: screen_names(retweeted_by),
+            ..self
+        }
+    }
+
+    /// Only match documents that mention any of these identifiers.
+    pub fn with_identifiers<I: IntoIterator<Item = Identifier>>(self, identifiers: I) -> Self {
+        Self {
+            identifiers: non_empty(
+                identifiers
+                    .into_iter()
+                    .map(|identifier| identifier.to_string()),
+            ),
+            ..self
+        }
+    }
+
+    /// Only match documents with any of these structured data types (e.g. `article`).
+    pub fn with_types<I: IntoIterator<Item = String>>(self, types: I) -> Self {
+        Self {
+            types: non_empty(types.into_iter().map(|value| value.to_lowercase())),
+            ..self
+        }
+    }
+
+    /// Only match documents whose author metadata contains this phrase.
+    pub fn with_author(self, author: Option<&str>) -> Self {
+        Self {
+            author: author
+                .map(str::trim)
+                .filter(|author| !author.is_empty())
+                .map(str::to_string),
+            ..self
+        }
+    }
+
+    /// Only match documents with a publication date in this range.
+    pub fn with_published_range(self, published_range: Option<Range<DateTime<Utc>>>) -> Self {
+        Self {
+            published_range,
+            ..self
+        }
+    }
+
+    /// Only match documents with a modification date in this range.
+    pub fn with_modified_range(self, modified_range: Option<Range<DateTime<Utc>>>) -> Self {
+        Self {
+            modified_range,
+            ..self
         }
     }
 
@@ -96,3 +233,21 @@ impl Query {
         format!("{:x}", md5::compute(email.to_ascii_lowercase()))
     }
 }
+
+fn screen_names<I: IntoIterator<Item = String>>(values: I) -> Option<HashSet<String>> {
+    non_empty(
+        values
+            .into_iter()
+            .map(|value| value.trim_start_matches('@').to_ascii_lowercase()),
+    )
+}
+
+fn non_empty<A: Eq + Hash, I: IntoIterator<Item = A>>(values: I) -> Option<HashSet<A>> {
+    let values = values.into_iter().collect::<HashSet<_>>();
+
+    if values.is_empty() {
+        None
+    } else {
+        Some(values)
+    }
+}
@@ -6,8 +6,23 @@ pub const PATTERN_FIELD_NAME: &str = "pattern";
 pub const YEAR_FIELD_NAME: &str = "year";
 pub const TIMESTAMP_FIELD_NAME: &str = "timestamp";
 pub const CONTENT_FIELD_NAME: &str = "content";
+pub const MAIN_CONTENT_FIELD_NAME: &str = "main_content";
 pub const TITLE_FIELD_NAME: &str = "title";
 pub const GRAVATAR_HASHES_FIELD_NAME: &str = "gravatar_hashes";
+pub const DESCRIPTION_FIELD_NAME: &str = "description";
+pub const CANONICAL_URL_FIELD_NAME: &str = "canonical_url";
+pub const LANG_FIELD_NAME: &str = "lang";
+pub const AUTHOR_FIELD_NAME: &str = "author";
+pub const PUBLISHED_FIELD_NAME: &str = "published";
+pub const MODIFIED_FIELD_NAME: &str = "modified";
+pub const STATUS_ID_FIELD_NAME: &str = "status_id";
+pub const STATUS_AUTHOR_FIELD_NAME: &str = "status_author";
+pub const POSTED_AT_FIELD_NAME: &str = "posted_at";
+pub const IN_REPLY_TO_FIELD_NAME: &str = "in_reply_to";
+pub const RETWEETED_BY_FIELD_NAME: &str = "retweeted_by";
+pub const IDENTIFIERS_FIELD_NAME: &str = "identifiers";
+pub const TYPES_FIELD_NAME: &str = "types";
+pub const IMAGE_FIELD_NAME: &str = "image";
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub struct Fields {
@@ -17,8 +32,23 @@ pub struct Fields {
     pub year: Field,
     pub timestamp: Field,
     pub content: Field,
+    pub main_content: Field,
     pub title: Field,
     pub gravatar_hashes: Field,
+    pub description: Field,
+    pub canonical_url: Field,
+    pub lang: Field,
+    pub author: Field,
+    pub published: Field,
+    pub modified: Field,
+    pub status_id: Field,
+    pub status_author: Field,
+    pub posted_at: Field,
+    pub in_reply_to: Field,
+    pub retweeted_by: Field,
+    pub identifiers: Field,
+    pub types: Field,
+    pub image: Field,
 }
 
 #[derive(Debug, Clone, Eq, PartialEq)]
@@ -70,15 +100,63 @@ impl Schema {
             )
             .set_stored();
 
+        let description_options = TextOptions::default()
+            .set_indexing_options(
+                TextFieldIndexing::default()
+                    .set_tokenizer("default")
+                    .set_index_option(IndexRecordOption::WithFreqsAndPositions),
+            )
+            .set_stored();
+        let author_options = description_options.clone();
+        let canonical_url_options = TextOptions::default().set_stored();
+        let lang_options = TextOptions::default()
+            .set_indexing_options(
+                TextFieldIndexing::default()
+                    .set_tokenizer("raw")
+                    .set_index_option(IndexRecordOption::Basic),
+            )
+            .set_stored();
+        let status_id_options = NumericOptions::default().set_indexed().set_stored();
+        let screen_name_options = lang_options.clone();
+        let identifiers_options = lang_options.clone();
+        let types_options = lang_options.clone();
+        let image_options = TextOptions::default().set_stored();
+        let date_options = DateOptions::default()
+            .set_indexed()
+            .set_stored()
+            .set_precision(DateTimePrecision::Seconds);
+
         let snapshot_id = schema_builder.add_i64_field(SNAPSHOT_ID_FIELD_NAME, snapshot_id_options);
         let surt_id = schema_builder.add_i64_field(SURT_ID_FIELD_NAME, surt_id_options);
         let pattern = schema_builder.add_facet_field(PATTERN_FIELD_NAME, pattern_options);
         let year = schema_builder.add_facet_field(YEAR_FIELD_NAME, year_options);
         let timestamp = schema_builder.add_date_field(TIMESTAMP_FIELD_NAME, timestamp_options);
+        let main_content =
+            schema_builder.add_text_field(MAIN_CONTENT_FIELD_NAME, content_options.clone());
         let content = schema_builder.add_text_field(CONTENT_FIELD_NAME, content_options);
         let title = schema_builder.add_text_field(TITLE_FIELD_NAME, title_options);
         let gravatar_hashes =
             schema_builder.add_text_field(GRAVATAR_HASHES_FIELD_NAME, gravatar_hashes_options);
+        let description =
+            schema_builder.add_text_field(DESCRIPTION_FIELD_NAME, description_options);
+        let canonical_url =
+            schema_builder.add_text_field(CANONICAL_URL_FIELD_NAME, canonical_url_options);
+        let lang = schema_builder.add_text_field(LANG_FIELD_NAME, lang_options);
+        let author = schema_builder.add_text_field(AUTHOR_FIELD_NAME, author_options);
+        let published = schema_builder.add_date_field(PUBLISHED_FIELD_NAME, date_options.clone());
+        let modified = schema_builder.add_date_field(MODIFIED_FIELD_NAME, date_options.clone());
+        let status_id = schema_builder.add_u64_field(STATUS_ID_FIELD_NAME, status_id_options);
+        let status_author =
+            schema_builder.add_text_field(STATUS_AUTHOR_FIELD_NAME, screen_name_options.clone());
+        let posted_at = schema_builder.add_date_field(POSTED_AT_FIELD_NAME, date_options);
+        let in_reply_to =
+            schema_builder.add_text_field(IN_REPLY_TO_FIELD_NAME, screen_name_options.clone());
+        let retweeted_by =
+            schema_builder.add_text_field(RETWEETED_BY_FIELD_NAME, screen_name_options);
+        let identifiers =
+            schema_builder.add_text_field(IDENTIFIERS_FIELD_NAME, identifiers_options);
+        let types = schema_builder.add_text_field(TYPES_FIELD_NAME, types_options);
+        let image = schema_builder.add_text_field(IMAGE_FIELD_NAME, image_options);
 
         Self {
             schema: schema_builder.build(),
@@ -89,8 +167,23 @@ impl Schema {
                 year,
                 timestamp,
                 content,
+                main_content,
                 title,
                 gravatar_hashes,
+                description,
+                canonical_url,
+                lang,
+                author,
+                published,
+                modified,
+                status_id,
+                status_author,
+                posted_at,
+                in_reply_to,
+                retweeted_by,
+                identifiers,
+                types,
+                image,
             },
         }
     }
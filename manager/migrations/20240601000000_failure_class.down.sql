@@ -0,0 +1,3 @@
+DROP INDEX idx_entry_failure_error_class;
+
+ALTER TABLE entry_failure DROP COLUMN error_class;
\ No newline at end of file
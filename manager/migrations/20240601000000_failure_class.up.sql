@@ -0,0 +1,13 @@
+ALTER TABLE entry_failure ADD COLUMN error_class VARCHAR(255) NOT NULL DEFAULT 'other';
+
+-- Classify existing failures by status code (as FailureClass::from_status_code does).
+UPDATE entry_failure SET error_class = CASE
+    WHEN status_code IN (404, 410) THEN 'not_found'
+    WHEN status_code IN (403, 451) THEN 'blocked'
+    WHEN status_code = 429 THEN 'rate_limited'
+    WHEN status_code BETWEEN 400 AND 499 THEN 'client_error'
+    WHEN status_code BETWEEN 500 AND 599 THEN 'server_error'
+    ELSE 'other'
+END;
+
+CREATE INDEX idx_entry_failure_error_class ON entry_failure (error_class);
@@ -0,0 +1 @@
+DROP TABLE snapshot_header;
\ No newline at end of file
@@ -0,0 +1,9 @@
+CREATE TABLE snapshot_header(
+    snapshot_id INTEGER PRIMARY KEY NOT NULL,
+    content_type TEXT,
+    date TEXT,
+    last_modified TEXT,
+    server TEXT,
+    set_cookie BOOLEAN NOT NULL,
+    FOREIGN KEY (snapshot_id) REFERENCES snapshot (id)
+);
\ No newline at end of file
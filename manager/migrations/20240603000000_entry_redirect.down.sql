@@ -0,0 +1 @@
+DROP TABLE entry_redirect;
\ No newline at end of file
@@ -0,0 +1,10 @@
+CREATE TABLE entry_redirect(
+    entry_id INTEGER NOT NULL,
+    position INTEGER NOT NULL,
+    url TEXT NOT NULL,
+    ts INTEGER NOT NULL,
+    FOREIGN KEY (entry_id) REFERENCES entry (id),
+    CONSTRAINT uniq_entry_redirect_entry_id_position UNIQUE (entry_id, position)
+);
+
+CREATE INDEX idx_entry_redirect_url_ts ON entry_redirect (url, ts);
\ No newline at end of file
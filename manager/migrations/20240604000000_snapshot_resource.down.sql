@@ -0,0 +1,2 @@
+DROP TABLE snapshot_resource_scan;
+DROP TABLE snapshot_resource;
\ No newline at end of file
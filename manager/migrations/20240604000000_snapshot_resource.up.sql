@@ -0,0 +1,21 @@
+CREATE TABLE snapshot_resource(
+    snapshot_id INTEGER NOT NULL,
+    url TEXT NOT NULL,
+    kind VARCHAR(255) NOT NULL,
+    resource_url TEXT,
+    resource_ts INTEGER,
+    resource_snapshot_id INTEGER,
+    error_class VARCHAR(255),
+    ts INTEGER NOT NULL,
+    FOREIGN KEY (snapshot_id) REFERENCES snapshot (id),
+    FOREIGN KEY (resource_snapshot_id) REFERENCES snapshot (id),
+    CONSTRAINT uniq_snapshot_resource_snapshot_id_url UNIQUE (snapshot_id, url)
+);
+
+CREATE INDEX idx_snapshot_resource_resource_snapshot_id ON snapshot_resource (resource_snapshot_id);
+
+CREATE TABLE snapshot_resource_scan(
+    snapshot_id INTEGER PRIMARY KEY NOT NULL,
+    ts INTEGER NOT NULL,
+    FOREIGN KEY (snapshot_id) REFERENCES snapshot (id)
+);
\ No newline at end of file
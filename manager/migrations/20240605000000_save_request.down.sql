@@ -0,0 +1 @@
+DROP TABLE save_request;
\ No newline at end of file
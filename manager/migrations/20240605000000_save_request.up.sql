@@ -0,0 +1,14 @@
+CREATE TABLE save_request(
+    id INTEGER PRIMARY KEY NOT NULL,
+    url TEXT NOT NULL,
+    pattern_id INTEGER,
+    job_id VARCHAR(255),
+    capture_ts INTEGER,
+    status_ext VARCHAR(255),
+    error_message TEXT,
+    ts INTEGER NOT NULL,
+    FOREIGN KEY (pattern_id) REFERENCES pattern (id)
+);
+
+CREATE INDEX idx_save_request_url ON save_request (url);
+CREATE INDEX idx_save_request_pattern_id ON save_request (pattern_id);
\ No newline at end of file
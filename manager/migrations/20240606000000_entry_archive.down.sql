@@ -0,0 +1,77 @@
+-- Captures that share a SURT and timestamp with a Wayback Machine capture are dropped.
+CREATE TABLE entry_success_copy AS SELECT * FROM entry_success;
+CREATE TABLE entry_failure_copy AS SELECT * FROM entry_failure;
+CREATE TABLE entry_redirect_copy AS SELECT * FROM entry_redirect;
+
+DROP TABLE entry_success;
+DROP TABLE entry_failure;
+DROP TABLE entry_redirect;
+
+CREATE TABLE entry_wayback(
+    id INTEGER PRIMARY KEY NOT NULL,
+    url TEXT NOT NULL,
+    surt_id INTEGER NOT NULL,
+    ts INTEGER NOT NULL,
+    digest VARCHAR(255) NOT NULL,
+    mime_type VARCHAR(255) NOT NULL,
+    status_code INTEGER,
+    length INTEGER NOT NULL,
+    FOREIGN KEY (surt_id) REFERENCES surt (id),
+    CONSTRAINT uniq_entry_surt_id_ts UNIQUE (surt_id, ts)
+);
+
+INSERT OR IGNORE INTO entry_wayback(id, url, surt_id, ts, digest, mime_type, status_code, length)
+    SELECT id, url, surt_id, ts, digest, mime_type, status_code, length FROM entry
+    ORDER BY archive != 'wayback', id;
+
+DROP TABLE entry;
+ALTER TABLE entry_wayback RENAME TO entry;
+
+CREATE INDEX idx_entry_digest ON entry (digest);
+CREATE INDEX idx_entry_surt_id ON entry (surt_id);
+
+CREATE TABLE entry_success(
+    id INTEGER PRIMARY KEY NOT NULL,
+    entry_id INTEGER NOT NULL,
+    snapshot_id INTEGER NOT NULL,
+    correct_digest BOOLEAN,
+    ts INTEGER NOT NULL,
+    FOREIGN KEY (entry_id) REFERENCES entry (id)
+    FOREIGN KEY (snapshot_id) REFERENCES snapshot (id),
+    CONSTRAINT uniq_entry_success_entry_id_snapshot_id_correct_digest UNIQUE (entry_id, snapshot_id, correct_digest)
+);
+
+CREATE INDEX idx_entry_success_entry_id ON entry_success (entry_id);
+CREATE INDEX idx_entry_success_snapshot_id ON entry_success (snapshot_id);
+
+CREATE TABLE entry_failure(
+    id INTEGER PRIMARY KEY NOT NULL,
+    entry_id INTEGER NOT NULL,
+    ts INTEGER NOT NULL,
+    status_code INTEGER NOT NULL,
+    error_message TEXT NOT NULL,
+    error_class VARCHAR(255) NOT NULL DEFAULT 'other',
+    FOREIGN KEY (entry_id) REFERENCES entry (id)
+);
+
+CREATE INDEX idx_entry_failure_entry_id ON entry_failure (entry_id);
+CREATE INDEX idx_entry_failure_error_class ON entry_failure (error_class);
+
+CREATE TABLE entry_redirect(
+    entry_id INTEGER NOT NULL,
+    position INTEGER NOT NULL,
+    url TEXT NOT NULL,
+    ts INTEGER NOT NULL,
+    FOREIGN KEY (entry_id) REFERENCES entry (id),
+    CONSTRAINT uniq_entry_redirect_entry_id_position UNIQUE (entry_id, position)
+);
+
+CREATE INDEX idx_entry_redirect_url_ts ON entry_redirect (url, ts);
+
+INSERT INTO entry_success SELECT * FROM entry_success_copy WHERE entry_id IN (SELECT id FROM entry);
+INSERT INTO entry_failure SELECT * FROM entry_failure_copy WHERE entry_id IN (SELECT id FROM entry);
+INSERT INTO entry_redirect SELECT * FROM entry_redirect_copy WHERE entry_id IN (SELECT id FROM entry);
+
+DROP TABLE entry_success_copy;
+DROP TABLE entry_failure_copy;
+DROP TABLE entry_redirect_copy;
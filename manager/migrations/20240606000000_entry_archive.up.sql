@@ -0,0 +1,81 @@
+-- Captures from different archives may share a SURT and timestamp, so the archive is part of the
+-- unique key. SQLite can't change a table constraint in place, and migrations run in a transaction
+-- with foreign keys enabled, so the tables that reference entry are copied and recreated as well.
+CREATE TABLE entry_success_copy AS SELECT * FROM entry_success;
+CREATE TABLE entry_failure_copy AS SELECT * FROM entry_failure;
+CREATE TABLE entry_redirect_copy AS SELECT * FROM entry_redirect;
+
+DROP TABLE entry_success;
+DROP TABLE entry_failure;
+DROP TABLE entry_redirect;
+
+CREATE TABLE entry_archive(
+    id INTEGER PRIMARY KEY NOT NULL,
+    url TEXT NOT NULL,
+    surt_id INTEGER NOT NULL,
+    ts INTEGER NOT NULL,
+    digest VARCHAR(255) NOT NULL,
+    mime_type VARCHAR(255) NOT NULL,
+    status_code INTEGER,
+    length INTEGER NOT NULL,
+    archive VARCHAR(255) NOT NULL DEFAULT 'wayback',
+    memento_url TEXT,
+    FOREIGN KEY (surt_id) REFERENCES surt (id),
+    CONSTRAINT uniq_entry_surt_id_ts_archive UNIQUE (surt_id, ts, archive)
+);
+
+INSERT INTO entry_archive(id, url, surt_id, ts, digest, mime_type, status_code, length)
+    SELECT id, url, surt_id, ts, digest, mime_type, status_code, length FROM entry;
+
+DROP TABLE entry;
+ALTER TABLE entry_archive RENAME TO entry;
+
+CREATE INDEX idx_entry_digest ON entry (digest);
+CREATE INDEX idx_entry_surt_id ON entry (surt_id);
+CREATE INDEX idx_entry_archive ON entry (archive);
+
+CREATE TABLE entry_success(
+    id INTEGER PRIMARY KEY NOT NULL,
+    entry_id INTEGER NOT NULL,
+    snapshot_id INTEGER NOT NULL,
+    correct_digest BOOLEAN,
+    ts INTEGER NOT NULL,
+    FOREIGN KEY (entry_id) REFERENCES entry (id)
+    FOREIGN KEY (snapshot_id) REFERENCES snapshot (id),
+    CONSTRAINT uniq_entry_success_entry_id_snapshot_id_correct_digest UNIQUE (entry_id, snapshot_id, correct_digest)
+);
+
+CREATE INDEX idx_entry_success_entry_id ON entry_success (entry_id);
+CREATE INDEX idx_entry_success_snapshot_id ON entry_success (snapshot_id);
+
+CREATE TABLE entry_failure(
+    id INTEGER PRIMARY KEY NOT NULL,
+    entry_id INTEGER NOT NULL,
+    ts INTEGER NOT NULL,
+    status_code INTEGER NOT NULL,
+    error_message TEXT NOT NULL,
+    error_class VARCHAR(255) NOT NULL DEFAULT 'other',
+    FOREIGN KEY (entry_id) REFERENCES entry (id)
+);
+
+CREATE INDEX idx_entry_failure_entry_id ON entry_failure (entry_id);
+CREATE INDEX idx_entry_failure_error_class ON entry_failure (error_class);
+
+CREATE TABLE entry_redirect(
+    entry_id INTEGER NOT NULL,
+    position INTEGER NOT NULL,
+    url TEXT NOT NULL,
+    ts INTEGER NOT NULL,
+    FOREIGN KEY (entry_id) REFERENCES entry (id),
+    CONSTRAINT uniq_entry_redirect_entry_id_position UNIQUE (entry_id, position)
+);
+
+CREATE INDEX idx_entry_redirect_url_ts ON entry_redirect (url, ts);
+
+INSERT INTO entry_success SELECT * FROM entry_success_copy;
+INSERT INTO entry_failure SELECT * FROM entry_failure_copy;
+INSERT INTO entry_redirect SELECT * FROM entry_redirect_copy;
+
+DROP TABLE entry_success_copy;
+DROP TABLE entry_failure_copy;
+DROP TABLE entry_redirect_copy;
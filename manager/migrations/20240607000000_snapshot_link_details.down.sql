@@ -0,0 +1,5 @@
+DROP INDEX idx_link_surt;
+DROP INDEX idx_snapshot_link_link_id;
+
+ALTER TABLE snapshot_link DROP COLUMN internal;
+ALTER TABLE snapshot_link DROP COLUMN text;
\ No newline at end of file
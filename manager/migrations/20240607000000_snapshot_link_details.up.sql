@@ -0,0 +1,5 @@
+ALTER TABLE snapshot_link ADD COLUMN text TEXT NOT NULL DEFAULT '';
+ALTER TABLE snapshot_link ADD COLUMN internal BOOLEAN NOT NULL DEFAULT FALSE;
+
+CREATE INDEX idx_snapshot_link_link_id ON snapshot_link (link_id);
+CREATE INDEX idx_link_surt ON link (surt);
@@ -1,7 +1,11 @@
-use crate::model::entry::InvalidDigest;
-use aib_cdx::entry::Entry as CdxEntry;
+use crate::model::{entry::InvalidDigest, FailureClass, FailureCount, RetryPolicy};
+use aib_cdx::{entry::Entry as CdxEntry, memento::Memento};
+use aib_core::entry::UrlParts;
 use chrono::{DateTime, Utc};
-use sqlx::{query_as, query_scalar, Connection, Executor, Sqlite, SqliteConnection};
+use sqlx::{query, query_as, query_scalar, Connection, Executor, Sqlite, SqliteConnection};
+
+/// The digest stored for captures whose digest isn't known until they are downloaded.
+pub const UNKNOWN_DIGEST: &str = "-";
 
 pub async fn insert<'c>(
     connection: &mut SqliteConnection,
@@ -13,6 +17,10 @@ pub async fn insert<'c>(
     Ok(entry_id)
 }
 
+/// Insert an entry for a CDX record.
+///
+/// If the capture is already known with an unknown digest (for example because
+/// it was requested with Save Page Now), the CDX details are filled in.
 pub async fn insert_entry<'c, E: Executor<'c, Database = Sqlite>>(
     executor: E,
     entry: &CdxEntry,
@@ -27,7 +35,12 @@ pub async fn insert_entry<'c, E: Executor<'c, Database = Sqlite>>(
 
     let id = query_scalar!(
         "INSERT INTO entry(url, surt_id, ts, digest, mime_type, status_code, length)
-            VALUES (?, ?, ?, ?, ?, ?, ?) ON CONFLICT DO UPDATE SET id = id RETURNING id",
+            VALUES (?, ?, ?, ?, ?, ?, ?) ON CONFLICT DO UPDATE SET
+                digest = CASE WHEN digest = '-' THEN excluded.digest ELSE digest END,
+                mime_type = CASE WHEN digest = '-' THEN excluded.mime_type ELSE mime_type END,
+                status_code = CASE WHEN digest = '-' THEN excluded.status_code ELSE status_code END,
+                length = CASE WHEN digest = '-' THEN excluded.length ELSE length END
+            RETURNING id",
         entry.original,
         surt_id,
         timestamp,
@@ -43,11 +56,73 @@ pub async fn insert_entry<'c, E: Executor<'c, Database = Sqlite>>(
     Ok(id as u64)
 }
 
+/// Insert an entry for a capture listed in a Memento TimeMap.
+///
+/// TimeMaps don't provide digests, lengths or status codes, so the digest is
+/// stored as `-` and the length as zero, and the URI-M is kept for downloading.
+/// Returns `None` if the archive's capture for the SURT and timestamp is
+/// already known.
+pub async fn insert_memento(
+    connection: &mut SqliteConnection,
+    memento: &Memento,
+    surt: &str,
+    mime_type: &str,
+) -> Result<Option<u64>, sqlx::Error> {
+    let surt_id = crate::db::surt::insert(&mut *connection, surt).await? as i64;
+    let timestamp = memento.timestamp.0.timestamp();
+    let archive = memento.archive.as_str();
+
+    let id = query_scalar!(
+        "INSERT INTO entry(url, surt_id, ts, digest, mime_type, length, archive, memento_url)
+            VALUES (?, ?, ?, '-', ?, 0, ?, ?) ON CONFLICT DO NOTHING RETURNING id",
+        memento.url,
+        surt_id,
+        timestamp,
+        mime_type,
+        archive,
+        memento.memento_url
+    )
+    .persistent(true)
+    .fetch_optional(&mut *connection)
+    .await?;
+
+    Ok(id.map(|id| id as u64))
+}
+
+/// Insert a Wayback Machine entry for a capture made by Save Page Now.
+///
+/// The digest, length and status code aren't known until the capture appears
+/// in a CDX query or is downloaded. Returns the ID of the existing entry if the
+/// capture is already known.
+pub async fn insert_saved(
+    connection: &mut SqliteConnection,
+    capture: &UrlParts,
+    surt: &str,
+    mime_type: &str,
+) -> Result<u64, sqlx::Error> {
+    let surt_id = crate::db::surt::insert(&mut *connection, surt).await? as i64;
+    let timestamp = capture.timestamp.0.timestamp();
+
+    let id = query_scalar!(
+        "INSERT INTO entry(url, surt_id, ts, digest, mime_type, length)
+            VALUES (?, ?, ?, '-', ?, 0) ON CONFLICT DO UPDATE SET id = id RETURNING id",
+        capture.url,
+        surt_id,
+        timestamp,
+        mime_type
+    )
+    .persistent(true)
+    .fetch_one(&mut *connection)
+    .await?;
+
+    Ok(id as u64)
+}
+
 pub async fn insert_entry_success(
     connection: &mut SqliteConnection,
     entry_id: u64,
     digest: &str,
-    correct_digest: bool,
+    correct_digest: Option<bool>,
     timestamp: DateTime<Utc>,
 ) -> Result<u64, sqlx::Error> {
     let mut tx = connection.begin().await?;
@@ -78,16 +153,19 @@ pub async fn insert_entry_error(
     entry_id: i64,
     timestamp: DateTime<Utc>,
     status_code: u16,
+    error_class: FailureClass,
     error_message: &str,
 ) -> Result<i64, sqlx::Error> {
     let timestamp = timestamp.timestamp();
     let status_code = status_code as i32;
+    let error_class = error_class.as_str();
 
     let id = query_scalar!(
-        "INSERT INTO entry_failure(entry_id, ts, status_code, error_message) VALUES (?, ?, ?, ?) RETURNING id",
+        "INSERT INTO entry_failure(entry_id, ts, status_code, error_class, error_message) VALUES (?, ?, ?, ?, ?) RETURNING id",
         entry_id,
         timestamp,
         status_code,
+        error_class,
         error_message
     )
     .persistent(true)
@@ -97,6 +175,71 @@ pub async fn insert_entry_error(
     Ok(id)
 }
 
+/// Record the redirects followed when downloading an entry, in order.
+///
+/// Any previously recorded chain for the entry is replaced.
+pub async fn insert_redirects(
+    connection: &mut SqliteConnection,
+    entry_id: u64,
+    redirects: &[UrlParts],
+) -> Result<(), sqlx::Error> {
+    let mut tx = connection.begin().await?;
+    let entry_id = entry_id as i64;
+
+    query!("DELETE FROM entry_redirect WHERE entry_id = ?", entry_id)
+        .persistent(true)
+        .execute(&mut *tx)
+        .await?;
+
+    for (position, redirect) in redirects.iter().enumerate() {
+        let position = position as i64;
+        let timestamp = redirect.timestamp.0.timestamp();
+
+        query!(
+            "INSERT INTO entry_redirect(entry_id, position, url, ts) VALUES (?, ?, ?, ?)",
+            entry_id,
+            position,
+            redirect.url,
+            timestamp
+        )
+        .persistent(true)
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
+pub async fn get_redirects<'c, E: Executor<'c, Database = Sqlite>>(
+    executor: E,
+    entry_id: u64,
+) -> Result<Vec<UrlParts>, sqlx::Error> {
+    let entry_id = entry_id as i64;
+
+    let rows = query!(
+        "SELECT url, ts FROM entry_redirect WHERE entry_id = ? ORDER BY position",
+        entry_id
+    )
+    .fetch_all(executor)
+    .await?;
+
+    rows.into_iter()
+        .map(|row| {
+            Ok(UrlParts::new(
+                row.url,
+                row.ts
+                    .try_into()
+                    .map_err(|error| sqlx::Error::Decode(Box::new(error)))?,
+            ))
+        })
+        .collect()
+}
+
+/// Entries that have not been downloaded, for matching against local snapshots by digest.
+///
+/// Entries imported from Memento TimeMaps have no digest and are not included.
 pub async fn missing_entries<'c, E: Executor<'c, Database = Sqlite>>(
     executor: E,
     mime_type: &str,
@@ -114,11 +257,13 @@ pub async fn missing_entries<'c, E: Executor<'c, Database = Sqlite>>(
             mime_type,
             entry.status_code AS status_code,
             digest,
-            length
+            length,
+            archive,
+            memento_url
         FROM entry
         LEFT JOIN entry_success ON entry_success.entry_id = entry.id
         JOIN surt ON surt.id = entry.surt_id
-        WHERE mime_type = ? AND entry_success.id IS NULL AND (entry.status_code IS NULL OR entry.status_code == 200) 
+        WHERE mime_type = ? AND digest != '-' AND entry_success.id IS NULL AND (entry.status_code IS NULL OR entry.status_code == 200) 
         LIMIT ?
         ",
     )
@@ -129,6 +274,98 @@ pub async fn missing_entries<'c, E: Executor<'c, Database = Sqlite>>(
     .await
 }
 
+/// Entries that have not been downloaded and are due for an attempt.
+///
+/// Entries that have failed are only included when the retry policy allows it.
+pub async fn pending_entries<'c, E: Executor<'c, Database = Sqlite>>(
+    executor: E,
+    mime_type: &str,
+    retry_policy: &RetryPolicy,
+    now: DateTime<Utc>,
+    count: Option<usize>,
+) -> Result<Vec<crate::model::Entry>, sqlx::Error> {
+    let count = count.map(|value| value as i32).unwrap_or(i32::MAX);
+    let permanent_classes = FailureClass::ALL
+        .iter()
+        .filter(|class| class.is_permanent())
+        .map(|class| format!("'{}'", class))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    query_as(&format!(
+        "SELECT
+            entry.id AS entry_id,
+            surt.id AS surt_id,
+            surt.value AS surt,
+            entry.ts AS timestamp,
+            url,
+            mime_type,
+            entry.status_code AS status_code,
+            digest,
+            length,
+            archive,
+            memento_url
+        FROM entry
+        LEFT JOIN entry_success ON entry_success.entry_id = entry.id
+        JOIN surt ON surt.id = entry.surt_id
+        LEFT JOIN (
+            SELECT
+                entry_id,
+                COUNT(*) AS attempts,
+                MAX(ts) AS last_attempt,
+                MAX(error_class IN ({})) AS permanent
+            FROM entry_failure
+            GROUP BY entry_id
+        ) AS failure ON failure.entry_id = entry.id
+        WHERE mime_type = ? AND entry_success.id IS NULL AND (entry.status_code IS NULL OR entry.status_code == 200)
+            AND (
+                failure.entry_id IS NULL OR (
+                    NOT failure.permanent
+                    AND failure.attempts < ?
+                    AND failure.last_attempt + ? * (1 << (failure.attempts - 1)) <= ?
+                )
+            )
+        ORDER BY entry.id
+        LIMIT ?
+        ",
+        permanent_classes
+    ))
+    .bind(mime_type)
+    .bind(retry_policy.max_attempts as i64)
+    .bind(retry_policy.base_delay.num_seconds())
+    .bind(now.timestamp())
+    .bind(count)
+    .persistent(true)
+    .fetch_all(executor)
+    .await
+}
+
+/// Count failed entries and download attempts by pattern and failure class.
+///
+/// Entries that have since been downloaded successfully are not included.
+pub async fn failure_counts<'c, E: Executor<'c, Database = Sqlite>>(
+    executor: E,
+) -> Result<Vec<FailureCount>, sqlx::Error> {
+    query_as(
+        "SELECT
+            pattern.slug AS pattern,
+            entry_failure.error_class AS error_class,
+            COUNT(DISTINCT entry_failure.entry_id) AS entries,
+            COUNT(*) AS attempts
+        FROM entry_failure
+        JOIN pattern_entry ON pattern_entry.entry_id = entry_failure.entry_id
+        JOIN pattern ON pattern.id = pattern_entry.pattern_id
+        LEFT JOIN entry_success ON entry_success.entry_id = entry_failure.entry_id
+        WHERE entry_success.id IS NULL
+        GROUP BY pattern.id, entry_failure.error_class
+        ORDER BY pattern.sort_id, entry_failure.error_class
+        ",
+    )
+    .persistent(true)
+    .fetch_all(executor)
+    .await
+}
+
 pub async fn invalid_digests<'c, E: Executor<'c, Database = Sqlite>>(
     executor: E,
 ) -> Result<Vec<InvalidDigest>, sqlx::Error> {
@@ -145,6 +382,7 @@ pub async fn invalid_digests<'c, E: Executor<'c, Database = Sqlite>>(
     .await
 }
 
+/// Entries with the given CDX digest.
 pub async fn find_entries_by_digest<'c, E: Executor<'c, Database = Sqlite>>(
     executor: E,
     digest: &str,
@@ -184,6 +422,112 @@ mod tests {
         Ok(())
     }
 
+    #[sqlx::test]
+    async fn test_pending_entries(pool: SqlitePool) -> Result<(), sqlx::Error> {
+        let mut connection = pool.acquire().await?;
+        let policy = RetryPolicy {
+            max_attempts: 2,
+            base_delay: Duration::hours(1),
+        };
+        let now = Utc::now();
+
+        let (id_0, id_1, _) = insert_entries(&mut connection).await?;
+
+        let pending = pending_entries(&mut *connection, "text/html", &policy, now, None).await?;
+        assert_eq!(
+            pending.iter().map(|entry| entry.id).collect::<Vec<_>>(),
+            vec![id_0, id_1]
+        );
+
+        insert_entry_error(
+            &mut connection,
+            id_0 as i64,
+            now,
+            404,
+            FailureClass::NotFound,
+            "Not found",
+        )
+        .await?;
+        insert_entry_error(
+            &mut connection,
+            id_1 as i64,
+            now,
+            503,
+            FailureClass::ServerError,
+            "Unavailable",
+        )
+        .await?;
+
+        let pending_ids = |entries: Vec<crate::model::Entry>| {
+            entries.iter().map(|entry| entry.id).collect::<Vec<_>>()
+        };
+
+        assert!(
+            pending_entries(&mut *connection, "text/html", &policy, now, None)
+                .await?
+                .is_empty()
+        );
+        assert_eq!(
+            pending_ids(
+                pending_entries(
+                    &mut *connection,
+                    "text/html",
+                    &policy,
+                    now + Duration::hours(2),
+                    None
+                )
+                .await?
+            ),
+            vec![id_1]
+        );
+
+        insert_entry_error(
+            &mut connection,
+            id_1 as i64,
+            now + Duration::hours(2),
+            503,
+            FailureClass::ServerError,
+            "Unavailable",
+        )
+        .await?;
+
+        assert!(pending_entries(
+            &mut *connection,
+            "text/html",
+            &policy,
+            now + Duration::days(10),
+            None
+        )
+        .await?
+        .is_empty());
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_redirects(pool: SqlitePool) -> Result<(), sqlx::Error> {
+        let mut connection = pool.acquire().await?;
+        let (id_0, id_1, _) = insert_entries(&mut connection).await?;
+        let redirects = vec![
+            UrlParts::new(
+                "https://test.com/a".to_string(),
+                "20200101000000".parse().unwrap(),
+            ),
+            UrlParts::new(
+                "https://test.com/b".to_string(),
+                "20200101000005".parse().unwrap(),
+            ),
+        ];
+
+        insert_redirects(&mut connection, id_0, &redirects[..1]).await?;
+        insert_redirects(&mut connection, id_0, &redirects).await?;
+
+        assert_eq!(get_redirects(&mut *connection, id_0).await?, redirects);
+        assert!(get_redirects(&mut *connection, id_1).await?.is_empty());
+
+        Ok(())
+    }
+
     async fn insert_entries<'a>(
         connection: &mut SqliteConnection,
     ) -> Result<(u64, u64, u64), sqlx::Error> {
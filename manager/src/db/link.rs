@@ -0,0 +1,306 @@
+use crate::model::{DomainCount, InboundLink, LinkTarget, OutboundLink};
+use aib_extractor::Link;
+use sqlx::{query, query_as, query_scalar, Connection, Executor, Sqlite, SqliteConnection};
+
+/// An upper bound for SURTs starting with a prefix (since SQLite compares text bytewise).
+const SURT_PREFIX_UPPER_BOUND: char = '\u{10FFFF}';
+
+/// Replace the links recorded for a snapshot.
+///
+/// Links without a SURT (for example to IP addresses) are skipped, and only the first anchor
+/// text is kept for a link that appears more than once. Returns the number of links recorded.
+pub async fn replace_all(
+    connection: &mut SqliteConnection,
+    snapshot_id: i64,
+    links: &[Link],
+) -> Result<usize, sqlx::Error> {
+    let mut tx = connection.begin().await?;
+    let mut count = 0;
+
+    query!(
+        "DELETE FROM snapshot_link WHERE snapshot_id = ?",
+        snapshot_id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    for link in links {
+        if let Some(surt) = &link.surt {
+            let link_id = query_scalar!(
+                "INSERT INTO link(url, surt)
+                    VALUES (?, ?) ON CONFLICT DO UPDATE SET id = id RETURNING id",
+                link.url,
+                surt
+            )
+            .persistent(true)
+            .fetch_one(&mut *tx)
+            .await?;
+
+            count += query!(
+                "INSERT OR IGNORE INTO snapshot_link(snapshot_id, link_id, text, internal)
+                    VALUES (?, ?, ?, ?)",
+                snapshot_id,
+                link_id,
+                link.text,
+                link.internal
+            )
+            .persistent(true)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected() as usize;
+        }
+    }
+
+    tx.commit().await?;
+
+    Ok(count)
+}
+
+/// The links from a snapshot, in document order.
+pub async fn outbound<'c, E: Executor<'c, Database = Sqlite>>(
+    executor: E,
+    snapshot_id: i64,
+) -> Result<Vec<OutboundLink>, sqlx::Error> {
+    query_as!(
+        OutboundLink,
+        "SELECT link.url, link.surt, snapshot_link.text, snapshot_link.internal
+        FROM snapshot_link
+        JOIN link ON link.id = snapshot_link.link_id
+        WHERE snapshot_link.snapshot_id = ?
+        ORDER BY snapshot_link.rowid",
+        snapshot_id
+    )
+    .fetch_all(executor)
+    .await
+}
+
+/// Links to a SURT or SURT prefix across all snapshots, ordered by the time of the linking page.
+pub async fn inbound<'c, E: Executor<'c, Database = Sqlite>>(
+    executor: E,
+    target: &LinkTarget,
+    limit: usize,
+) -> Result<Vec<InboundLink>, sqlx::Error> {
+    let (lower, upper) = match target {
+        LinkTarget::Surt(surt) => (surt.clone(), surt.clone()),
+        LinkTarget::SurtPrefix(prefix) => {
+            (prefix.clone(), format!("{prefix}{SURT_PREFIX_UPPER_BOUND}"))
+        }
+    };
+    let limit = i64::try_from(limit).unwrap_or(i64::MAX);
+
+    let rows = query!(
+        r#"SELECT
+            snapshot_link.snapshot_id AS "snapshot_id!",
+            entry.url AS "page_url!",
+            MIN(entry.ts) AS "page_ts!: i64",
+            link.url AS "url!",
+            link.surt AS "surt!",
+            snapshot_link.text AS "text!"
+        FROM link
+        JOIN snapshot_link ON snapshot_link.link_id = link.id
+        JOIN entry_success ON entry_success.snapshot_id = snapshot_link.snapshot_id
+        JOIN entry ON entry.id = entry_success.entry_id
+        WHERE link.surt BETWEEN ? AND ?
+        GROUP BY snapshot_link.snapshot_id, link.id
+        ORDER BY MIN(entry.ts), link.surt
+        LIMIT ?"#,
+        lower,
+        upper,
+        limit
+    )
+    .fetch_all(executor)
+    .await?;
+
+    rows.into_iter()
+        .map(|row| {
+            Ok(InboundLink {
+                snapshot_id: row.snapshot_id,
+                page_url: row.page_url,
+                page_timestamp: row
+                    .page_ts
+                    .try_into()
+                    .map_err(|error| sqlx::Error::Decode(Box::new(error)))?,
+                url: row.url,
+                surt: row.surt,
+                text: row.text,
+            })
+        })
+        .collect()
+}
+
+/// The external domains linked to most often from a pattern's snapshots.
+pub async fn top_external_domains<'c, E: Executor<'c, Database = Sqlite>>(
+    executor: E,
+    pattern_slug: &str,
+    limit: usize,
+) -> Result<Vec<DomainCount>, sqlx::Error> {
+    let limit = i64::try_from(limit).unwrap_or(i64::MAX);
+
+    let rows = query!(
+        r#"SELECT
+            substr(link.surt, 1, instr(link.surt, ')') - 1) AS "domain!: String",
+            COUNT(*) AS "links!: i64",
+            COUNT(DISTINCT snapshot_link.snapshot_id) AS "snapshots!: i64"
+        FROM snapshot_link
+        JOIN link ON link.id = snapshot_link.link_id
+        WHERE snapshot_link.internal = FALSE AND snapshot_link.snapshot_id IN (
+            SELECT entry_success.snapshot_id
+            FROM entry_success
+            JOIN pattern_entry ON pattern_entry.entry_id = entry_success.entry_id
+            JOIN pattern ON pattern.id = pattern_entry.pattern_id
+            WHERE pattern.slug = ?
+        )
+        GROUP BY 1
+        ORDER BY 2 DESC, 1
+        LIMIT ?"#,
+        pattern_slug,
+        limit
+    )
+    .fetch_all(executor)
+    .await?;
+
+    rows.into_iter()
+        .map(|row| {
+            let mut parts = row.domain.split(',').collect::<Vec<_>>();
+            parts.reverse();
+
+            Ok(DomainCount {
+                domain: parts.join("."),
+                links: crate::model::try_cast(row.links)?,
+                snapshots: crate::model::try_cast(row.snapshots)?,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::Pattern;
+    use aib_cdx::entry::Entry as CdxEntry;
+    use aib_core::surt::Surt;
+    use chrono::Utc;
+    use sqlx::SqlitePool;
+
+    fn link(url: &str, surt: &str, text: &str, internal: bool) -> Link {
+        Link {
+            url: url.to_string(),
+            surt: Some(surt.to_string()),
+            text: text.to_string(),
+            internal,
+        }
+    }
+
+    #[sqlx::test]
+    async fn test_links(pool: SqlitePool) -> Result<(), sqlx::Error> {
+        let mut connection = pool.acquire().await?;
+
+        let url = "https://example.com/";
+        let digest = "XXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXX";
+        let entry_id = crate::db::entry::insert(
+            &mut connection,
+            &CdxEntry {
+                key: Surt::from_url(url).unwrap(),
+                timestamp: "20200101000000".parse().unwrap(),
+                original: url.to_string(),
+                mime_type: "text/html".parse().unwrap(),
+                status_code: Some(200),
+                digest: digest.parse().unwrap(),
+                length: 1000,
+                extra_info: None,
+            },
+        )
+        .await?;
+        let pattern_id = crate::db::pattern::insert(
+            &mut *connection,
+            &Pattern {
+                id: None,
+                surt: "com,example)/".parse().unwrap(),
+                name: "Example".to_string(),
+                slug: "example".to_string(),
+                sort_id: 0,
+                prefix: true,
+                stats: None,
+            },
+        )
+        .await?;
+        crate::db::pattern::insert_pattern_entry(&mut *connection, pattern_id, entry_id).await?;
+        crate::db::entry::insert_entry_success(
+            &mut connection,
+            entry_id,
+            digest,
+            Some(true),
+            Utc::now(),
+        )
+        .await?;
+        let snapshot_id = crate::db::snapshot::insert(&mut *connection, digest).await?;
+
+        let links = vec![
+            link(
+                "https://example.com/about",
+                "com,example)/about",
+                "About",
+                true,
+            ),
+            link("https://other.org/a", "org,other)/a", "A", false),
+            link("https://other.org/b", "org,other)/b", "B", false),
+            link("https://other.org/a", "org,other)/a", "A again", false),
+        ];
+
+        assert_eq!(replace_all(&mut connection, snapshot_id, &links).await?, 3);
+        assert_eq!(replace_all(&mut connection, snapshot_id, &links).await?, 3);
+
+        let outbound_links = outbound(&mut *connection, snapshot_id).await?;
+
+        assert_eq!(
+            outbound_links
+                .iter()
+                .map(|link| (link.surt.as_str(), link.text.as_str(), link.internal))
+                .collect::<Vec<_>>(),
+            vec![
+                ("com,example)/about", "About", true),
+                ("org,other)/a", "A", false),
+                ("org,other)/b", "B", false)
+            ]
+        );
+
+        let inbound_links = inbound(
+            &mut *connection,
+            &LinkTarget::SurtPrefix("org,other)/".to_string()),
+            10,
+        )
+        .await?;
+
+        assert_eq!(
+            inbound_links
+                .iter()
+                .map(|link| (link.page_url.as_str(), link.url.as_str()))
+                .collect::<Vec<_>>(),
+            vec![
+                ("https://example.com/", "https://other.org/a"),
+                ("https://example.com/", "https://other.org/b")
+            ]
+        );
+
+        let inbound_links = inbound(
+            &mut *connection,
+            &LinkTarget::url("https://OTHER.org/b").unwrap(),
+            10,
+        )
+        .await?;
+
+        assert_eq!(inbound_links.len(), 1);
+        assert_eq!(inbound_links[0].text, "B");
+
+        assert_eq!(
+            top_external_domains(&mut *connection, "example", 10).await?,
+            vec![DomainCount {
+                domain: "other.org".to_string(),
+                links: 2,
+                snapshots: 1,
+            }]
+        );
+
+        Ok(())
+    }
+}
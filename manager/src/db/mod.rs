@@ -5,8 +5,11 @@ use sqlx::{query, query_as, query_scalar, Acquire, Executor, Row, Sqlite, Sqlite
 use std::collections::HashMap;
 
 pub mod entry;
+pub mod link;
 pub mod model;
 pub mod pattern;
+pub mod resource;
+pub mod save;
 pub mod snapshot;
 pub mod surt;
 
@@ -257,7 +260,7 @@ impl<'a> Db<'a> {
             FROM entry
             LEFT JOIN entry_success ON entry_success.entry_id = entry.id
             JOIN surt ON surt.id = entry.surt_id
-            WHERE mime_type = ? AND entry_success.id IS NULL AND (entry.status_code IS NULL OR entry.status_code == 200) 
+            WHERE mime_type = ? AND digest != '-' AND entry_success.id IS NULL AND (entry.status_code IS NULL OR entry.status_code == 200) 
             ",
             mime_type,
         )
@@ -283,37 +286,4 @@ impl<'a> Db<'a> {
         .await?
         .is_some())
     }
-
-    async fn insert_link<'c, E: Executor<'c, Database = Sqlite>>(
-        executor: E,
-        url: &str,
-        surt: &str,
-    ) -> Result<i64, Error> {
-        Ok(query_scalar!(
-            "INSERT INTO link(url, surt)
-                VALUES (?, ?) ON CONFLICT DO UPDATE SET id = id RETURNING id",
-            url,
-            surt
-        )
-        .persistent(true)
-        .fetch_one(executor)
-        .await?)
-    }
-
-    async fn insert_snapshot_link<'c, E: Executor<'c, Database = Sqlite>>(
-        executor: E,
-        snapshot_id: i64,
-        link_id: i64,
-    ) -> Result<(), Error> {
-        query!(
-            "INSERT OR IGNORE INTO snapshot_link(snapshot_id, link_id) VALUES (?, ?)",
-            snapshot_id,
-            link_id
-        )
-        .persistent(true)
-        .execute(executor)
-        .await?;
-
-        Ok(())
-    }
 }
@@ -0,0 +1,160 @@
+use crate::model::{FailureClass, Resource};
+use aib_core::entry::UrlParts;
+use aib_extractor::requisites::RequisiteKind;
+use chrono::{DateTime, Utc};
+use sqlx::{query, query_as, query_scalar, Connection, Executor, Sqlite, SqliteConnection};
+
+/// Successfully downloaded pages whose resources have not been looked for yet.
+///
+/// Each page is returned with the URL and timestamp of its earliest capture.
+pub async fn pending_pages<'c, E: Executor<'c, Database = Sqlite>>(
+    executor: E,
+    mime_type: &str,
+    count: Option<usize>,
+) -> Result<Vec<(i64, String, UrlParts)>, sqlx::Error> {
+    let count = count.map(|value| value as i32).unwrap_or(i32::MAX);
+
+    let rows: Vec<(i64, String, String, i64)> = query_as(
+        "SELECT snapshot.id, snapshot.digest, entry.url, MIN(entry.ts)
+        FROM entry_success
+        JOIN entry ON entry.id = entry_success.entry_id
+        JOIN snapshot ON snapshot.id = entry_success.snapshot_id
+        LEFT JOIN snapshot_resource_scan ON snapshot_resource_scan.snapshot_id = snapshot.id
+        WHERE entry.mime_type = ? AND snapshot_resource_scan.snapshot_id IS NULL
+        GROUP BY snapshot.id
+        ORDER BY snapshot.id
+        LIMIT ?",
+    )
+    .bind(mime_type)
+    .bind(count)
+    .persistent(true)
+    .fetch_all(executor)
+    .await?;
+
+    rows.into_iter()
+        .map(|(snapshot_id, digest, url, timestamp)| {
+            Ok((
+                snapshot_id,
+                digest,
+                UrlParts::new(
+                    url,
+                    timestamp
+                        .try_into()
+                        .map_err(|error| sqlx::Error::Decode(Box::new(error)))?,
+                ),
+            ))
+        })
+        .collect()
+}
+
+/// Record the outcome of downloading one of a page's resources.
+///
+/// A successful outcome is the capture that was served and the digest of its
+/// content. A later attempt replaces an earlier one.
+pub async fn insert(
+    connection: &mut SqliteConnection,
+    snapshot_id: i64,
+    url: &str,
+    kind: RequisiteKind,
+    outcome: Result<(&UrlParts, &str), FailureClass>,
+    timestamp: DateTime<Utc>,
+) -> Result<(), sqlx::Error> {
+    let mut tx = connection.begin().await?;
+
+    let (capture, resource_snapshot_id, error_class) = match outcome {
+        Ok((capture, digest)) => (
+            Some(capture),
+            Some(crate::db::snapshot::insert(&mut *tx, digest).await?),
+            None,
+        ),
+        Err(error_class) => (None, None, Some(error_class)),
+    };
+    let kind = kind.as_str();
+    let resource_url = capture.map(|capture| capture.url.as_str());
+    let resource_ts = capture.map(|capture| capture.timestamp.0.timestamp());
+    let error_class = error_class.map(|error_class| error_class.as_str());
+    let timestamp = timestamp.timestamp();
+
+    query!(
+        "INSERT INTO snapshot_resource(snapshot_id, url, kind, resource_url, resource_ts, resource_snapshot_id, error_class, ts)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+            ON CONFLICT DO UPDATE SET
+                kind = excluded.kind,
+                resource_url = excluded.resource_url,
+                resource_ts = excluded.resource_ts,
+                resource_snapshot_id = excluded.resource_snapshot_id,
+                error_class = excluded.error_class,
+                ts = excluded.ts",
+        snapshot_id,
+        url,
+        kind,
+        resource_url,
+        resource_ts,
+        resource_snapshot_id,
+        error_class,
+        timestamp
+    )
+    .persistent(true)
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
+/// Mark a page's resources as having been looked for.
+pub async fn mark_scanned<'c, E: Executor<'c, Database = Sqlite>>(
+    executor: E,
+    snapshot_id: i64,
+    timestamp: DateTime<Utc>,
+) -> Result<(), sqlx::Error> {
+    let timestamp = timestamp.timestamp();
+
+    query!(
+        "INSERT OR REPLACE INTO snapshot_resource_scan(snapshot_id, ts) VALUES (?, ?)",
+        snapshot_id,
+        timestamp
+    )
+    .persistent(true)
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+/// The resources recorded for a page.
+pub async fn get_resources<'c, E: Executor<'c, Database = Sqlite>>(
+    executor: E,
+    snapshot_id: i64,
+) -> Result<Vec<Resource>, sqlx::Error> {
+    query_as(
+        "SELECT url, kind, resource_url, resource_ts, snapshot.digest AS digest, error_class
+        FROM snapshot_resource
+        LEFT JOIN snapshot ON snapshot.id = snapshot_resource.resource_snapshot_id
+        WHERE snapshot_resource.snapshot_id = ?
+        ORDER BY url",
+    )
+    .bind(snapshot_id)
+    .persistent(true)
+    .fetch_all(executor)
+    .await
+}
+
+/// The pages that use the resource with the given digest.
+pub async fn get_pages<'c, E: Executor<'c, Database = Sqlite>>(
+    executor: E,
+    digest: &str,
+) -> Result<Vec<i64>, sqlx::Error> {
+    query_scalar!(
+        "SELECT DISTINCT snapshot_resource.snapshot_id
+        FROM snapshot_resource
+        JOIN snapshot ON snapshot.id = snapshot_resource.resource_snapshot_id
+        WHERE snapshot.digest = ?
+        ORDER BY snapshot_resource.snapshot_id",
+        digest
+    )
+    .persistent(true)
+    .fetch_all(executor)
+    .await
+}
@@ -0,0 +1,80 @@
+use aib_core::entry::UrlParts;
+use chrono::{DateTime, Utc};
+use sqlx::{query, query_scalar, Executor, Sqlite};
+
+/// Record a completed Save Page Now request.
+pub async fn insert_success<'c, E: Executor<'c, Database = Sqlite>>(
+    executor: E,
+    pattern_id: Option<u64>,
+    job_id: &str,
+    capture: &UrlParts,
+    timestamp: DateTime<Utc>,
+) -> Result<(), sqlx::Error> {
+    let pattern_id = pattern_id.map(|value| value as i64);
+    let capture_ts = capture.timestamp.0.timestamp();
+    let timestamp = timestamp.timestamp();
+
+    query!(
+        "INSERT INTO save_request(url, pattern_id, job_id, capture_ts, ts) VALUES (?, ?, ?, ?, ?)",
+        capture.url,
+        pattern_id,
+        job_id,
+        capture_ts,
+        timestamp
+    )
+    .persistent(true)
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+/// Record a rejected or failed Save Page Now request.
+pub async fn insert_failure<'c, E: Executor<'c, Database = Sqlite>>(
+    executor: E,
+    url: &str,
+    pattern_id: Option<u64>,
+    job_id: Option<&str>,
+    status_ext: Option<&str>,
+    error_message: &str,
+    timestamp: DateTime<Utc>,
+) -> Result<(), sqlx::Error> {
+    let pattern_id = pattern_id.map(|value| value as i64);
+    let timestamp = timestamp.timestamp();
+
+    query!(
+        "INSERT INTO save_request(url, pattern_id, job_id, status_ext, error_message, ts)
+            VALUES (?, ?, ?, ?, ?, ?)",
+        url,
+        pattern_id,
+        job_id,
+        status_ext,
+        error_message,
+        timestamp
+    )
+    .persistent(true)
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+/// The distinct URLs of the entries imported for a pattern.
+pub async fn pattern_urls<'c, E: Executor<'c, Database = Sqlite>>(
+    executor: E,
+    pattern_id: u64,
+) -> Result<Vec<String>, sqlx::Error> {
+    let pattern_id = pattern_id as i64;
+
+    query_scalar!(
+        "SELECT DISTINCT entry.url
+            FROM pattern_entry
+            JOIN entry ON entry.id = pattern_entry.entry_id
+            WHERE pattern_entry.pattern_id = ?
+            ORDER BY entry.url",
+        pattern_id
+    )
+    .persistent(true)
+    .fetch_all(executor)
+    .await
+}
@@ -1,4 +1,5 @@
-use sqlx::{query_scalar, Executor, Sqlite};
+use aib_downloader::OriginalHeaders;
+use sqlx::{query, query_as, query_scalar, Executor, Sqlite};
 
 pub async fn insert<'c, E: Executor<'c, Database = Sqlite>>(
     executor: E,
@@ -13,3 +14,109 @@ pub async fn insert<'c, E: Executor<'c, Database = Sqlite>>(
     .fetch_one(executor)
     .await
 }
+
+/// All snapshot digests, with the largest CDX length recorded for each.
+pub async fn get_all_digests<'c, E: Executor<'c, Database = Sqlite>>(
+    executor: E,
+) -> Result<Vec<(i64, String, Option<i64>)>, sqlx::Error> {
+    query_as(
+        "SELECT snapshot.id, snapshot.digest, MAX(entry.length)
+        FROM snapshot
+        LEFT JOIN entry_success ON entry_success.snapshot_id = snapshot.id
+        LEFT JOIN entry ON entry.id = entry_success.entry_id
+        GROUP BY snapshot.id
+        ORDER BY snapshot.digest",
+    )
+    .persistent(true)
+    .fetch_all(executor)
+    .await
+}
+
+/// Record the original response headers for a snapshot.
+///
+/// If headers have already been recorded for the snapshot, they are kept.
+pub async fn insert_headers<'c, E: Executor<'c, Database = Sqlite>>(
+    executor: E,
+    snapshot_id: i64,
+    headers: &OriginalHeaders,
+) -> Result<(), sqlx::Error> {
+    query!(
+        "INSERT OR IGNORE INTO snapshot_header(snapshot_id, content_type, date, last_modified, server, set_cookie)
+            VALUES (?, ?, ?, ?, ?, ?)",
+        snapshot_id,
+        headers.content_type,
+        headers.date,
+        headers.last_modified,
+        headers.server,
+        headers.set_cookie
+    )
+    .persistent(true)
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn get_headers<'c, E: Executor<'c, Database = Sqlite>>(
+    executor: E,
+    snapshot_id: i64,
+) -> Result<Option<OriginalHeaders>, sqlx::Error> {
+    query_as!(
+        OriginalHeaders,
+        "SELECT content_type, date, last_modified, server, set_cookie
+        FROM snapshot_header
+        WHERE snapshot_id = ?",
+        snapshot_id
+    )
+    .fetch_optional(executor)
+    .await
+}
+
+/// The URL of the earliest entry captured as the given snapshot.
+pub async fn get_url<'c, E: Executor<'c, Database = Sqlite>>(
+    executor: E,
+    snapshot_id: i64,
+) -> Result<Option<String>, sqlx::Error> {
+    query_scalar(
+        "SELECT entry.url
+            FROM entry
+            JOIN entry_success ON entry_success.entry_id = entry.id
+            WHERE entry_success.snapshot_id = ?
+            ORDER BY entry.ts
+            LIMIT 1",
+    )
+    .bind(snapshot_id)
+    .fetch_optional(executor)
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::SqlitePool;
+
+    #[sqlx::test]
+    async fn test_headers(pool: SqlitePool) -> Result<(), sqlx::Error> {
+        let mut connection = pool.acquire().await?;
+        let snapshot_id = insert(&mut *connection, "XXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXX").await?;
+        let headers = OriginalHeaders {
+            content_type: Some("text/html; charset=utf-8".to_string()),
+            date: Some("Sun, 08 May 2016 21:55:03 GMT".to_string()),
+            last_modified: None,
+            server: Some("tsa_a".to_string()),
+            set_cookie: true,
+        };
+
+        assert_eq!(get_headers(&mut *connection, snapshot_id).await?, None);
+
+        insert_headers(&mut *connection, snapshot_id, &headers).await?;
+        insert_headers(&mut *connection, snapshot_id, &OriginalHeaders::default()).await?;
+
+        assert_eq!(
+            get_headers(&mut *connection, snapshot_id).await?,
+            Some(headers)
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,393 @@
+//! Concurrent downloading of missing entries into the item store.
+//!
+//! Every attempt is recorded in the database, either as an `entry_success` or
+//! an `entry_failure` row, so an interrupted run can simply be restarted: it
+//! will only request entries that have not been attempted yet, or whose
+//! failures the retry policy allows to be attempted again.
+//!
+//! Each entry is requested from the archive it was imported from.
+
+use crate::model::{Entry, FailureClass, RetryPolicy};
+use aib_core::digest::Sha1Digest;
+use aib_core::entry::UrlParts;
+use aib_downloader::{ArchiveDownloader, Downloader, OriginalHeaders};
+use aib_store::items::ItemStore;
+use chrono::Utc;
+use futures::StreamExt;
+use sqlx::SqliteConnection;
+
+const DEFAULT_PARALLELISM: usize = 4;
+const DEFAULT_BATCH_SIZE: usize = 1000;
+const NOT_FOUND_STATUS_CODE: u16 = 404;
+const NO_STATUS_CODE: u16 = 0;
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("I/O error")]
+    Io(#[from] std::io::Error),
+    #[error("SQL error")]
+    Sqlx(#[from] sqlx::Error),
+    #[error("Digest error")]
+    Digest(#[from] aib_core::digest::Error),
+    #[error("Item store error")]
+    Store(#[from] aib_store::items::Error),
+    #[error("Task error")]
+    Task(#[from] tokio::task::JoinError),
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Config {
+    /// The number of downloads in progress at any time.
+    pub parallelism: usize,
+    /// The number of entries read from the database at a time.
+    pub batch_size: usize,
+    /// The maximum number of entries to attempt in this run.
+    pub limit: Option<usize>,
+    pub retry_policy: RetryPolicy,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            parallelism: DEFAULT_PARALLELISM,
+            batch_size: DEFAULT_BATCH_SIZE,
+            limit: None,
+            retry_policy: RetryPolicy::default(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Summary {
+    pub downloaded: usize,
+    pub invalid_digest: usize,
+    pub failed: usize,
+    /// The number of failures that will not be retried.
+    pub permanent: usize,
+}
+
+impl Summary {
+    pub fn total(&self) -> usize {
+        self.downloaded + self.failed
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum Outcome {
+    Success {
+        digest: Sha1Digest,
+        /// Unknown for captures from archives that don't provide digests.
+        correct_digest: Option<bool>,
+        headers: OriginalHeaders,
+        redirects: Vec<UrlParts>,
+    },
+    Failure {
+        status_code: u16,
+        error_class: FailureClass,
+        error_message: String,
+        redirects: Vec<UrlParts>,
+    },
+}
+
+impl Outcome {
+    fn redirects(&self) -> &[UrlParts] {
+        match self {
+            Self::Success { redirects, .. } | Self::Failure { redirects, .. } => redirects,
+        }
+    }
+}
+
+/// Download pending entries with the given MIME type until none are left.
+///
+/// Request rate limits should be configured on the downloaders.
+pub async fn run(
+    connection: &mut SqliteConnection,
+    downloader: &ArchiveDownloader,
+    store: &ItemStore,
+    mime_type: &str,
+    config: &Config,
+) -> Result<Summary, Error> {
+    let started = Utc::now();
+    let mut summary = Summary::default();
+
+    loop {
+        let batch_size = config
+            .limit
+            .map(|limit| limit.saturating_sub(summary.total()).min(config.batch_size))
+            .unwrap_or(config.batch_size);
+
+        if batch_size == 0 {
+            break;
+        }
+
+        // Entries that fail during this run will not be due again until after it ends.
+        let entries = crate::db::entry::pending_entries(
+            &mut *connection,
+            mime_type,
+            &config.retry_policy,
+            started,
+            Some(batch_size),
+        )
+        .await?;
+
+        if entries.is_empty() {
+            break;
+        }
+
+        let mut results = futures::stream::iter(entries)
+            .map(|entry| async move {
+                let outcome = download_entry(downloader.get(entry.archive), store, &entry).await;
+
+                (entry, outcome)
+            })
+            .buffer_unordered(config.parallelism);
+
+        while let Some((entry, outcome)) = results.next().await {
+            let outcome = outcome?;
+
+            if !outcome.redirects().is_empty() {
+                crate::db::entry::insert_redirects(&mut *connection, entry.id, outcome.redirects())
+                    .await?;
+            }
+
+            match outcome {
+                Outcome::Success {
+                    digest,
+                    correct_digest,
+                    headers,
+                    ..
+                } => {
+                    let digest_string = digest.to_string();
+
+                    crate::db::entry::insert_entry_success(
+                        &mut *connection,
+                        entry.id,
+                        &digest_string,
+                        correct_digest,
+                        Utc::now(),
+                    )
+                    .await?;
+
+                    if !headers.is_empty() {
+                        let snapshot_id =
+                            crate::db::snapshot::insert(&mut *connection, &digest_string).await?;
+                        crate::db::snapshot::insert_headers(
+                            &mut *connection,
+                            snapshot_id,
+                            &headers,
+                        )
+                        .await?;
+                    }
+
+                    if correct_digest == Some(false) {
+                        log::warn!(
+                            "Invalid digest for {} ({}): {} instead of {}",
+                            entry.entry.original,
+                            entry.entry.timestamp,
+                            digest,
+                            entry.entry.digest
+                        );
+                        summary.invalid_digest += 1;
+                    }
+
+                    summary.downloaded += 1;
+                }
+                Outcome::Failure {
+                    status_code,
+                    error_class,
+                    error_message,
+                    ..
+                } => {
+                    log::warn!(
+                        "Failed to download {} ({}): {} ({})",
+                        entry.entry.original,
+                        entry.entry.timestamp,
+                        error_message,
+                        error_class
+                    );
+
+                    crate::db::entry::insert_entry_error(
+                        &mut *connection,
+                        entry.id as i64,
+                        Utc::now(),
+                        status_code,
+                        error_class,
+                        &error_message,
+                    )
+                    .await?;
+
+                    if error_class.is_permanent() {
+                        summary.permanent += 1;
+                    }
+
+                    summary.failed += 1;
+                }
+            }
+        }
+    }
+
+    Ok(summary)
+}
+
+async fn download_entry(
+    downloader: &Downloader,
+    store: &ItemStore,
+    entry: &Entry,
+) -> Result<Outcome, Error> {
+    let download = match &entry.memento_url {
+        Some(memento_url) => {
+            downloader
+                .download_memento_to(
+                    memento_url,
+                    &entry.entry.original,
+                    entry.entry.timestamp,
+                    || store.writer(),
+                )
+                .await
+        }
+        None => {
+            downloader
+                .download_to(
+                    &entry.entry.original,
+                    entry.entry.timestamp,
+                    aib_downloader::Modifier::Original,
+                    || store.writer(),
+                )
+                .await
+        }
+    };
+
+    match download {
+        Ok(Some(download)) => {
+            for redirect in &download.redirects {
+                log::warn!(
+                    "Redirecting: {} ({}) to {}",
+                    entry.entry.original,
+                    entry.entry.timestamp,
+                    redirect.url
+                );
+            }
+
+            let store = store.clone();
+            // Captures from other archives or from Save Page Now have no known digest.
+            let expected = Some(entry.entry.digest.valid())
+                .filter(|_| entry.entry.digest.invalid() != Some(crate::db::entry::UNKNOWN_DIGEST));
+            let digest = download.digest;
+            let writer = download.writer;
+
+            tokio::task::spawn_blocking(move || store.commit(&digest.to_string(), writer))
+                .await??;
+
+            Ok(Outcome::Success {
+                digest,
+                correct_digest: expected.map(|expected| expected == Some(digest)),
+                headers: download.headers,
+                redirects: download.redirects,
+            })
+        }
+        Ok(None) => Ok(Outcome::Failure {
+            status_code: NOT_FOUND_STATUS_CODE,
+            error_class: FailureClass::NotFound,
+            error_message: "Not found".to_string(),
+            redirects: vec![],
+        }),
+        Err(error) => {
+            let (status_code, error_class) = classify(&error);
+
+            Ok(Outcome::Failure {
+                status_code,
+                error_class,
+                error_message: error.to_string(),
+                redirects: error
+                    .redirect_chain()
+                    .map(|chain| chain.redirects.clone())
+                    .unwrap_or_default(),
+            })
+        }
+    }
+}
+
+/// Determine the status code (if any) and failure class for a download error.
+pub fn classify(error: &aib_downloader::Error) -> (u16, FailureClass) {
+    match error {
+        aib_downloader::Error::UnexpectedStatus(status_code) => (
+            status_code.as_u16(),
+            FailureClass::from_status_code(status_code.as_u16()),
+        ),
+        aib_downloader::Error::Client(error) => match error.status() {
+            Some(status_code) => (
+                status_code.as_u16(),
+                FailureClass::from_status_code(status_code.as_u16()),
+            ),
+            None if error.is_timeout() => (NO_STATUS_CODE, FailureClass::Timeout),
+            None => (NO_STATUS_CODE, FailureClass::Network),
+        },
+        aib_downloader::Error::UnexpectedRedirect(_)
+        | aib_downloader::Error::UnexpectedRedirectUrl(_)
+        | aib_downloader::Error::RedirectLoop(_)
+        | aib_downloader::Error::TooManyRedirects(_) => {
+            (NO_STATUS_CODE, FailureClass::InvalidRedirect)
+        }
+        aib_downloader::Error::BodyTooLarge { .. } => (NO_STATUS_CODE, FailureClass::TooLarge),
+        aib_downloader::Error::Truncated { .. } => (NO_STATUS_CODE, FailureClass::Truncated),
+        _ => (NO_STATUS_CODE, FailureClass::Other),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::StatusCode;
+
+    #[test]
+    fn classify_errors() {
+        assert_eq!(
+            classify(&aib_downloader::Error::UnexpectedStatus(
+                StatusCode::TOO_MANY_REQUESTS
+            )),
+            (429, FailureClass::RateLimited)
+        );
+        assert_eq!(
+            classify(&aib_downloader::Error::UnexpectedStatus(
+                StatusCode::FORBIDDEN
+            )),
+            (403, FailureClass::Blocked)
+        );
+        assert_eq!(
+            classify(&aib_downloader::Error::UnexpectedStatus(
+                StatusCode::BAD_GATEWAY
+            )),
+            (502, FailureClass::ServerError)
+        );
+        assert_eq!(
+            classify(&aib_downloader::Error::UnexpectedRedirect(None)),
+            (0, FailureClass::InvalidRedirect)
+        );
+        assert_eq!(
+            classify(&aib_downloader::Error::TooManyRedirects(
+                aib_downloader::RedirectChain {
+                    start: UrlParts::new(
+                        "http://a.com/".to_string(),
+                        "20160508215503".parse().unwrap()
+                    ),
+                    redirects: vec![],
+                }
+            )),
+            (0, FailureClass::InvalidRedirect)
+        );
+        assert_eq!(
+            classify(&aib_downloader::Error::BodyTooLarge { limit: 1024 }),
+            (0, FailureClass::TooLarge)
+        );
+        assert_eq!(
+            classify(&aib_downloader::Error::Truncated {
+                expected: 1024,
+                received: 512
+            }),
+            (0, FailureClass::Truncated)
+        );
+        assert!(!FailureClass::Truncated.is_permanent());
+        assert!(FailureClass::Blocked.is_permanent());
+        assert!(!FailureClass::Timeout.is_permanent());
+    }
+}
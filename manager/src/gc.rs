@@ -0,0 +1,178 @@
+//! Garbage collection for the item store.
+//!
+//! Items are orphaned when no snapshot in the database refers to them (for
+//! example because their pattern was dropped), and snapshots are missing when
+//! the database refers to an item that is not in the store.
+
+use aib_core::digest::Sha1Digest;
+use aib_store::items::{ItemStore, ValidationError};
+use sqlx::SqliteConnection;
+use std::collections::HashSet;
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("I/O error")]
+    Io(#[from] std::io::Error),
+    #[error("SQL error")]
+    Sqlx(#[from] sqlx::Error),
+    #[error("Digest error")]
+    Digest(#[from] aib_core::digest::Error),
+    #[error("Item store error")]
+    Store(#[from] aib_store::items::Error),
+}
+
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    /// The item is in the store but has no snapshot row.
+    Orphaned,
+    /// The snapshot row exists but the item is not in the store.
+    Missing,
+}
+
+#[derive(Clone, Debug, Eq, Ord, PartialEq, PartialOrd, serde::Serialize)]
+pub struct Item {
+    pub status: Status,
+    pub digest: Sha1Digest,
+    /// The compressed size on disk for orphaned items, or the largest CDX
+    /// length for missing items.
+    pub size: Option<u64>,
+}
+
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Report {
+    pub orphaned: Vec<Item>,
+    pub missing: Vec<Item>,
+}
+
+impl Report {
+    pub fn orphaned_size(&self) -> u64 {
+        self.orphaned.iter().filter_map(|item| item.size).sum()
+    }
+
+    pub fn items(&self) -> impl Iterator<Item = &Item> {
+        self.orphaned.iter().chain(self.missing.iter())
+    }
+}
+
+pub async fn report(connection: &mut SqliteConnection, store: &ItemStore) -> Result<Report, Error> {
+    let snapshots = crate::db::snapshot::get_all_digests(&mut *connection).await?;
+    let mut known = HashSet::with_capacity(snapshots.len());
+    let mut report = Report::default();
+
+    for (_, digest, length) in snapshots {
+        let digest = digest.parse::<Sha1Digest>()?;
+
+        if !store.contains(&digest.to_string()) {
+            report.missing.push(Item {
+                status: Status::Missing,
+                digest,
+                size: length.and_then(|length| length.try_into().ok()),
+            });
+        }
+
+        known.insert(digest);
+    }
+
+    for result in store.unchecked_entries() {
+        match result? {
+            Ok(entry) => {
+                if !known.contains(&entry.digest) {
+                    report.orphaned.push(Item {
+                        status: Status::Orphaned,
+                        digest: entry.digest,
+                        size: Some(entry.path.metadata()?.len()),
+                    });
+                }
+            }
+            Err(ValidationError::Unexpected(path)) => {
+                log::warn!("Unexpected path in item store: {:?}", path);
+            }
+            Err(ValidationError::InvalidDigest { .. }) => {}
+        }
+    }
+
+    report.orphaned.sort();
+
+    Ok(report)
+}
+
+/// Delete orphaned items that are not in the retention allowlist.
+///
+/// Returns the number of items deleted and their total compressed size.
+pub fn collect(
+    store: &ItemStore,
+    report: &Report,
+    allowlist: &HashSet<Sha1Digest>,
+) -> Result<(usize, u64), Error> {
+    let mut count = 0;
+    let mut size = 0;
+
+    for item in &report.orphaned {
+        if !allowlist.contains(&item.digest) {
+            if let Some(removed) = store.remove(&item.digest.to_string())? {
+                count += 1;
+                size += removed;
+            }
+        }
+    }
+
+    Ok((count, size))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::SqlitePool;
+
+    #[sqlx::test]
+    async fn test_report_and_collect(pool: SqlitePool) -> Result<(), Error> {
+        let mut connection = pool.acquire().await?;
+        let dir = tempdir::TempDir::new("gc")?;
+        let store = ItemStore::new(dir.path(), None);
+
+        let kept = aib_core::digest::compute_digest(&mut "kept".as_bytes())?;
+        let orphaned = aib_core::digest::compute_digest(&mut "orphaned".as_bytes())?;
+        let allowed = aib_core::digest::compute_digest(&mut "allowed".as_bytes())?;
+        let missing = aib_core::digest::compute_digest(&mut "missing".as_bytes())?;
+
+        store.save(&kept.to_string(), &mut "kept".as_bytes())?;
+        store.save(&orphaned.to_string(), &mut "orphaned".as_bytes())?;
+        store.save(&allowed.to_string(), &mut "allowed".as_bytes())?;
+
+        crate::db::snapshot::insert(&mut *connection, &kept.to_string()).await?;
+        crate::db::snapshot::insert(&mut *connection, &missing.to_string()).await?;
+
+        let report = report(&mut connection, &store).await?;
+
+        let mut expected_orphaned = vec![orphaned, allowed];
+        expected_orphaned.sort();
+
+        assert_eq!(
+            report
+                .orphaned
+                .iter()
+                .map(|item| item.digest)
+                .collect::<Vec<_>>(),
+            expected_orphaned
+        );
+        assert_eq!(
+            report.missing,
+            vec![Item {
+                status: Status::Missing,
+                digest: missing,
+                size: None
+            }]
+        );
+
+        let allowlist = vec![allowed].into_iter().collect();
+        let (count, _) = collect(&store, &report, &allowlist)?;
+
+        assert_eq!(count, 1);
+        assert!(store.contains(&kept.to_string()));
+        assert!(store.contains(&allowed.to_string()));
+        assert!(!store.contains(&orphaned.to_string()));
+
+        Ok(())
+    }
+}
@@ -81,8 +81,14 @@ pub async fn find_local_snapshots(
     for Entry { id, entry, .. } in entries {
         let digest = entry.digest.to_string();
         if store.contains(&digest) {
-            crate::db::entry::insert_entry_success(&mut *connection, id, &digest, true, Utc::now())
-                .await?;
+            crate::db::entry::insert_entry_success(
+                &mut *connection,
+                id,
+                &digest,
+                Some(true),
+                Utc::now(),
+            )
+            .await?;
 
             count += 1;
         }
@@ -164,7 +170,7 @@ pub async fn import_invalid_digests(
                     &mut *connection,
                     entry_id,
                     &actual_digest,
-                    false,
+                    Some(false),
                     Utc::now(),
                 )
                 .await?;
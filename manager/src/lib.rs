@@ -1,14 +1,20 @@
-use aib_extractor::Document;
+use aib_extractor::{site::Extractors, Document, Format, Mode};
 use aib_indexer::{Index, Query};
 use itertools::Itertools;
 use sqlx::SqlitePool;
+use std::collections::BTreeMap;
 use std::fs::File;
 use std::io::Read;
 use std::path::{Path, PathBuf};
 
 pub mod db;
+pub mod download;
+pub mod gc;
 pub mod import;
+pub mod memento;
 pub mod model;
+pub mod requisites;
+pub mod save;
 pub mod search;
 
 const DEFAULT_FIRST_YEAR: u16 = 2004;
@@ -41,12 +47,39 @@ pub enum Error {
     Search(#[from] search::Error),
     #[error("Snapshot missing for digest")]
     MissingSnapshot(String),
+    #[error("Unsupported MIME type")]
+    UnsupportedMimeType(String),
+}
+
+/// A document extracted from a stored snapshot.
+struct Extraction {
+    document: Result<Document<'static>, aib_extractor::Error>,
+    /// The charset used to decode text formats, and whether it had malformed sequences.
+    charset: Option<(aib_extractor::charset::Charset, bool)>,
+}
+
+/// The outcome of indexing snapshots.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct IndexSummary {
+    pub documents: usize,
+    /// The number of text documents decoded for each detected charset.
+    pub charsets: BTreeMap<&'static str, usize>,
+    /// The number of documents with malformed sequences for their charset.
+    pub decoding_errors: usize,
+    /// The number of documents that could not be extracted (for example invalid JSON).
+    pub extraction_errors: usize,
+    /// The number of links recorded.
+    pub links: usize,
+    /// The number of items skipped because their metadata shows a different type.
+    pub skipped: usize,
 }
 
 pub struct Manager {
     db_pool: SqlitePool,
     pub index: Index,
     store: aib_store::items::ItemStore,
+    extractors: Extractors,
+    mode: Mode,
 }
 
 impl Manager {
@@ -67,42 +100,75 @@ impl Manager {
             db_pool: pool,
             index: Index::open(index_path, &pattern_slugs, DEFAULT_FIRST_YEAR)?,
             store: aib_store::items::ItemStore::new(store_path, level),
+            extractors: Extractors::default(),
+            mode: Mode::default(),
         })
     }
 
-    pub fn extract(&self) -> Result<(), Error> {
-        let mut buffer = String::new();
-        for path in self.store.files() {
-            let path = path?;
-            let mut decoder = zstd::Decoder::new(File::open(&path)?)?;
-            buffer.clear();
-            match decoder
-                .read_to_string(&mut buffer)
-                .map_err(|error| Error::IoWithPath(error, path))
-            {
-                Ok(_) => {
-                    let html = Document::parse(&buffer)?;
+    /// Set how the text of HTML documents is extracted.
+    pub fn with_mode(self, mode: Mode) -> Self {
+        Self { mode, ..self }
+    }
+
+    /// Use a metadata table to skip items that can't be extracted without decompressing them.
+    pub fn with_metadata(self, table: aib_store::items::metadata::MetadataTable) -> Self {
+        Self {
+            store: self.store.with_metadata(table),
+            ..self
+        }
+    }
 
-                    for link in html.links {
-                        println!("{}", link);
+    /// Extract and record the links of snapshots with the given MIME type, without indexing them.
+    ///
+    /// Returns the number of links recorded.
+    pub async fn extract(&self, mime_type: &str) -> Result<usize, Error> {
+        let format = Format::from_mime_type(mime_type)
+            .ok_or_else(|| Error::UnsupportedMimeType(mime_type.to_string()))?;
+
+        let mut connection = self.db_pool.acquire().await?;
+        let mut db = db::Db::new(&mut connection);
+
+        let snapshot_info = db.get_snapshot_info(mime_type).await?;
+        let mut buffer = vec![];
+        let mut count = 0;
+
+        for (snapshot_id, digest) in snapshot_info
+            .into_iter()
+            .map(|(snapshot_id, _, _, digest, _)| (snapshot_id, digest))
+            .dedup()
+        {
+            if let Some(extraction) = self
+                .read_document(&mut connection, snapshot_id, &digest, format, &mut buffer)
+                .await?
+            {
+                match extraction.document {
+                    Ok(document) => {
+                        count +=
+                            db::link::replace_all(&mut connection, snapshot_id, &document.links)
+                                .await?;
+                    }
+                    Err(error) => {
+                        log::warn!("Extraction failed for {}: {:?}", digest, error);
                     }
-                }
-                Err(error) => {
-                    log::warn!("{:?}", error);
                 }
             }
         }
 
-        Ok(())
+        Ok(count)
     }
 
-    pub async fn index(&mut self, mime_type: &str) -> Result<usize, Error> {
+    /// Index snapshots with the given MIME type, decoding them according to
+    /// their detected charsets.
+    pub async fn index(&mut self, mime_type: &str) -> Result<IndexSummary, Error> {
+        let format = Format::from_mime_type(mime_type)
+            .ok_or_else(|| Error::UnsupportedMimeType(mime_type.to_string()))?;
+
         let mut connection = self.db_pool.acquire().await?;
         let mut db = db::Db::new(&mut connection);
 
         let snapshot_info = db.get_snapshot_info(mime_type).await?;
-        let mut buffer = String::new();
-        let mut count = 0;
+        let mut buffer = vec![];
+        let mut summary = IndexSummary::default();
 
         for (_, mut group) in &snapshot_info
             .into_iter()
@@ -111,41 +177,123 @@ impl Manager {
             // Safe because of guarantees provided by Itertools.
             let (snapshot_id, surt_id, pattern_slug, digest, timestamp) = group.next().unwrap();
 
-            let path = self
-                .store
-                .location(&digest)
-                .ok_or_else(|| Error::MissingSnapshot(digest))?;
+            if let Some(metadata) = digest
+                .parse()
+                .ok()
+                .and_then(|digest| self.store.metadata(&digest))
+            {
+                if !format.accepts_sniffed(&metadata.mime_type) {
+                    log::info!("Skipping {} ({})", digest, metadata.mime_type);
+                    summary.skipped += 1;
+                    continue;
+                }
+            }
 
-            let mut decoder = zstd::Decoder::new(File::open(&path)?)?;
-            buffer.clear();
+            let Some(extraction) = self
+                .read_document(&mut connection, snapshot_id, &digest, format, &mut buffer)
+                .await?
+            else {
+                continue;
+            };
 
-            match decoder
-                .read_to_string(&mut buffer)
-                .map_err(|error| Error::IoWithPath(error, path))
-            {
-                Ok(_) => {
-                    let html = scraper::Html::parse_document(&buffer);
-                    let document = Document::extract(&html)?;
-
-                    self.index.add_document(
-                        snapshot_id,
-                        surt_id,
-                        &pattern_slug,
-                        timestamp,
-                        &document,
-                    )?;
-
-                    count += 1;
+            if let Some((charset, had_errors)) = extraction.charset {
+                if had_errors {
+                    summary.decoding_errors += 1;
                 }
+
+                *summary.charsets.entry(charset.name()).or_default() += 1;
+            }
+
+            // Invalid JSON or PDF content should not stop indexing.
+            let document = match extraction.document {
+                Ok(document) => document,
                 Err(error) => {
-                    log::warn!("{:?}", error);
+                    log::warn!("Extraction failed for {}: {:?}", digest, error);
+                    summary.extraction_errors += 1;
+                    continue;
                 }
-            }
+            };
+
+            summary.links +=
+                db::link::replace_all(&mut connection, snapshot_id, &document.links).await?;
+
+            self.index
+                .add_document(snapshot_id, surt_id, &pattern_slug, timestamp, &document)?;
+
+            summary.documents += 1;
         }
 
         self.index.commit_writer()?;
 
-        Ok(count)
+        Ok(summary)
+    }
+
+    /// Read a snapshot from the item store, decode it (for text formats) and extract a document
+    /// according to its format.
+    ///
+    /// Returns `None` (after logging) if the item can't be read.
+    async fn read_document(
+        &self,
+        connection: &mut sqlx::SqliteConnection,
+        snapshot_id: i64,
+        digest: &str,
+        format: Format,
+        buffer: &mut Vec<u8>,
+    ) -> Result<Option<Extraction>, Error> {
+        let path = self
+            .store
+            .location(digest)
+            .ok_or_else(|| Error::MissingSnapshot(digest.to_string()))?;
+
+        let mut decoder = zstd::Decoder::new(File::open(&path)?)?;
+        buffer.clear();
+
+        if let Err(error) = decoder.read_to_end(buffer) {
+            log::warn!("{:?}", Error::IoWithPath(error, path));
+            return Ok(None);
+        }
+
+        if !format.is_text() {
+            return Ok(Some(Extraction {
+                document: Document::parse_pdf(buffer, self.mode),
+                charset: None,
+            }));
+        }
+
+        let headers = db::snapshot::get_headers(&mut *connection, snapshot_id).await?;
+        let content_type = headers.and_then(|headers| headers.content_type);
+        let decoded = aib_extractor::charset::decode(buffer, content_type.as_deref());
+
+        if decoded.had_errors {
+            log::warn!(
+                "Malformed {} content in {} ({})",
+                decoded.charset.name(),
+                digest,
+                decoded.charset.source
+            );
+        }
+
+        let document = match format {
+            Format::Json => Document::parse_json(&decoded.text),
+            Format::Text => Ok(Document::parse_text(&decoded.text, self.mode)),
+            _ => {
+                let html =
+                    scraper::Html::parse_document(&aib_extractor::wayback::clean(&decoded.text));
+
+                match db::snapshot::get_url(&mut *connection, snapshot_id).await? {
+                    Some(url) => {
+                        Document::extract_for_url(&html, &url, &self.extractors, self.mode)
+                    }
+                    None => Document::extract(&html, None, self.mode),
+                }
+                .map(Document::into_owned)
+            }
+        };
+
+        Ok(Some(Extraction {
+            document,
+            charset: Some((decoded.charset, decoded.had_errors)),
+        }))
     }
 
     pub async fn search(
@@ -0,0 +1,155 @@
+//! Importing captures from other archives with Memento TimeMaps.
+//!
+//! Imported entries are tagged with the archive that was queried, and are
+//! downloaded from that archive along with entries imported from CDX results.
+
+use aib_cdx::memento::MementoClient;
+use aib_core::surt::Surt;
+use sqlx::SqliteConnection;
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("SQL error")]
+    Sqlx(#[from] sqlx::Error),
+    #[error("SURT error")]
+    Surt(#[from] aib_core::surt::Error),
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Summary {
+    /// The number of new entries imported.
+    pub entries: usize,
+    /// The number of URLs whose TimeMaps could not be retrieved.
+    pub failed: usize,
+}
+
+/// Import the captures of the given URLs listed in the client's archive.
+///
+/// TimeMaps don't provide MIME types, so the entries are recorded with the
+/// given MIME type.
+pub async fn import(
+    connection: &mut SqliteConnection,
+    client: &MementoClient,
+    urls: &[String],
+    mime_type: &str,
+) -> Result<Summary, Error> {
+    let mut summary = Summary::default();
+
+    for url in urls {
+        let timemap = match client.timemap(url).await {
+            Ok(timemap) => timemap,
+            Err(error) => {
+                log::warn!(
+                    "Failed to retrieve TimeMap for {} from {}: {}",
+                    url,
+                    client.archive(),
+                    error
+                );
+                summary.failed += 1;
+                continue;
+            }
+        };
+
+        for memento in timemap.mementos {
+            let surt = Surt::from_url(&memento.url)?;
+
+            let id = crate::db::entry::insert_memento(
+                &mut *connection,
+                &memento,
+                &surt.to_string(),
+                mime_type,
+            )
+            .await?;
+
+            if id.is_some() {
+                summary.entries += 1;
+            }
+        }
+    }
+
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aib_core::archive::Archive;
+    use aib_downloader::{ArchiveDownloader, Downloader};
+    use aib_mock_wayback::MockWayback;
+    use sqlx::SqlitePool;
+    use std::time::Duration;
+
+    #[sqlx::test]
+    async fn test_import(pool: SqlitePool) -> Result<(), Box<dyn std::error::Error>> {
+        let mut connection = pool.acquire().await?;
+        let server = MockWayback::start_bundled().await?;
+        let client = MementoClient::new(Archive::ArquivoPt)?
+            .with_timemap_base(&server.timemap_base())
+            .with_timegate_base(&server.timegate_base());
+
+        let urls = vec![
+            "https://example.com/".to_string(),
+            "https://example.com/missing".to_string(),
+        ];
+
+        let summary = import(&mut connection, &client, &urls, "text/html").await?;
+
+        assert_eq!(summary.entries, 2);
+        assert_eq!(summary.failed, 0);
+
+        let pending = crate::db::entry::pending_entries(
+            &mut *connection,
+            "text/html",
+            &Default::default(),
+            chrono::Utc::now(),
+            None,
+        )
+        .await?;
+
+        assert_eq!(
+            pending
+                .iter()
+                .map(|entry| (
+                    entry.archive,
+                    entry.entry.timestamp.to_string(),
+                    entry.memento_url.is_some()
+                ))
+                .collect::<Vec<_>>(),
+            vec![
+                (Archive::ArquivoPt, "20200101000000".to_string(), true),
+                (Archive::ArquivoPt, "20220101000000".to_string(), true)
+            ]
+        );
+
+        let dir = tempdir::TempDir::new("memento")?;
+        let store = aib_store::items::ItemStore::new(dir.path(), None);
+        let downloader = ArchiveDownloader::new(Downloader::default()).with_archive(
+            Archive::ArquivoPt,
+            Downloader::new(Duration::from_secs(1))?.with_base_url(&server.base_url())?,
+        );
+
+        let summary = crate::download::run(
+            &mut connection,
+            &downloader,
+            &store,
+            "text/html",
+            &Default::default(),
+        )
+        .await?;
+
+        assert_eq!(summary.downloaded, 2);
+        assert_eq!(summary.invalid_digest, 0);
+        assert_eq!(summary.failed, 0);
+
+        // Captures without CDX digests are never reported as invalid.
+        assert!(crate::db::entry::invalid_digests(&mut *connection)
+            .await?
+            .is_empty());
+
+        let summary = import(&mut connection, &client, &urls, "text/html").await?;
+
+        assert_eq!(summary.entries, 0);
+
+        Ok(())
+    }
+}
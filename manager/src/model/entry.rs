@@ -1,5 +1,6 @@
 use aib_cdx::entry::Entry as CdxEntry;
 use aib_core::{
+    archive::Archive,
     digest::{Digest, Sha1Digest},
     timestamp::Timestamp,
 };
@@ -9,6 +10,9 @@ use sqlx::{ColumnIndex, Decode, FromRow, Row, Type};
 pub struct Entry {
     pub id: u64,
     pub surt_id: u64,
+    pub archive: Archive,
+    /// The URI-M for entries imported from Memento TimeMaps.
+    pub memento_url: Option<String>,
     pub entry: CdxEntry,
 }
 
@@ -32,10 +36,16 @@ where
         let status_code = row.try_get::<Option<i32>, _>("status_code")?;
         let digest = row.try_get::<&str, _>("digest")?;
         let length = row.try_get::<i64, _>("length")?;
+        let archive = row.try_get::<&str, _>("archive")?;
+        let memento_url = row.try_get::<Option<&str>, _>("memento_url")?;
 
         Ok(Self {
             id: super::try_cast(entry_id)?,
             surt_id: super::try_cast(surt_id)?,
+            archive: archive
+                .parse()
+                .map_err(|error| sqlx::Error::Decode(Box::new(error)))?,
+            memento_url: memento_url.map(str::to_string),
             entry: CdxEntry {
                 key: surt_str
                     .parse()
@@ -0,0 +1,156 @@
+use serde::{Deserialize, Serialize};
+use sqlx::{ColumnIndex, Decode, FromRow, Row, Type};
+use std::fmt::Display;
+use std::str::FromStr;
+
+/// The kind of failure recorded for a download attempt.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FailureClass {
+    /// The Wayback Machine does not have the capture (404 or 410).
+    NotFound,
+    /// The capture is excluded or otherwise unavailable (403 or 451).
+    Blocked,
+    /// The response was a redirect that could not be followed.
+    InvalidRedirect,
+    /// Any other 4xx response.
+    ClientError,
+    /// The response body was larger than the configured limit.
+    TooLarge,
+    /// The response body was shorter than its `Content-Length`.
+    Truncated,
+    RateLimited,
+    ServerError,
+    Timeout,
+    Network,
+    Other,
+}
+
+impl FailureClass {
+    pub const ALL: [Self; 11] = [
+        Self::NotFound,
+        Self::Blocked,
+        Self::InvalidRedirect,
+        Self::ClientError,
+        Self::TooLarge,
+        Self::Truncated,
+        Self::RateLimited,
+        Self::ServerError,
+        Self::Timeout,
+        Self::Network,
+        Self::Other,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::NotFound => "not_found",
+            Self::Blocked => "blocked",
+            Self::InvalidRedirect => "invalid_redirect",
+            Self::ClientError => "client_error",
+            Self::TooLarge => "too_large",
+            Self::Truncated => "truncated",
+            Self::RateLimited => "rate_limited",
+            Self::ServerError => "server_error",
+            Self::Timeout => "timeout",
+            Self::Network => "network",
+            Self::Other => "other",
+        }
+    }
+
+    /// Indicates whether retrying the download could not succeed.
+    pub fn is_permanent(&self) -> bool {
+        matches!(
+            self,
+            Self::NotFound
+                | Self::Blocked
+                | Self::InvalidRedirect
+                | Self::ClientError
+                | Self::TooLarge
+        )
+    }
+
+    pub fn from_status_code(status_code: u16) -> Self {
+        match status_code {
+            404 | 410 => Self::NotFound,
+            403 | 451 => Self::Blocked,
+            429 => Self::RateLimited,
+            400..=499 => Self::ClientError,
+            500..=599 => Self::ServerError,
+            _ => Self::Other,
+        }
+    }
+}
+
+impl Display for FailureClass {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for FailureClass {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|class| class.as_str() == s)
+            .ok_or_else(|| s.to_string())
+    }
+}
+
+/// The number of failed entries and attempts for a pattern and failure class.
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
+pub struct FailureCount {
+    pub pattern: String,
+    pub error_class: FailureClass,
+    pub permanent: bool,
+    pub entries: u64,
+    pub attempts: u64,
+}
+
+impl<'r, R: Row> FromRow<'r, R> for FailureCount
+where
+    for<'a> &'a str: ColumnIndex<R>,
+    i64: Decode<'r, R::Database>,
+    i64: Type<R::Database>,
+    &'r str: Decode<'r, R::Database>,
+    &'r str: Type<R::Database>,
+{
+    fn from_row(row: &'r R) -> Result<Self, sqlx::Error> {
+        let pattern = row.try_get::<&str, _>("pattern")?;
+        let error_class = row
+            .try_get::<&str, _>("error_class")?
+            .parse::<FailureClass>()
+            .map_err(|value| sqlx::Error::Decode(format!("Invalid error class: {value}").into()))?;
+        let entries = row.try_get::<i64, _>("entries")?;
+        let attempts = row.try_get::<i64, _>("attempts")?;
+
+        Ok(Self {
+            pattern: pattern.to_string(),
+            error_class,
+            permanent: error_class.is_permanent(),
+            entries: super::try_cast(entries)?,
+            attempts: super::try_cast(attempts)?,
+        })
+    }
+}
+
+/// Determines when failed downloads are attempted again.
+///
+/// Entries with a permanent failure are never retried. Others are retried
+/// until they reach the maximum number of attempts, with the delay after the
+/// last attempt doubling each time.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    pub base_delay: chrono::Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            base_delay: chrono::Duration::hours(1),
+        }
+    }
+}
@@ -0,0 +1,48 @@
+use aib_core::{surt::Surt, timestamp::Timestamp};
+use serde::{Deserialize, Serialize};
+
+/// A link from a snapshot.
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
+pub struct OutboundLink {
+    pub url: String,
+    pub surt: String,
+    pub text: String,
+    /// Whether the link points to the page's own host.
+    pub internal: bool,
+}
+
+/// A link to a target URL from a snapshot (identified by its earliest capture).
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
+pub struct InboundLink {
+    pub snapshot_id: i64,
+    pub page_url: String,
+    pub page_timestamp: Timestamp,
+    pub url: String,
+    pub surt: String,
+    pub text: String,
+}
+
+/// The number of external links to a domain from a pattern's snapshots.
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
+pub struct DomainCount {
+    /// The host name (e.g. `www.example.com`).
+    pub domain: String,
+    pub links: u64,
+    pub snapshots: u64,
+}
+
+/// The target of inbound links.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum LinkTarget {
+    /// Links with exactly this SURT.
+    Surt(String),
+    /// Links whose SURTs start with this prefix (e.g. `com,example)/blog/`).
+    SurtPrefix(String),
+}
+
+impl LinkTarget {
+    /// Links with the same SURT as a URL.
+    pub fn url(url: &str) -> Result<Self, aib_core::surt::Error> {
+        Ok(Self::Surt(Surt::from_url(url)?.to_string()))
+    }
+}
@@ -1,10 +1,16 @@
 pub mod entry;
+pub mod failure;
+pub mod link;
 pub mod pattern;
+pub mod resource;
 
 pub use entry::Entry;
+pub use failure::{FailureClass, FailureCount, RetryPolicy};
+pub use link::{DomainCount, InboundLink, LinkTarget, OutboundLink};
 pub use pattern::Pattern;
+pub use resource::Resource;
 
-fn try_cast<S, T>(value: S) -> Result<T, sqlx::Error>
+pub(crate) fn try_cast<S, T>(value: S) -> Result<T, sqlx::Error>
 where
     S: TryInto<T>,
     <S as TryInto<T>>::Error: std::error::Error + Send + Sync + 'static,
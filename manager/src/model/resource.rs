@@ -0,0 +1,61 @@
+use super::FailureClass;
+use aib_core::entry::UrlParts;
+use aib_extractor::requisites::RequisiteKind;
+use sqlx::{ColumnIndex, Decode, FromRow, Row, Type};
+
+/// An embedded resource of a page, and the capture (if any) downloaded for it.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Resource {
+    pub url: String,
+    pub kind: RequisiteKind,
+    /// The capture that was served, which usually has a different timestamp from the page.
+    pub capture: Option<UrlParts>,
+    pub digest: Option<String>,
+    pub error_class: Option<FailureClass>,
+}
+
+impl<'r, R: Row> FromRow<'r, R> for Resource
+where
+    for<'a> &'a str: ColumnIndex<R>,
+    Option<i64>: Decode<'r, R::Database>,
+    Option<i64>: Type<R::Database>,
+    &'r str: Decode<'r, R::Database>,
+    &'r str: Type<R::Database>,
+    Option<&'r str>: Decode<'r, R::Database>,
+    Option<&'r str>: Type<R::Database>,
+{
+    fn from_row(row: &'r R) -> Result<Self, sqlx::Error> {
+        let url = row.try_get::<&str, _>("url")?;
+        let kind = row.try_get::<&str, _>("kind")?;
+        let resource_url = row.try_get::<Option<&str>, _>("resource_url")?;
+        let resource_ts = row.try_get::<Option<i64>, _>("resource_ts")?;
+        let digest = row.try_get::<Option<&str>, _>("digest")?;
+        let error_class = row.try_get::<Option<&str>, _>("error_class")?;
+
+        let capture = match (resource_url, resource_ts) {
+            (Some(resource_url), Some(resource_ts)) => Some(UrlParts::new(
+                resource_url.to_string(),
+                resource_ts
+                    .try_into()
+                    .map_err(|error| sqlx::Error::Decode(Box::new(error)))?,
+            )),
+            _ => None,
+        };
+
+        Ok(Self {
+            url: url.to_string(),
+            kind: kind
+                .parse()
+                .map_err(|error| sqlx::Error::Decode(Box::new(error)))?,
+            capture,
+            digest: digest.map(str::to_string),
+            error_class: error_class
+                .map(|value| {
+                    value.parse::<FailureClass>().map_err(|value| {
+                        sqlx::Error::Decode(format!("Invalid error class: {value}").into())
+                    })
+                })
+                .transpose()?,
+        })
+    }
+}
@@ -0,0 +1,354 @@
+//! Downloading the embedded resources of pages ("page requisites").
+//!
+//! Images, stylesheets and scripts referenced by downloaded pages are
+//! requested with the corresponding replay modifier at the page's timestamp,
+//! and the Wayback Machine redirects each request to the nearest capture of
+//! the resource. Resource contents are saved in the item store, and the
+//! relationship between each page and its resources is recorded in the
+//! database. Pages are marked once their resources have been attempted, so
+//! interrupted runs can be restarted.
+
+use crate::download::classify;
+use crate::model::FailureClass;
+use aib_core::entry::UrlParts;
+use aib_downloader::{Downloader, Modifier};
+use aib_extractor::requisites::{Requisite, RequisiteKind};
+use aib_store::items::ItemStore;
+use chrono::Utc;
+use futures::StreamExt;
+use sqlx::SqliteConnection;
+
+const DEFAULT_PARALLELISM: usize = 4;
+const DEFAULT_BATCH_SIZE: usize = 100;
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("SQL error")]
+    Sqlx(#[from] sqlx::Error),
+    #[error("Item store error")]
+    Store(#[from] aib_store::items::Error),
+    #[error("Task error")]
+    Task(#[from] tokio::task::JoinError),
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Config {
+    /// The number of resource downloads in progress at any time.
+    pub parallelism: usize,
+    /// The number of pages read from the database at a time.
+    pub batch_size: usize,
+    /// The maximum number of pages to process in this run.
+    pub limit: Option<usize>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            parallelism: DEFAULT_PARALLELISM,
+            batch_size: DEFAULT_BATCH_SIZE,
+            limit: None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Summary {
+    pub pages: usize,
+    pub downloaded: usize,
+    pub failed: usize,
+}
+
+/// The replay modifier used to request a kind of resource.
+pub fn modifier(kind: RequisiteKind) -> Modifier {
+    match kind {
+        RequisiteKind::Image => Modifier::Image,
+        RequisiteKind::Stylesheet => Modifier::Css,
+        RequisiteKind::Script => Modifier::JavaScript,
+    }
+}
+
+/// Download the resources of pending pages with the given MIME type until none are left.
+pub async fn run(
+    connection: &mut SqliteConnection,
+    downloader: &Downloader,
+    store: &ItemStore,
+    mime_type: &str,
+    config: &Config,
+) -> Result<Summary, Error> {
+    let mut summary = Summary::default();
+
+    loop {
+        let batch_size = config
+            .limit
+            .map(|limit| limit.saturating_sub(summary.pages).min(config.batch_size))
+            .unwrap_or(config.batch_size);
+
+        if batch_size == 0 {
+            break;
+        }
+
+        let pages =
+            crate::db::resource::pending_pages(&mut *connection, mime_type, Some(batch_size))
+                .await?;
+
+        if pages.is_empty() {
+            break;
+        }
+
+        for (snapshot_id, digest, page) in pages {
+            let page = &page;
+            let headers = crate::db::snapshot::get_headers(&mut *connection, snapshot_id).await?;
+            let content_type = headers.and_then(|headers| headers.content_type);
+            let requisites = read_requisites(store, &digest, content_type, page).await?;
+
+            let mut results = futures::stream::iter(requisites)
+                .map(|requisite| async move {
+                    let outcome = download_requisite(downloader, store, &requisite, page).await;
+
+                    (requisite, outcome)
+                })
+                .buffer_unordered(config.parallelism);
+
+            while let Some((requisite, outcome)) = results.next().await {
+                let outcome = outcome?;
+
+                match &outcome {
+                    Ok(_) => summary.downloaded += 1,
+                    Err(error_class) => {
+                        log::warn!(
+                            "Failed to download {} for {} ({}): {}",
+                            requisite.url,
+                            page.url,
+                            page.timestamp,
+                            error_class
+                        );
+                        summary.failed += 1;
+                    }
+                }
+
+                crate::db::resource::insert(
+                    &mut *connection,
+                    snapshot_id,
+                    &requisite.url,
+                    requisite.kind,
+                    outcome
+                        .as_ref()
+                        .map(|(capture, digest)| (capture, digest.as_str()))
+                        .map_err(|error_class| *error_class),
+                    Utc::now(),
+                )
+                .await?;
+            }
+
+            crate::db::resource::mark_scanned(&mut *connection, snapshot_id, Utc::now()).await?;
+            summary.pages += 1;
+        }
+    }
+
+    Ok(summary)
+}
+
+/// Read the resources of a stored page, decoding it according to its original `Content-Type`
+/// header (if known).
+async fn read_requisites(
+    store: &ItemStore,
+    digest: &str,
+    content_type: Option<String>,
+    page: &UrlParts,
+) -> Result<Vec<Requisite>, Error> {
+    let store = store.clone();
+    let digest = digest.to_string();
+    let page_url = page.url.clone();
+
+    tokio::task::spawn_blocking(move || {
+        Ok(match store.read(&digest)? {
+            Some(bytes) => {
+                let decoded = aib_extractor::charset::decode(&bytes, content_type.as_deref());
+                let html =
+                    scraper::Html::parse_document(&aib_extractor::wayback::clean(&decoded.text));
+
+                aib_extractor::requisites::requisites(&html, &page_url)
+            }
+            None => {
+                log::warn!("Missing item for page: {}", digest);
+
+                vec![]
+            }
+        })
+    })
+    .await?
+}
+
+/// Download a resource, returning the capture that was served and the digest
+/// of its contents, or the class of the failure.
+async fn download_requisite(
+    downloader: &Downloader,
+    store: &ItemStore,
+    requisite: &Requisite,
+    page: &UrlParts,
+) -> Result<Result<(UrlParts, String), FailureClass>, Error> {
+    match downloader
+        .download_to(
+            &requisite.url,
+            page.timestamp,
+            modifier(requisite.kind),
+            || store.writer(),
+        )
+        .await
+    {
+        Ok(Some(download)) => {
+            let capture = download
+                .redirects
+                .last()
+                .cloned()
+                .unwrap_or_else(|| UrlParts::new(requisite.url.clone(), page.timestamp));
+            let digest = download.digest.to_string();
+            let store = store.clone();
+            let writer = download.writer;
+
+            tokio::task::spawn_blocking({
+                let digest = digest.clone();
+
+                move || store.commit(&digest, writer)
+            })
+            .await??;
+
+            Ok(Ok((capture, digest)))
+        }
+        Ok(None) => Ok(Err(FailureClass::NotFound)),
+        Err(error) => Ok(Err(classify(&error).1)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aib_cdx::entry::Entry as CdxEntry;
+    use aib_core::surt::Surt;
+    use aib_mock_wayback::MockWayback;
+    use sqlx::SqlitePool;
+    use std::time::Duration;
+
+    #[sqlx::test]
+    async fn test_run(pool: SqlitePool) -> Result<(), Box<dyn std::error::Error>> {
+        let mut connection = pool.acquire().await?;
+        let dir = tempdir::TempDir::new("requisites")?;
+        let store = ItemStore::new(dir.path(), None);
+        let server = MockWayback::start_bundled().await?;
+        let downloader =
+            Downloader::new(Duration::from_secs(1))?.with_base_url(&server.base_url())?;
+
+        let url = "https://example.com/page";
+        let content = include_bytes!("../../mock-wayback/fixtures/captures/page.html");
+        let digest = aib_core::digest::compute_digest(&mut content.as_slice())?;
+        store.save(&digest.to_string(), &mut content.as_slice())?;
+
+        let entry_id = crate::db::entry::insert(
+            &mut connection,
+            &CdxEntry {
+                key: Surt::from_url(url)?,
+                timestamp: "20200101000000".parse()?,
+                original: url.to_string(),
+                mime_type: "text/html".parse()?,
+                status_code: Some(200),
+                digest: aib_core::digest::Digest::Valid(digest),
+                length: content.len() as u64,
+                extra_info: None,
+            },
+        )
+        .await?;
+        crate::db::entry::insert_entry_success(
+            &mut connection,
+            entry_id,
+            &digest.to_string(),
+            Some(true),
+            Utc::now(),
+        )
+        .await?;
+
+        let pending =
+            crate::db::resource::pending_pages(&mut *connection, "text/html", None).await?;
+        assert_eq!(pending.len(), 1);
+        let snapshot_id = pending[0].0;
+
+        let summary = run(
+            &mut connection,
+            &downloader,
+            &store,
+            "text/html",
+            &Config::default(),
+        )
+        .await?;
+
+        assert_eq!(
+            summary,
+            Summary {
+                pages: 1,
+                downloaded: 3,
+                failed: 1
+            }
+        );
+        assert!(
+            crate::db::resource::pending_pages(&mut *connection, "text/html", None)
+                .await?
+                .is_empty()
+        );
+
+        let resources = crate::db::resource::get_resources(&mut *connection, snapshot_id).await?;
+        let captures = resources
+            .iter()
+            .map(|resource| {
+                (
+                    resource.url.as_str(),
+                    resource.kind,
+                    resource
+                        .capture
+                        .as_ref()
+                        .map(|capture| capture.timestamp.to_string()),
+                    resource.error_class,
+                )
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            captures,
+            vec![
+                (
+                    "https://cdn.example.com/app.js",
+                    RequisiteKind::Script,
+                    Some("20200101000000".to_string()),
+                    None
+                ),
+                (
+                    "https://example.com/images/logo.png",
+                    RequisiteKind::Image,
+                    Some("20200102000000".to_string()),
+                    None
+                ),
+                (
+                    "https://example.com/missing.png",
+                    RequisiteKind::Image,
+                    None,
+                    Some(FailureClass::NotFound)
+                ),
+                (
+                    "https://example.com/style.css",
+                    RequisiteKind::Stylesheet,
+                    Some("20191231000000".to_string()),
+                    None
+                ),
+            ]
+        );
+
+        let style_digest = resources[3].digest.as_deref().unwrap();
+        assert!(store.contains(style_digest));
+        assert_eq!(
+            crate::db::resource::get_pages(&mut *connection, style_digest)
+                .await?
+                .len(),
+            1
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,204 @@
+//! Requesting new captures with Save Page Now.
+//!
+//! Each request is recorded in the database with the timestamp of the
+//! resulting capture (or the archive's error). Each new capture is also added
+//! as an entry (and to the pattern, if any) so that it can be downloaded in the
+//! usual way. Its digest is unknown until a later CDX import fills it in.
+
+use aib_core::{entry::UrlParts, surt::Surt};
+use aib_downloader::save::SaveClient;
+use chrono::Utc;
+use futures::StreamExt;
+use sqlx::SqliteConnection;
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("SQL error")]
+    Sqlx(#[from] sqlx::Error),
+    #[error("SURT error")]
+    Surt(#[from] aib_core::surt::Error),
+    #[error("Unknown pattern: {0}")]
+    UnknownPattern(String),
+}
+
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Summary {
+    pub captures: Vec<UrlParts>,
+    pub failed: usize,
+}
+
+/// The URLs of the entries imported for the pattern with the given slug, and its ID.
+pub async fn pattern_urls(
+    connection: &mut SqliteConnection,
+    slug: &str,
+) -> Result<(u64, Vec<String>), Error> {
+    let pattern_id = crate::db::pattern::get_all(&mut *connection)
+        .await?
+        .into_iter()
+        .find(|pattern| pattern.slug == slug)
+        .and_then(|pattern| pattern.id)
+        .ok_or_else(|| Error::UnknownPattern(slug.to_string()))?;
+
+    let urls = crate::db::save::pattern_urls(&mut *connection, pattern_id).await?;
+
+    Ok((pattern_id, urls))
+}
+
+/// Request captures of the given URLs, recording the outcome of each request.
+///
+/// The MIME type is used for the entries added for new captures.
+pub async fn run(
+    connection: &mut SqliteConnection,
+    client: &SaveClient,
+    urls: &[String],
+    pattern_id: Option<u64>,
+    mime_type: &str,
+    parallelism: usize,
+) -> Result<Summary, Error> {
+    let mut summary = Summary::default();
+
+    let mut results = futures::stream::iter(urls)
+        .map(|url| async move { (url, client.save(url).await) })
+        .buffer_unordered(parallelism);
+
+    while let Some((url, result)) = results.next().await {
+        match result {
+            Ok(capture) => {
+                let capture_parts = UrlParts::new(capture.url, capture.timestamp);
+
+                crate::db::save::insert_success(
+                    &mut *connection,
+                    pattern_id,
+                    &capture.job_id,
+                    &capture_parts,
+                    Utc::now(),
+                )
+                .await?;
+
+                let surt = Surt::from_url(&capture_parts.url)?;
+                let entry_id = crate::db::entry::insert_saved(
+                    &mut *connection,
+                    &capture_parts,
+                    &surt.to_string(),
+                    mime_type,
+                )
+                .await?;
+
+                if let Some(pattern_id) = pattern_id {
+                    crate::db::pattern::insert_pattern_entry(
+                        &mut *connection,
+                        pattern_id,
+                        entry_id,
+                    )
+                    .await?;
+                }
+
+                summary.captures.push(capture_parts);
+            }
+            Err(error) => {
+                log::warn!("Failed to save {}: {}", url, error);
+
+                crate::db::save::insert_failure(
+                    &mut *connection,
+                    url,
+                    pattern_id,
+                    error.job_id(),
+                    error.status_ext(),
+                    &error.to_string(),
+                    Utc::now(),
+                )
+                .await?;
+
+                summary.failed += 1;
+            }
+        }
+    }
+
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aib_mock_wayback::MockWayback;
+    use sqlx::SqlitePool;
+    use std::time::Duration;
+
+    #[sqlx::test]
+    async fn test_run(pool: SqlitePool) -> Result<(), Box<dyn std::error::Error>> {
+        let mut connection = pool.acquire().await?;
+        let server = MockWayback::start_bundled().await?;
+        let client = SaveClient::new(Duration::from_secs(1))?
+            .with_base_url(&server.save_base())?
+            .with_polling(Duration::from_millis(10), 5);
+
+        let urls = vec![
+            "https://example.com/new".to_string(),
+            "https://example.com/blocked".to_string(),
+        ];
+
+        let summary = run(&mut connection, &client, &urls, None, "text/html", 2).await?;
+
+        let expected = vec![UrlParts::new(
+            "https://example.com/new".to_string(),
+            "20240601120000".parse()?,
+        )];
+
+        assert_eq!(summary.captures, expected);
+        assert_eq!(summary.failed, 1);
+
+        let entry: (String, i64, String, String) =
+            sqlx::query_as("SELECT url, ts, digest, mime_type FROM entry")
+                .fetch_one(&mut *connection)
+                .await?;
+
+        assert_eq!(
+            entry,
+            (
+                "https://example.com/new".to_string(),
+                expected[0].timestamp.0.timestamp(),
+                "-".to_string(),
+                "text/html".to_string()
+            )
+        );
+
+        // A later CDX import fills in the digest and other details.
+        let cdx_entry = aib_cdx::entry::Entry {
+            key: Surt::from_url("https://example.com/new")?,
+            timestamp: expected[0].timestamp,
+            original: "https://example.com/new".to_string(),
+            mime_type: "text/html".parse()?,
+            status_code: Some(200),
+            digest: "XXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXX".parse()?,
+            length: 1024,
+            extra_info: None,
+        };
+
+        crate::db::entry::insert(&mut connection, &cdx_entry).await?;
+
+        let entry: (i64, String, Option<i64>, i64) =
+            sqlx::query_as("SELECT COUNT(*), digest, status_code, length FROM entry")
+                .fetch_one(&mut *connection)
+                .await?;
+
+        assert_eq!(
+            entry,
+            (
+                1,
+                "XXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXX".to_string(),
+                Some(200),
+                1024
+            )
+        );
+
+        let status_ext: Option<String> =
+            sqlx::query_scalar("SELECT status_ext FROM save_request WHERE url = ?")
+                .bind("https://example.com/blocked")
+                .fetch_one(&mut *connection)
+                .await?;
+
+        assert_eq!(status_ext.as_deref(), Some("error:blocked-url"));
+
+        Ok(())
+    }
+}
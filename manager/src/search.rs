@@ -1,5 +1,6 @@
 use aib_core::{entry::UrlParts, surt::Surt, timestamp::Timestamp};
 use aib_indexer::{Index, Query, Snippet};
+use chrono::{DateTime, Utc};
 use indexmap::IndexMap;
 use itertools::Itertools;
 use serde::ser::{Serialize, SerializeStruct, Serializer};
@@ -35,6 +36,32 @@ pub struct Hit {
     pub url: UrlParts,
     pub title: String,
     pub snippet: Snippet,
+    pub metadata: HitMetadata,
+}
+
+/// Document metadata stored in the index for a hit.
+#[derive(Clone, Debug, Default, PartialEq, serde::Serialize)]
+pub struct HitMetadata {
+    pub description: Option<String>,
+    #[serde(rename = "canonicalUrl")]
+    pub canonical_url: Option<String>,
+    pub lang: Option<String>,
+    pub author: Option<String>,
+    pub published: Option<DateTime<Utc>>,
+    pub modified: Option<DateTime<Utc>>,
+    #[serde(rename = "statusId")]
+    pub status_id: Option<u64>,
+    #[serde(rename = "statusAuthor")]
+    pub status_author: Option<String>,
+    #[serde(rename = "postedAt")]
+    pub posted_at: Option<DateTime<Utc>>,
+    #[serde(rename = "inReplyTo")]
+    pub in_reply_to: Option<String>,
+    #[serde(rename = "retweetedBy")]
+    pub retweeted_by: Option<String>,
+    pub identifiers: Vec<String>,
+    pub types: Vec<String>,
+    pub image: Option<String>,
 }
 
 impl Serialize for Hit {
@@ -42,12 +69,13 @@ impl Serialize for Hit {
     where
         S: Serializer,
     {
-        let mut result = serializer.serialize_struct("Hit", 5)?;
+        let mut result = serializer.serialize_struct("Hit", 6)?;
         result.serialize_field("url", &self.url.to_wb_url(true, false))?;
         result.serialize_field("score", &self.score)?;
         result.serialize_field("pattern", &self.pattern_slug)?;
         result.serialize_field("title", &self.title)?;
         result.serialize_field("snippet", &self.snippet)?;
+        result.serialize_field("metadata", &self.metadata)?;
         result.end()
     }
 }
@@ -68,9 +96,32 @@ pub async fn search<'a>(
     for (_surt_id, hits) in results.hits {
         for hit in hits {
             snapshot_ids.push(hit.snapshot_id);
+            let metadata = HitMetadata {
+                description: hit.description,
+                canonical_url: hit.canonical_url,
+                lang: hit.lang,
+                author: hit.author,
+                published: hit.published,
+                modified: hit.modified,
+                status_id: hit.status_id,
+                status_author: hit.status_author,
+                posted_at: hit.posted_at,
+                in_reply_to: hit.in_reply_to,
+                retweeted_by: hit.retweeted_by,
+                identifiers: hit.identifiers,
+                types: hit.types,
+                image: hit.image,
+            };
+
             snapshot_map.insert(
                 hit.snapshot_id,
-                (hit.pattern_slug, hit.score, hit.title, hit.snippet),
+                (
+                    hit.pattern_slug,
+                    hit.score,
+                    hit.title,
+                    hit.snippet,
+                    metadata,
+                ),
             );
         }
     }
@@ -94,7 +145,7 @@ pub async fn search<'a>(
             .collect::<IndexMap<_, _>>();
 
         for (snapshot_id, url, _surt) in group {
-            let (pattern_slug, score, title, snippet) = snapshot_map
+            let (pattern_slug, score, title, snippet, metadata) = snapshot_map
                 .get(&snapshot_id)
                 .cloned()
                 .ok_or_else(|| Error::MissingSnapshot(snapshot_id))?;
@@ -108,6 +159,7 @@ pub async fn search<'a>(
                     url,
                     title,
                     snippet,
+                    metadata,
                 }),
             );
         }
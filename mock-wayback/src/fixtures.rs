@@ -0,0 +1,166 @@
+//! Fixture definitions for the mock server.
+//!
+//! A fixture directory contains a `wayback.json` manifest listing captures and
+//! CDX queries, with content files referenced by paths relative to the
+//! directory.
+
+use aib_core::{entry::UrlParts, timestamp::Timestamp};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+const MANIFEST_FILE_NAME: &str = "wayback.json";
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("I/O error")]
+    Io(#[from] std::io::Error),
+    #[error("JSON error")]
+    Json(#[from] serde_json::Error),
+    #[error("Missing fixture file")]
+    MissingFile(PathBuf),
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct Fixtures {
+    #[serde(default)]
+    pub captures: Vec<Capture>,
+    #[serde(default)]
+    pub cdx: Vec<CdxQuery>,
+    #[serde(default)]
+    pub saves: Vec<Save>,
+    #[serde(skip)]
+    base: PathBuf,
+}
+
+impl Fixtures {
+    pub fn load<P: AsRef<Path>>(base: P) -> Result<Self, Error> {
+        let base = base.as_ref();
+        let manifest = std::fs::read_to_string(base.join(MANIFEST_FILE_NAME))?;
+        let mut fixtures: Self = serde_json::from_str(&manifest)?;
+        fixtures.base = base.to_path_buf();
+
+        for path in fixtures.paths() {
+            if !fixtures.base.join(path).is_file() {
+                return Err(Error::MissingFile(path.to_path_buf()));
+            }
+        }
+
+        Ok(fixtures)
+    }
+
+    /// The fixtures included with this crate.
+    pub fn bundled() -> Result<Self, Error> {
+        Self::load(Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures"))
+    }
+
+    pub fn capture(&self, url: &str, timestamp: Timestamp) -> Option<&Capture> {
+        self.captures
+            .iter()
+            .find(|capture| capture.url == url && capture.timestamp == timestamp)
+    }
+
+    /// The capture of the URL closest in time to the given timestamp.
+    pub fn nearest_capture(&self, url: &str, timestamp: Timestamp) -> Option<&Capture> {
+        self.captures
+            .iter()
+            .filter(|capture| capture.url == url)
+            .min_by_key(|capture| (capture.timestamp.0 - timestamp.0).abs())
+    }
+
+    pub fn cdx_query(&self, url: &str, match_type: &str) -> Option<&CdxQuery> {
+        self.cdx
+            .iter()
+            .find(|query| query.url == url && query.match_type == match_type)
+    }
+
+    pub fn save(&self, url: &str) -> Option<&Save> {
+        self.saves.iter().find(|save| save.url == url)
+    }
+
+    /// Read a content file referenced by the manifest.
+    pub fn read(&self, path: &Path) -> std::io::Result<Vec<u8>> {
+        std::fs::read(self.base.join(path))
+    }
+
+    fn paths(&self) -> impl Iterator<Item = &Path> {
+        self.captures
+            .iter()
+            .flat_map(|capture| capture.file.iter().chain(capture.iframe_file.iter()))
+            .chain(self.cdx.iter().flat_map(|query| query.pages.iter()))
+            .map(PathBuf::as_path)
+    }
+}
+
+/// A capture and the way the server should respond to requests for it.
+#[derive(Clone, Debug, Deserialize)]
+pub struct Capture {
+    pub url: String,
+    pub timestamp: Timestamp,
+    /// The content served for `id_` requests (and other modifiers, unless
+    /// more specific content is provided).
+    pub file: Option<PathBuf>,
+    /// The content served for `if_` requests.
+    pub iframe_file: Option<PathBuf>,
+    /// The status code for successful requests.
+    #[serde(default = "default_status")]
+    pub status: u16,
+    /// The capture that this capture redirects to, if it is a redirect.
+    pub redirect: Option<UrlParts>,
+    /// Original response headers, served with the `x-archive-orig-` prefix.
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    /// Responses returned (in order) before the capture is served.
+    #[serde(default)]
+    pub failures: Vec<Failure>,
+    /// A delay in milliseconds before every response.
+    #[serde(default)]
+    pub delay_ms: u64,
+    /// Close the connection after sending this many bytes of the body.
+    pub truncate: Option<usize>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct Failure {
+    pub status: u16,
+    /// The value of the `Retry-After` header, in seconds.
+    pub retry_after: Option<u64>,
+}
+
+/// The outcome of a Save Page Now request for a URL.
+#[derive(Clone, Debug, Deserialize)]
+pub struct Save {
+    pub url: String,
+    /// The timestamp of the new capture, if the job succeeds.
+    pub timestamp: Option<Timestamp>,
+    /// The number of status requests that fail with a server error, before any others.
+    #[serde(default)]
+    pub failed_polls: usize,
+    /// The number of status requests that report the job as pending.
+    #[serde(default)]
+    pub pending_polls: usize,
+    /// The error code reported for a failed job (or a rejected submission).
+    pub status_ext: Option<String>,
+    #[serde(default)]
+    pub message: String,
+    /// Reject the submission instead of creating a job.
+    #[serde(default)]
+    pub reject: bool,
+}
+
+/// The pages of results for a CDX query.
+#[derive(Clone, Debug, Deserialize)]
+pub struct CdxQuery {
+    pub url: String,
+    #[serde(default = "default_match_type")]
+    pub match_type: String,
+    pub pages: Vec<PathBuf>,
+}
+
+fn default_status() -> u16 {
+    200
+}
+
+fn default_match_type() -> String {
+    "exact".to_string()
+}
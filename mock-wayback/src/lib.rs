@@ -0,0 +1,554 @@
+//! An in-process HTTP server that emulates the Wayback Machine for tests.
+//!
+//! The server answers capture requests (`/web/<timestamp><modifier>/<url>`)
+//! and CDX queries (`/web/timemap/json`) from a set of [`Fixtures`], and
+//! can simulate redirects (including to the nearest capture when there is no
+//! exact match), missing captures, rate limiting, slow responses
+//! and truncated bodies. It also implements the Save Page Now job submission
+//! (`/save`) and status (`/save/status/<job_id>`) endpoints, and Memento
+//! link-format TimeMaps (`/web/timemap/link/<url>`) and TimeGates
+//! (`/web/<url>`) for the captures.
+
+use aib_core::timestamp::Timestamp;
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+
+pub mod fixtures;
+
+pub use fixtures::Fixtures;
+
+const CAPTURE_PATH_PATTERN: &str =
+    r"^/web/(?P<timestamp>\d{14})(?P<modifier>[a-z]{2}_)?/(?P<url>.+)$";
+const CDX_PATH: &str = "/web/timemap/json";
+const TIMEMAP_PATH_PREFIX: &str = "/web/timemap/link/";
+const TIMEGATE_PATH_PREFIX: &str = "/web/";
+const SAVE_PATH: &str = "/save";
+const SAVE_STATUS_PATH_PREFIX: &str = "/save/status/";
+const ORIGINAL_HEADER_PREFIX: &str = "x-archive-orig-";
+const HTTP_DATE_FMT: &str = "%a, %d %b %Y %H:%M:%S GMT";
+
+static CAPTURE_PATH_RE: Lazy<regex::Regex> =
+    Lazy::new(|| regex::Regex::new(CAPTURE_PATH_PATTERN).unwrap());
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("I/O error")]
+    Io(#[from] std::io::Error),
+    #[error("Fixture error")]
+    Fixtures(#[from] fixtures::Error),
+}
+
+/// A request received by the server.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Request {
+    pub method: String,
+    pub target: String,
+}
+
+#[derive(Debug)]
+struct State {
+    fixtures: Fixtures,
+    address: SocketAddr,
+    requests: Mutex<Vec<Request>>,
+    /// The number of failures already returned for each capture.
+    failures: Mutex<HashMap<(String, Timestamp), usize>>,
+    /// The URL and the number of status requests so far for each Save Page Now job.
+    jobs: Mutex<HashMap<String, (String, usize)>>,
+}
+
+/// A running mock server, which is shut down when dropped.
+#[derive(Debug)]
+pub struct MockWayback {
+    state: Arc<State>,
+    handle: tokio::task::JoinHandle<()>,
+}
+
+impl MockWayback {
+    pub async fn start(fixtures: Fixtures) -> Result<Self, Error> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let state = Arc::new(State {
+            fixtures,
+            address: listener.local_addr()?,
+            requests: Mutex::default(),
+            failures: Mutex::default(),
+            jobs: Mutex::default(),
+        });
+
+        let handle = tokio::spawn({
+            let state = state.clone();
+
+            async move {
+                while let Ok((stream, _)) = listener.accept().await {
+                    let state = state.clone();
+
+                    tokio::spawn(async move {
+                        if let Err(error) = handle_connection(&state, stream).await {
+                            log::warn!("Mock Wayback Machine connection error: {:?}", error);
+                        }
+                    });
+                }
+            }
+        });
+
+        Ok(Self { state, handle })
+    }
+
+    /// Start a server for the fixtures in the given directory.
+    pub async fn start_dir<P: AsRef<Path>>(base: P) -> Result<Self, Error> {
+        Self::start(Fixtures::load(base)?).await
+    }
+
+    /// Start a server for the fixtures included with this crate.
+    pub async fn start_bundled() -> Result<Self, Error> {
+        Self::start(Fixtures::bundled()?).await
+    }
+
+    pub fn address(&self) -> SocketAddr {
+        self.state.address
+    }
+
+    /// The base URL for captures, for use with a downloader.
+    pub fn base_url(&self) -> String {
+        format!("http://{}/web/", self.state.address)
+    }
+
+    /// The base URL for CDX queries, for use with an index client.
+    pub fn cdx_base(&self) -> String {
+        format!("http://{}/web/timemap", self.state.address)
+    }
+
+    /// The base URL for link-format TimeMaps, for use with a Memento client.
+    pub fn timemap_base(&self) -> String {
+        format!("http://{}{}", self.state.address, TIMEMAP_PATH_PREFIX)
+    }
+
+    /// The base URL for TimeGate requests, for use with a Memento client.
+    pub fn timegate_base(&self) -> String {
+        format!("http://{}{}", self.state.address, TIMEGATE_PATH_PREFIX)
+    }
+
+    /// The base URL for Save Page Now requests, for use with a save client.
+    pub fn save_base(&self) -> String {
+        format!("http://{}{}/", self.state.address, SAVE_PATH)
+    }
+
+    /// All requests received so far, in order.
+    pub fn requests(&self) -> Vec<Request> {
+        self.state.requests.lock().unwrap().clone()
+    }
+}
+
+impl Drop for MockWayback {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
+
+struct Response {
+    status: u16,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+    delay: Duration,
+    truncate: Option<usize>,
+}
+
+impl Response {
+    fn new(status: u16) -> Self {
+        Self {
+            status,
+            headers: vec![],
+            body: vec![],
+            delay: Duration::ZERO,
+            truncate: None,
+        }
+    }
+
+    fn with_header<V: ToString>(mut self, name: &str, value: V) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    fn with_body(mut self, body: Vec<u8>) -> Self {
+        self.body = body;
+        self
+    }
+}
+
+async fn handle_connection(state: &State, stream: TcpStream) -> std::io::Result<()> {
+    let mut reader = BufReader::new(stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line).await?;
+
+    // Only the body length and requested datetime are needed from the headers.
+    let mut content_length = 0;
+    let mut accept_datetime = None;
+
+    loop {
+        let mut line = String::new();
+
+        if reader.read_line(&mut line).await? == 0 || line.trim_end().is_empty() {
+            break;
+        }
+
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = value.trim().parse().unwrap_or_default();
+            } else if name.eq_ignore_ascii_case("accept-datetime") {
+                accept_datetime = Some(value.trim().to_string());
+            }
+        }
+    }
+
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body).await?;
+
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or_default().to_string();
+    let target = parts.next().unwrap_or_default().to_string();
+
+    state.requests.lock().unwrap().push(Request {
+        method: method.clone(),
+        target: target.clone(),
+    });
+
+    let response = if method == "POST" {
+        respond_submit(state, &target, &body)
+    } else {
+        respond(state, &target, accept_datetime.as_deref())?
+    };
+    let mut stream = reader.into_inner();
+
+    if !response.delay.is_zero() {
+        tokio::time::sleep(response.delay).await;
+    }
+
+    let mut head = format!(
+        "HTTP/1.1 {} {}\r\nContent-Length: {}\r\nConnection: close\r\n",
+        response.status,
+        reason(response.status),
+        response.body.len()
+    );
+
+    for (name, value) in &response.headers {
+        head.push_str(&format!("{}: {}\r\n", name, value));
+    }
+
+    head.push_str("\r\n");
+    stream.write_all(head.as_bytes()).await?;
+
+    if method != "HEAD" {
+        let end = response
+            .truncate
+            .unwrap_or(response.body.len())
+            .min(response.body.len());
+        stream.write_all(&response.body[..end]).await?;
+    }
+
+    stream.shutdown().await
+}
+
+fn respond(
+    state: &State,
+    target: &str,
+    accept_datetime: Option<&str>,
+) -> std::io::Result<Response> {
+    if target.starts_with(CDX_PATH) {
+        respond_cdx(state, target)
+    } else if let Some(url) = target.strip_prefix(TIMEMAP_PATH_PREFIX) {
+        Ok(respond_timemap(state, url))
+    } else if let Some(job_id) = target.strip_prefix(SAVE_STATUS_PATH_PREFIX) {
+        Ok(respond_status(state, job_id))
+    } else if let Some(captures) = CAPTURE_PATH_RE.captures(target) {
+        match captures["timestamp"].parse::<Timestamp>() {
+            Ok(timestamp) => respond_capture(
+                state,
+                &captures["url"],
+                timestamp,
+                captures.name("modifier").map_or("", |value| value.as_str()),
+            ),
+            Err(_) => Ok(Response::new(400)),
+        }
+    } else if let Some(url) = target.strip_prefix(TIMEGATE_PATH_PREFIX) {
+        Ok(respond_timegate(state, url, accept_datetime))
+    } else {
+        Ok(Response::new(404))
+    }
+}
+
+fn respond_capture(
+    state: &State,
+    url: &str,
+    timestamp: Timestamp,
+    modifier: &str,
+) -> std::io::Result<Response> {
+    let capture = match state.fixtures.capture(url, timestamp) {
+        Some(capture) => capture,
+        None => {
+            // Like the Wayback Machine, redirect to the nearest capture of the URL.
+            return Ok(match state.fixtures.nearest_capture(url, timestamp) {
+                Some(nearest) => Response::new(302).with_header(
+                    "Location",
+                    format!(
+                        "http://{}/web/{}{}/{}",
+                        state.address, nearest.timestamp, modifier, nearest.url
+                    ),
+                ),
+                None => Response::new(404),
+            });
+        }
+    };
+
+    let failure = {
+        let mut failures = state.failures.lock().unwrap();
+        let count = failures.entry((url.to_string(), timestamp)).or_default();
+        let failure = capture.failures.get(*count);
+
+        if failure.is_some() {
+            *count += 1;
+        }
+
+        failure
+    };
+
+    let mut response = match failure {
+        Some(failure) => {
+            let response = Response::new(failure.status);
+
+            match failure.retry_after {
+                Some(retry_after) => response.with_header("Retry-After", retry_after),
+                None => response,
+            }
+        }
+        None => {
+            let file = match (modifier, &capture.iframe_file) {
+                ("if_", Some(iframe_file)) => Some(iframe_file),
+                _ => capture.file.as_ref(),
+            };
+            let body = match file {
+                Some(file) => state.fixtures.read(file)?,
+                None => vec![],
+            };
+
+            let mut response = match &capture.redirect {
+                Some(redirect) => Response::new(302).with_header(
+                    "Location",
+                    format!(
+                        "http://{}/web/{}{}/{}",
+                        state.address, redirect.timestamp, modifier, redirect.url
+                    ),
+                ),
+                None => Response::new(capture.status),
+            }
+            .with_body(body);
+
+            for (name, value) in &capture.headers {
+                response = response.with_header(&format!("{ORIGINAL_HEADER_PREFIX}{name}"), value);
+            }
+
+            response.truncate = capture.truncate;
+            response
+        }
+    };
+
+    response.delay = Duration::from_millis(capture.delay_ms);
+
+    Ok(response)
+}
+
+fn respond_cdx(state: &State, target: &str) -> std::io::Result<Response> {
+    let parameters = url::Url::parse(&format!("http://{}{}", state.address, target))
+        .map(|url| url.query_pairs().into_owned().collect::<HashMap<_, _>>())
+        .unwrap_or_default();
+
+    let query = parameters.get("url").and_then(|url| {
+        state.fixtures.cdx_query(
+            url,
+            parameters
+                .get("matchType")
+                .map(String::as_str)
+                .unwrap_or("exact"),
+        )
+    });
+
+    let body = if parameters.get("showNumPages").map(String::as_str) == Some("true") {
+        format!(
+            "[[\"numpages\"],[\"{}\"]]",
+            query.map_or(0, |query| query.pages.len())
+        )
+        .into_bytes()
+    } else {
+        let page = parameters
+            .get("page")
+            .and_then(|page| page.parse::<usize>().ok())
+            .unwrap_or_default();
+
+        match query.and_then(|query| query.pages.get(page)) {
+            Some(path) => state.fixtures.read(path)?,
+            None => b"[]".to_vec(),
+        }
+    };
+
+    Ok(Response::new(200)
+        .with_header("Content-Type", "application/json")
+        .with_body(body))
+}
+
+fn respond_timemap(state: &State, url: &str) -> Response {
+    let mut captures = state
+        .fixtures
+        .captures
+        .iter()
+        .filter(|capture| capture.url == url)
+        .collect::<Vec<_>>();
+
+    if captures.is_empty() {
+        return Response::new(404);
+    }
+
+    captures.sort_by_key(|capture| capture.timestamp);
+
+    let mut links = vec![
+        format!("<{}>; rel=\"original\"", url),
+        format!(
+            "<http://{}{}{}>; rel=\"timegate\"",
+            state.address, TIMEGATE_PATH_PREFIX, url
+        ),
+    ];
+
+    for capture in captures {
+        links.push(format!(
+            "<http://{}{}{}/{}>; rel=\"memento\"; datetime=\"{}\"",
+            state.address,
+            TIMEGATE_PATH_PREFIX,
+            capture.timestamp,
+            url,
+            capture.timestamp.0.format(HTTP_DATE_FMT)
+        ));
+    }
+
+    Response::new(200)
+        .with_header("Content-Type", "application/link-format")
+        .with_body(links.join(",\n").into_bytes())
+}
+
+fn respond_timegate(state: &State, url: &str, accept_datetime: Option<&str>) -> Response {
+    let timestamp = match accept_datetime {
+        Some(value) => match chrono::DateTime::parse_from_rfc2822(value) {
+            Ok(datetime) => Timestamp(datetime.with_timezone(&chrono::Utc)),
+            Err(_) => return Response::new(400),
+        },
+        None => Timestamp(chrono::Utc::now()),
+    };
+
+    match state.fixtures.nearest_capture(url, timestamp) {
+        Some(capture) => Response::new(302)
+            .with_header(
+                "Location",
+                format!(
+                    "http://{}{}{}/{}",
+                    state.address, TIMEGATE_PATH_PREFIX, capture.timestamp, url
+                ),
+            )
+            .with_header("Vary", "accept-datetime"),
+        None => Response::new(404),
+    }
+}
+
+fn respond_submit(state: &State, target: &str, body: &[u8]) -> Response {
+    if target.trim_end_matches('/') != SAVE_PATH {
+        return Response::new(404);
+    }
+
+    let url = url::form_urlencoded::parse(body)
+        .find(|(name, _)| name == "url")
+        .map(|(_, value)| value.into_owned())
+        .unwrap_or_default();
+
+    let body = match state.fixtures.save(&url) {
+        Some(save) if save.reject => serde_json::json!({
+            "status": "error",
+            "status_ext": save.status_ext,
+            "message": save.message,
+        }),
+        Some(_) => {
+            let mut jobs = state.jobs.lock().unwrap();
+            let job_id = format!("spn2-{}", jobs.len());
+            jobs.insert(job_id.clone(), (url.clone(), 0));
+
+            serde_json::json!({ "url": url, "job_id": job_id })
+        }
+        None => serde_json::json!({
+            "status": "error",
+            "status_ext": "error:invalid-url-syntax",
+            "message": "The URL is not valid.",
+        }),
+    };
+
+    json_response(&body)
+}
+
+fn respond_status(state: &State, job_id: &str) -> Response {
+    let mut jobs = state.jobs.lock().unwrap();
+
+    let (url, polls) = match jobs.get_mut(job_id) {
+        Some(job) => job,
+        None => return Response::new(404),
+    };
+
+    *polls += 1;
+
+    // Safe because jobs are only created for URLs with fixtures.
+    let save = state.fixtures.save(url).unwrap();
+
+    if *polls <= save.failed_polls {
+        return Response::new(503);
+    }
+
+    let body = if *polls <= save.failed_polls + save.pending_polls {
+        serde_json::json!({ "status": "pending", "job_id": job_id })
+    } else {
+        match save.timestamp {
+            Some(timestamp) if save.status_ext.is_none() => serde_json::json!({
+                "status": "success",
+                "job_id": job_id,
+                "original_url": url,
+                "timestamp": timestamp.to_string(),
+            }),
+            _ => serde_json::json!({
+                "status": "error",
+                "job_id": job_id,
+                "status_ext": save.status_ext,
+                "message": save.message,
+            }),
+        }
+    };
+
+    json_response(&body)
+}
+
+fn json_response(body: &serde_json::Value) -> Response {
+    Response::new(200)
+        .with_header("Content-Type", "application/json")
+        .with_body(body.to_string().into_bytes())
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        302 => "Found",
+        400 => "Bad Request",
+        403 => "Forbidden",
+        404 => "Not Found",
+        429 => "Too Many Requests",
+        500 => "Internal Server Error",
+        502 => "Bad Gateway",
+        503 => "Service Unavailable",
+        _ => "Unknown",
+    }
+}
@@ -1,6 +1,11 @@
 use super::{error::Error, AuthDb, SqliteAuthorizer};
 use aib_auth::model::Provider;
-use rocket::http::CookieJar;
+use rocket::{
+    http::{CookieJar, Status},
+    outcome::try_outcome,
+    request::{FromRequest, Outcome, Request},
+    State,
+};
 use rocket_db_pools::Connection;
 
 pub mod callback;
@@ -66,3 +71,40 @@ pub async fn lookup_is_trusted(
         }
     })
 }
+
+/// A request guard that only succeeds for trusted users.
+///
+/// Requests without a token cookie are rejected without consulting the
+/// authorization database.
+pub struct Trusted;
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for Trusted {
+    type Error = Error;
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let cookies = request.cookies();
+
+        if TOKEN_COOKIE_NAMES
+            .iter()
+            .all(|name| cookies.get_private(name).is_none())
+        {
+            return Outcome::Error((Status::Unauthorized, Error::Unauthorized));
+        }
+
+        let authorizer = try_outcome!(request
+            .guard::<&State<SqliteAuthorizer>>()
+            .await
+            .map_error(|(status, _)| (status, Error::AuthorizationUnavailable)));
+        let connection = try_outcome!(request
+            .guard::<Connection<AuthDb>>()
+            .await
+            .map_error(|(status, _)| (status, Error::AuthorizationUnavailable)));
+
+        match lookup_is_trusted(cookies, authorizer, connection).await {
+            Ok(true) => Outcome::Success(Trusted),
+            Ok(false) => Outcome::Error((Status::Unauthorized, Error::Unauthorized)),
+            Err(error) => Outcome::Error((Status::InternalServerError, error)),
+        }
+    }
+}
@@ -16,8 +16,18 @@ pub enum Error {
     Sqlx(#[from] sqlx::Error),
     #[error("Search error")]
     Search(#[from] aib_manager::search::Error),
+    #[error("Item store error")]
+    Store(#[from] aib_store::items::Error),
+    #[error("Task error")]
+    Task(#[from] tokio::task::JoinError),
     #[error("Unauthorized")]
     Unauthorized,
+    #[error("Not found")]
+    NotFound,
+    #[error("Bad request")]
+    BadRequest,
+    #[error("Authorization unavailable")]
+    AuthorizationUnavailable,
     #[error("Authorization error")]
     Authorization(#[from] aib_auth::Error<aib_auth_sqlx::Error>),
     #[error("Google OpenID error")]
@@ -30,6 +40,8 @@ impl<'r, 'o: 'r> Responder<'r, 'o> for Error {
     fn respond_to(self, req: &'r Request<'_>) -> Result<'o> {
         match self {
             Self::Unauthorized => Status::Unauthorized.respond_to(req),
+            Self::NotFound => Status::NotFound.respond_to(req),
+            Self::BadRequest => Status::BadRequest.respond_to(req),
             _ => Status::InternalServerError.respond_to(req),
         }
     }
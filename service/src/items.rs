@@ -0,0 +1,441 @@
+//! Raw snapshot content by digest, for trusted users.
+
+use crate::auth::Trusted;
+use crate::error::Error;
+use aib_store::items::ItemStore;
+use indexmap::IndexMap;
+use rocket::{
+    http::{ContentType, Header, Status},
+    request::{FromRequest, Outcome, Request},
+    response::{self, Responder, Response},
+    serde::json::Json,
+    State,
+};
+use std::io::Cursor;
+use std::ops::RangeInclusive;
+
+const ZSTD_ENCODING: &str = "zstd";
+const CACHE_CONTROL: &str = "private, max-age=31536000, immutable";
+
+/// The request headers that affect how an item is returned.
+pub struct ItemRequest {
+    accepts_zstd: bool,
+    range: Option<String>,
+    if_none_match: Option<String>,
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for ItemRequest {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let headers = request.headers();
+
+        Outcome::Success(Self {
+            accepts_zstd: headers
+                .get("Accept-Encoding")
+                .any(|value| accepts_encoding(value, ZSTD_ENCODING)),
+            range: headers.get_one("Range").map(str::to_string),
+            if_none_match: headers.get_one("If-None-Match").map(str::to_string),
+        })
+    }
+}
+
+pub struct ItemResponse {
+    etag: String,
+    encoding: Option<&'static str>,
+    body: Vec<u8>,
+    range: Option<String>,
+    not_modified: bool,
+}
+
+impl<'r> Responder<'r, 'static> for ItemResponse {
+    fn respond_to(self, _: &'r Request<'_>) -> response::Result<'static> {
+        let mut response = Response::build();
+
+        response
+            .header(Header::new("ETag", self.etag))
+            .header(Header::new("Cache-Control", CACHE_CONTROL))
+            .header(Header::new("Vary", "Accept-Encoding"))
+            .header(Header::new("Accept-Ranges", "bytes"));
+
+        if self.not_modified {
+            return response.status(Status::NotModified).ok();
+        }
+
+        if let Some(encoding) = self.encoding {
+            response.header(Header::new("Content-Encoding", encoding));
+        }
+
+        response.header(ContentType::Binary);
+
+        let len = self.body.len() as u64;
+
+        match self
+            .range
+            .as_deref()
+            .and_then(|value| parse_range(value, len))
+        {
+            Some(Some(range)) => {
+                let (start, end) = (*range.start(), *range.end());
+                let body = self.body[start as usize..=end as usize].to_vec();
+
+                response
+                    .status(Status::PartialContent)
+                    .header(Header::new(
+                        "Content-Range",
+                        format!("bytes {}-{}/{}", start, end, len),
+                    ))
+                    .sized_body(body.len(), Cursor::new(body))
+                    .ok()
+            }
+            Some(None) => response
+                .status(Status::RangeNotSatisfiable)
+                .header(Header::new("Content-Range", format!("bytes */{}", len)))
+                .ok(),
+            None => response
+                .sized_body(self.body.len(), Cursor::new(self.body))
+                .ok(),
+        }
+    }
+}
+
+/// Serve an item, either decompressed or as stored (when the client accepts
+/// zstd encoding).
+///
+/// `HEAD` requests are handled by Rocket's automatic `GET` fallback.
+#[get("/items/<digest>")]
+pub async fn item(
+    digest: &str,
+    request: ItemRequest,
+    store: &State<ItemStore>,
+    _trusted: Trusted,
+) -> Result<ItemResponse, Error> {
+    respond(digest, request, store.inner()).await
+}
+
+async fn respond(
+    digest: &str,
+    request: ItemRequest,
+    store: &ItemStore,
+) -> Result<ItemResponse, Error> {
+    let encoding = if request.accepts_zstd {
+        Some(ZSTD_ENCODING)
+    } else {
+        None
+    };
+
+    let etag = match encoding {
+        Some(encoding) => format!("\"{}.{}\"", digest, encoding),
+        None => format!("\"{}\"", digest),
+    };
+
+    if !store.contains(digest) {
+        return Err(Error::NotFound);
+    }
+
+    if request
+        .if_none_match
+        .as_deref()
+        .map(|value| {
+            value
+                .split(',')
+                .any(|tag| tag.trim() == etag || tag.trim() == "*")
+        })
+        .unwrap_or(false)
+    {
+        return Ok(ItemResponse {
+            etag,
+            encoding,
+            body: vec![],
+            range: None,
+            not_modified: true,
+        });
+    }
+
+    let store = store.clone();
+    let digest = digest.to_string();
+
+    let body = tokio::task::spawn_blocking(move || {
+        if encoding.is_some() {
+            store.read_compressed(&digest)
+        } else {
+            store.read(&digest)
+        }
+    })
+    .await??
+    .ok_or(Error::NotFound)?;
+
+    Ok(ItemResponse {
+        etag,
+        encoding,
+        body,
+        range: request.range,
+        not_modified: false,
+    })
+}
+
+/// Check which of a list of digests are available.
+#[post("/items/exists", data = "<digests>")]
+pub async fn exists(
+    digests: Json<Vec<String>>,
+    store: &State<ItemStore>,
+    _trusted: Trusted,
+) -> Result<Json<IndexMap<String, bool>>, Error> {
+    Ok(Json(
+        digests
+            .0
+            .into_iter()
+            .map(|digest| {
+                let contains = store.contains(&digest);
+                (digest, contains)
+            })
+            .collect(),
+    ))
+}
+
+fn accepts_encoding(header: &str, encoding: &str) -> bool {
+    header.split(',').any(|part| {
+        let mut parameters = part.split(';').map(str::trim);
+
+        parameters
+            .next()
+            .map(|name| name.eq_ignore_ascii_case(encoding))
+            .unwrap_or(false)
+            && !parameters.any(|parameter| {
+                parameter
+                    .strip_prefix("q=")
+                    .and_then(|value| value.parse::<f32>().ok())
+                    .map(|value| value == 0.0)
+                    .unwrap_or(false)
+            })
+    })
+}
+
+/// Parse a single byte range.
+///
+/// Returns `None` if the header should be ignored (including when it
+/// contains multiple ranges or an invalid range), and `Some(None)` if it is
+/// unsatisfiable.
+fn parse_range(header: &str, len: u64) -> Option<Option<RangeInclusive<u64>>> {
+    let spec = header.trim().strip_prefix("bytes=")?;
+
+    if spec.contains(',') {
+        return None;
+    }
+
+    let (start, end) = spec.split_once('-')?;
+    let (start, end) = (start.trim(), end.trim());
+
+    let range = if start.is_empty() {
+        let suffix = end.parse::<u64>().ok()?;
+
+        if suffix == 0 || len == 0 {
+            None
+        } else {
+            Some(len.saturating_sub(suffix)..=len - 1)
+        }
+    } else {
+        let start = start.parse::<u64>().ok()?;
+        let end = if end.is_empty() {
+            len.saturating_sub(1)
+        } else {
+            let end = end.parse::<u64>().ok()?;
+
+            if end < start {
+                return None;
+            }
+
+            end.min(len.saturating_sub(1))
+        };
+
+        if start >= len {
+            None
+        } else {
+            Some(start..=end)
+        }
+    };
+
+    Some(range)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rocket::local::asynchronous::Client;
+
+    const CONTENTS: &str = "<html><title>Foo</title></html>";
+
+    #[get("/items/<digest>")]
+    async fn unauthorized_item(
+        digest: &str,
+        request: ItemRequest,
+        store: &State<ItemStore>,
+    ) -> Result<ItemResponse, Error> {
+        respond(digest, request, store.inner()).await
+    }
+
+    async fn client(store_dir: &std::path::Path) -> (Client, String) {
+        let store = ItemStore::new(store_dir, None);
+        let digest = aib_core::digest::compute_digest(&mut CONTENTS.as_bytes())
+            .unwrap()
+            .to_string();
+        store.save(&digest, &mut CONTENTS.as_bytes()).unwrap();
+
+        let rocket = rocket::build()
+            .manage(store)
+            .mount("/", routes![unauthorized_item])
+            .mount("/trusted", routes![item, exists]);
+
+        (Client::tracked(rocket).await.unwrap(), digest)
+    }
+
+    #[rocket::async_test]
+    async fn serve_ranges() {
+        let store_dir = tempdir::TempDir::new("items").unwrap();
+        let (client, digest) = client(store_dir.path()).await;
+
+        let response = client
+            .get(format!("/items/{}", digest))
+            .header(Header::new("Range", "bytes=6-12"))
+            .dispatch()
+            .await;
+
+        assert_eq!(response.status(), Status::PartialContent);
+        assert_eq!(
+            response.headers().get_one("Content-Range"),
+            Some(format!("bytes 6-12/{}", CONTENTS.len()).as_str())
+        );
+        assert_eq!(
+            response.into_string().await.as_deref(),
+            Some(&CONTENTS[6..=12])
+        );
+
+        let response = client
+            .get(format!("/items/{}", digest))
+            .header(Header::new("Range", "bytes=1000-"))
+            .dispatch()
+            .await;
+
+        assert_eq!(response.status(), Status::RangeNotSatisfiable);
+
+        let response = client
+            .get(format!("/items/{}", digest))
+            .header(Header::new("Range", "bytes=12-6"))
+            .dispatch()
+            .await;
+
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(response.into_string().await.as_deref(), Some(CONTENTS));
+    }
+
+    #[rocket::async_test]
+    async fn reject_untrusted() {
+        let store_dir = tempdir::TempDir::new("items").unwrap();
+        let (client, digest) = client(store_dir.path()).await;
+
+        let response = client
+            .get(format!("/trusted/items/{}", digest))
+            .dispatch()
+            .await;
+
+        assert_eq!(response.status(), Status::Unauthorized);
+
+        let response = client
+            .post("/trusted/items/exists")
+            .json(&vec![digest])
+            .dispatch()
+            .await;
+
+        assert_eq!(response.status(), Status::Unauthorized);
+    }
+
+    #[rocket::async_test]
+    async fn serve_not_modified() {
+        let store_dir = tempdir::TempDir::new("items").unwrap();
+        let (client, digest) = client(store_dir.path()).await;
+
+        let response = client.get(format!("/items/{}", digest)).dispatch().await;
+        let etag = response.headers().get_one("ETag").unwrap().to_string();
+
+        assert_eq!(etag, format!("\"{}\"", digest));
+        assert_eq!(response.into_string().await.as_deref(), Some(CONTENTS));
+
+        let response = client
+            .get(format!("/items/{}", digest))
+            .header(Header::new("If-None-Match", etag.clone()))
+            .dispatch()
+            .await;
+
+        assert_eq!(response.status(), Status::NotModified);
+
+        // The ETag of the decompressed item doesn't match the zstd-encoded one.
+        let response = client
+            .get(format!("/items/{}", digest))
+            .header(Header::new("Accept-Encoding", "zstd"))
+            .header(Header::new("If-None-Match", etag))
+            .dispatch()
+            .await;
+
+        assert_eq!(response.status(), Status::Ok);
+    }
+
+    #[rocket::async_test]
+    async fn serve_zstd() {
+        let store_dir = tempdir::TempDir::new("items").unwrap();
+        let (client, digest) = client(store_dir.path()).await;
+
+        let response = client
+            .get(format!("/items/{}", digest))
+            .header(Header::new("Accept-Encoding", "gzip, zstd"))
+            .dispatch()
+            .await;
+
+        assert_eq!(response.headers().get_one("Content-Encoding"), Some("zstd"));
+        assert_eq!(
+            response.headers().get_one("ETag"),
+            Some(format!("\"{}.zstd\"", digest).as_str())
+        );
+
+        let body = response.into_bytes().await.unwrap();
+
+        assert_eq!(
+            zstd::decode_all(body.as_slice()).unwrap(),
+            CONTENTS.as_bytes()
+        );
+
+        let response = client
+            .get(format!("/items/{}", digest))
+            .header(Header::new("Accept-Encoding", "zstd;q=0"))
+            .dispatch()
+            .await;
+
+        assert_eq!(response.headers().get_one("Content-Encoding"), None);
+        assert_eq!(response.into_string().await.as_deref(), Some(CONTENTS));
+
+        let response = client.get("/items/unknown").dispatch().await;
+
+        assert_eq!(response.status(), Status::NotFound);
+    }
+
+    #[test]
+    fn parse_ranges() {
+        assert_eq!(parse_range("bytes=0-9", 100), Some(Some(0..=9)));
+        assert_eq!(parse_range("bytes=90-", 100), Some(Some(90..=99)));
+        assert_eq!(parse_range("bytes=-10", 100), Some(Some(90..=99)));
+        assert_eq!(parse_range("bytes=50-500", 100), Some(Some(50..=99)));
+        assert_eq!(parse_range("bytes=100-", 100), Some(None));
+        assert_eq!(parse_range("bytes=150-200", 100), Some(None));
+        assert_eq!(parse_range("bytes=50-20", 100), None);
+        assert_eq!(parse_range("bytes=0-1,5-6", 100), None);
+        assert_eq!(parse_range("items=0-1", 100), None);
+    }
+
+    #[test]
+    fn accepts_zstd() {
+        assert!(accepts_encoding("gzip, zstd", "zstd"));
+        assert!(accepts_encoding("zstd;q=0.5", "zstd"));
+        assert!(!accepts_encoding("zstd;q=0", "zstd"));
+        assert!(!accepts_encoding("gzip, br", "zstd"));
+    }
+}
@@ -10,9 +10,13 @@ use aib_auth::{
 };
 use aib_auth_sqlx::SqlxAuthDb;
 use aib_indexer::{query::Range, Index};
-use aib_manager::model::Pattern;
+use aib_manager::model::{
+    DomainCount, FailureCount, InboundLink, LinkTarget, OutboundLink, Pattern,
+};
+use aib_store::items::ItemStore;
 use rocket::{
     fairing::{AdHoc, Fairing},
+    form::{Form, ValueField},
     http::CookieJar,
     serde::json::Json,
     Build, Rocket, State,
@@ -24,6 +28,7 @@ use std::path::PathBuf;
 
 mod auth;
 mod error;
+mod items;
 mod result;
 mod time;
 
@@ -32,6 +37,7 @@ use time::NaiveDateParam;
 const DEFAULT_SEARCH_LIMIT: usize = 10;
 const DEFAULT_SEARCH_SNIPPET_MAX_CHARS: usize = 200;
 const DEFAULT_FIRST_YEAR: u16 = 2004;
+const DEFAULT_LINK_LIMIT: usize = 100;
 const USER_AGENT: &str = "archivindex-builder";
 
 fn provider_fairing<P: IsProvider>() -> impl Fairing {
@@ -43,6 +49,7 @@ pub struct AppConfig {
     domain: Option<String>,
     authorization: String,
     index: PathBuf,
+    items: Option<PathBuf>,
     default_login_redirect_uri: rocket::http::uri::Reference<'static>,
 }
 
@@ -65,6 +72,71 @@ async fn patterns(
     ))
 }
 
+#[get("/failures")]
+async fn failures(
+    cookies: &CookieJar<'_>,
+    auth_db_connection: Connection<AuthDb>,
+    mut data_db_connection: Connection<DataDb>,
+    authorizer: &State<SqliteAuthorizer>,
+) -> Result<Json<Vec<FailureCount>>, error::Error> {
+    if !auth::lookup_is_trusted(cookies, authorizer, auth_db_connection).await? {
+        return Err(error::Error::Unauthorized);
+    }
+
+    Ok(Json(
+        aib_manager::db::entry::failure_counts(&mut *data_db_connection.as_mut()).await?,
+    ))
+}
+
+#[get("/links?<snapshot_id>")]
+async fn links(
+    snapshot_id: i64,
+    mut data_db_connection: Connection<DataDb>,
+) -> Result<Json<Vec<OutboundLink>>, error::Error> {
+    Ok(Json(
+        aib_manager::db::link::outbound(&mut *data_db_connection.as_mut(), snapshot_id).await?,
+    ))
+}
+
+#[get("/backlinks?<url>&<surt_prefix>&<limit>")]
+async fn backlinks(
+    url: Option<String>,
+    surt_prefix: Option<String>,
+    limit: Option<usize>,
+    mut data_db_connection: Connection<DataDb>,
+) -> Result<Json<Vec<InboundLink>>, error::Error> {
+    let target = match (url, surt_prefix) {
+        (Some(url), None) => LinkTarget::url(&url).map_err(|_| error::Error::BadRequest)?,
+        (None, Some(surt_prefix)) => LinkTarget::SurtPrefix(surt_prefix),
+        _ => return Err(error::Error::BadRequest),
+    };
+
+    Ok(Json(
+        aib_manager::db::link::inbound(
+            &mut *data_db_connection.as_mut(),
+            &target,
+            limit.unwrap_or(DEFAULT_LINK_LIMIT),
+        )
+        .await?,
+    ))
+}
+
+#[get("/link-domains?<pattern>&<limit>")]
+async fn link_domains(
+    pattern: String,
+    limit: Option<usize>,
+    mut data_db_connection: Connection<DataDb>,
+) -> Result<Json<Vec<DomainCount>>, error::Error> {
+    Ok(Json(
+        aib_manager::db::link::top_external_domains(
+            &mut *data_db_connection.as_mut(),
+            &pattern,
+            limit.unwrap_or(DEFAULT_LINK_LIMIT),
+        )
+        .await?,
+    ))
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize)]
 struct Query {
     #[serde(rename = "searchTerm")]
@@ -83,6 +155,77 @@ struct Filter {
     filter_type: String,
 }
 
+/// Search filters, shared by the GET and POST search endpoints.
+#[derive(FromForm)]
+struct SearchParams {
+    email: Option<String>,
+    start: Option<NaiveDateParam>,
+    end: Option<NaiveDateParam>,
+    pattern: Vec<String>,
+    year: Vec<u16>,
+    lang: Vec<String>,
+    status_author: Vec<String>,
+    status_id: Vec<u64>,
+    posted_start: Option<NaiveDateParam>,
+    posted_end: Option<NaiveDateParam>,
+    in_reply_to: Vec<String>,
+    retweeted_by: Vec<String>,
+    identifier: Vec<String>,
+    #[field(name = "type")]
+    types: Vec<String>,
+    author: Option<String>,
+    published_start: Option<NaiveDateParam>,
+    published_end: Option<NaiveDateParam>,
+    modified_start: Option<NaiveDateParam>,
+    modified_end: Option<NaiveDateParam>,
+    /// Only used by the GET endpoint (the POST endpoint pages with `current` and
+    /// `resultsPerPage`).
+    limit: Option<usize>,
+    offset: Option<usize>,
+}
+
+impl SearchParams {
+    /// Read filters with the same names and formats as the GET endpoint's parameters.
+    fn from_filters(filters: &[Filter]) -> Result<Self, error::Error> {
+        Form::parse_iter(filters.iter().flat_map(|filter| {
+            filter
+                .values
+                .iter()
+                .map(|value| ValueField::from((filter.field.as_str(), value.as_str())))
+        }))
+        .map_err(|_| error::Error::BadRequest)
+    }
+
+    fn into_query(self, content: &str) -> Result<aib_indexer::Query, error::Error> {
+        let date_range = |start: Option<NaiveDateParam>, end: Option<NaiveDateParam>| {
+            Range::new(start, end).map(|range| range.map(|value| value.into()))
+        };
+
+        Ok(aib_indexer::Query::new(content)
+            .with_gravatar_email(self.email.as_deref())
+            .with_date_range(date_range(self.start, self.end))
+            .with_pattern_slugs(self.pattern)
+            .with_years(self.year)
+            .with_langs(self.lang)
+            .with_status_authors(self.status_author)
+            .with_status_ids(self.status_id)
+            .with_posted_range(date_range(self.posted_start, self.posted_end))
+            .with_in_reply_to(self.in_reply_to)
+            .with_retweeted_by(self.retweeted_by)
+            .with_identifiers(
+                self.identifier
+                    .iter()
+                    .map(|value| value.parse())
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|_| error::Error::BadRequest)?,
+            )
+            .with_types(self.types)
+            .with_author(self.author.as_deref())
+            .with_published_range(date_range(self.published_start, self.published_end))
+            .with_modified_range(date_range(self.modified_start, self.modified_end)))
+    }
+}
+
 #[post("/search", data = "<query>")]
 async fn search_post(
     query: Json<Query>,
@@ -94,31 +237,7 @@ async fn search_post(
 ) -> Result<Json<result::SearchResult>, error::Error> {
     let db = aib_manager::db::Db::new(&mut data_db_connection);
 
-    let index_query = aib_indexer::Query::new(
-        &query.search_term,
-        None,
-        None,
-        query
-            .0
-            .filters
-            .iter()
-            .filter(|filter| filter.field == "pattern")
-            .flat_map(|filter| &filter.values)
-            .cloned()
-            .collect(),
-        query
-            .0
-            .filters
-            .iter()
-            .filter(|filter| filter.field == "year")
-            .flat_map(|filter| {
-                filter
-                    .values
-                    .iter()
-                    .map(|value| value.parse::<u16>().unwrap_or(0))
-            })
-            .collect(),
-    );
+    let index_query = SearchParams::from_filters(&query.filters)?.into_query(&query.search_term)?;
 
     let search_result = aib_manager::search::search(
         index,
@@ -139,16 +258,10 @@ async fn search_post(
     Ok(Json(search_result.into()))
 }
 
-#[get("/search?<query>&<email>&<start>&<end>&<pattern>&<year>&<limit>&<offset>")]
+#[get("/search?<query>&<params..>")]
 async fn search(
     query: String,
-    email: Option<String>,
-    start: Option<NaiveDateParam>,
-    end: Option<NaiveDateParam>,
-    pattern: Option<Vec<String>>,
-    year: Option<Vec<u16>>,
-    limit: Option<usize>,
-    offset: Option<usize>,
+    params: SearchParams,
     cookies: &CookieJar<'_>,
     index: &State<Index>,
     auth_db_connection: Connection<AuthDb>,
@@ -157,23 +270,17 @@ async fn search(
 ) -> Result<Json<result::SearchResult>, error::Error> {
     let db = aib_manager::db::Db::new(&mut data_db_connection);
 
-    let date_range = Range::new(start, end).map(|range| range.map(|value| value.into()));
-
-    let query = aib_indexer::Query::new(
-        &query,
-        email.as_deref(),
-        date_range,
-        pattern.unwrap_or_default(),
-        year.unwrap_or_default(),
-    );
+    let limit = params.limit.unwrap_or(DEFAULT_SEARCH_LIMIT);
+    let offset = params.offset.unwrap_or(0);
+    let query = params.into_query(&query)?;
 
     let search_result = aib_manager::search::search(
         index,
         db,
         DEFAULT_SEARCH_SNIPPET_MAX_CHARS,
         &query,
-        limit.unwrap_or(DEFAULT_SEARCH_LIMIT),
-        offset.unwrap_or(0),
+        limit,
+        offset,
     )
     .await;
 
@@ -224,6 +331,17 @@ fn rocket() -> _ {
                 }
             }
         }))
+        .attach(AdHoc::on_ignite("items", |rocket| async {
+            match rocket
+                .state::<AppConfig>()
+                .and_then(|config| config.items.clone())
+            {
+                Some(path) => rocket
+                    .manage(ItemStore::new(path, None))
+                    .mount("/", routes![items::item, items::exists]),
+                None => rocket,
+            }
+        }))
         .attach(cors.to_cors().unwrap())
         .attach(provider_fairing::<GitHub>())
         .attach(provider_fairing::<Google>())
@@ -232,6 +350,10 @@ fn rocket() -> _ {
             "/",
             routes![
                 patterns,
+                failures,
+                links,
+                backlinks,
+                link_domains,
                 search,
                 search_post,
                 auth::login::status,
@@ -284,3 +406,41 @@ async fn init_authorization(rocket: &Rocket<Build>) -> Option<SqliteAuthorizer>
     .await
     .ok()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn search_params() {
+        let from_query = Form::<SearchParams>::parse(
+            "query=test&pattern=a&pattern=b&type=article&start=2020-01-01&status_id=20&limit=5",
+        )
+        .unwrap()
+        .into_query("test")
+        .unwrap();
+
+        let filter = |field: &str, values: &[&str]| Filter {
+            field: field.to_string(),
+            values: values.iter().map(|value| value.to_string()).collect(),
+            filter_type: "any".to_string(),
+        };
+        let from_filters = SearchParams::from_filters(&[
+            filter("pattern", &["a", "b"]),
+            filter("type", &["article"]),
+            filter("start", &["2020-01-01"]),
+            filter("status_id", &["20"]),
+        ])
+        .unwrap()
+        .into_query("test")
+        .unwrap();
+
+        assert_eq!(from_query, from_filters);
+        assert_eq!(from_query.status_ids, Some([20].into_iter().collect()));
+        assert!(from_query.date_range.is_some());
+        assert!(matches!(
+            SearchParams::from_filters(&[filter("status_id", &["jack"])]),
+            Err(error::Error::BadRequest)
+        ));
+    }
+}
@@ -38,6 +38,7 @@ impl From<aib_manager::search::SearchResult> for SearchResult {
                             url: hit.url.to_wb_url(true, false),
                             title: hit.title,
                             snippet: hit.snippet.to_html(SNIPPET_HIGHLIGHT_TAG),
+                            metadata: hit.metadata,
                         });
                     }
 
@@ -89,4 +90,5 @@ pub struct SnapshotResult {
     pub url: String,
     pub title: String,
     pub snippet: String,
+    pub metadata: aib_manager::search::HitMetadata,
 }
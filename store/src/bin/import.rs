@@ -1,6 +1,13 @@
+use aib_store::items::{
+    index::DigestIndex,
+    manifest::Manifest,
+    metadata::{Metadata, MetadataTable},
+    sync::{Remote, Source},
+};
 use cli_helpers::prelude::*;
-use futures::stream::TryStreamExt;
+use futures::stream::{StreamExt, TryStreamExt};
 use std::fs::File;
+use std::io::BufRead;
 use std::path::PathBuf;
 
 #[tokio::main]
@@ -79,6 +86,127 @@ async fn main() -> Result<(), Error> {
                 })
                 .await?;
         }
+        Command::Manifest { input, output } => {
+            let store = aib_store::items::ItemStore::new(input, None);
+            let manifest = Manifest::from_store(&store)?;
+
+            manifest.write(File::create(output)?)?;
+
+            log::info!("Wrote manifest with {} digests", manifest.len());
+        }
+        Command::Sync {
+            source,
+            manifest,
+            output,
+            level,
+            parallelism,
+        } => {
+            let source = if source.starts_with("http://") || source.starts_with("https://") {
+                let manifest = manifest.ok_or(Error::MissingManifest)?;
+
+                Source::Remote(Remote::new(&source, &manifest)?)
+            } else {
+                Source::Local(aib_store::items::ItemStore::new(source, level))
+            };
+
+            let target = aib_store::items::ItemStore::new(output, level);
+            let missing = source
+                .manifest()
+                .await?
+                .difference(&Manifest::from_store(&target)?);
+
+            log::info!("Copying {} items", missing.len());
+
+            let mut count = 0;
+            let mut failed = 0;
+            let mut results = aib_store::items::sync::sync(&source, &target, missing, parallelism);
+
+            while let Some(result) = results.next().await {
+                match result {
+                    Ok((_, Some(_))) => {
+                        count += 1;
+                    }
+                    Ok((digest, None)) => {
+                        log::info!("Skipped {}", digest);
+                    }
+                    Err(error) => {
+                        log::error!("{:?}", error);
+                        failed += 1;
+                    }
+                }
+            }
+
+            log::info!("Copied {} items ({} failed)", count, failed);
+        }
+        Command::Index {
+            input,
+            index,
+            bloom,
+        } => {
+            let store = aib_store::items::ItemStore::new(input, None);
+            let index = DigestIndex::rebuild(index, &store, bloom)?;
+
+            log::info!("Indexed {} digests", index.len());
+        }
+        Command::CompactIndex { index } => {
+            let index = DigestIndex::open(index)?.compact()?;
+
+            log::info!("Compacted index with {} digests", index.len());
+        }
+        Command::Metadata {
+            input,
+            table,
+            parallelism,
+        } => {
+            let store = aib_store::items::ItemStore::new(input, None);
+            let mut table = MetadataTable::open(table)?;
+            let manifest = Manifest::from_store(&store)?;
+            let digests = manifest
+                .digests()
+                .iter()
+                .filter(|digest| !table.contains(digest))
+                .copied()
+                .collect::<Vec<_>>();
+
+            log::info!("Computing metadata for {} items", digests.len());
+
+            let mut results = futures::stream::iter(digests)
+                .map(|digest| {
+                    let store = store.clone();
+                    tokio::task::spawn_blocking(move || Metadata::compute(&store, digest))
+                })
+                .buffer_unordered(parallelism);
+
+            let mut count = 0;
+
+            while let Some(result) = results.next().await {
+                match result? {
+                    Ok(Some(metadata)) => {
+                        table.insert(metadata)?;
+                        count += 1;
+                    }
+                    Ok(None) => {}
+                    Err(error) => {
+                        log::error!("{:?}", error);
+                    }
+                }
+            }
+
+            table.flush()?;
+
+            log::info!("Added metadata for {} items", count);
+        }
+        Command::Missing { index, input } => {
+            let index = DigestIndex::open(index)?;
+            let digests = std::io::BufReader::new(File::open(input)?)
+                .lines()
+                .map(|line| Ok(line?.trim().parse()?))
+                .collect::<Result<Vec<_>, Error>>()?;
+
+            for digest in index.missing(digests) {
+                println!("{}", digest);
+            }
+        }
     }
 
     Ok(())
@@ -96,6 +224,20 @@ pub enum Error {
     Store(#[from] aib_store::Error),
     #[error("Item store error")]
     ItemStore(#[from] aib_store::items::Error),
+    #[error("Manifest error")]
+    Manifest(#[from] aib_store::items::manifest::Error),
+    #[error("Sync error")]
+    Sync(#[from] aib_store::items::sync::Error),
+    #[error("Metadata error")]
+    Metadata(#[from] aib_store::items::metadata::Error),
+    #[error("Task error")]
+    Task(#[from] tokio::task::JoinError),
+    #[error("Index error")]
+    Index(#[from] aib_store::items::index::Error),
+    #[error("Digest error")]
+    Digest(#[from] aib_core::digest::Error),
+    #[error("Remote source requires a manifest URL")]
+    MissingManifest,
 }
 
 #[derive(Debug, Parser)]
@@ -137,4 +279,48 @@ enum Command {
         #[clap(long)]
         level: Option<i32>,
     },
+    Manifest {
+        #[clap(long)]
+        input: PathBuf,
+        #[clap(long)]
+        output: PathBuf,
+    },
+    Sync {
+        #[clap(long)]
+        source: String,
+        #[clap(long)]
+        manifest: Option<String>,
+        #[clap(long)]
+        output: PathBuf,
+        #[clap(long)]
+        level: Option<i32>,
+        #[clap(long, default_value = "8")]
+        parallelism: usize,
+    },
+    Index {
+        #[clap(long)]
+        input: PathBuf,
+        #[clap(long)]
+        index: PathBuf,
+        #[clap(long)]
+        bloom: bool,
+    },
+    CompactIndex {
+        #[clap(long)]
+        index: PathBuf,
+    },
+    Metadata {
+        #[clap(long)]
+        input: PathBuf,
+        #[clap(long)]
+        table: PathBuf,
+        #[clap(long, default_value = "8")]
+        parallelism: usize,
+    },
+    Missing {
+        #[clap(long)]
+        index: PathBuf,
+        #[clap(long)]
+        input: PathBuf,
+    },
 }
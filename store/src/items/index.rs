@@ -0,0 +1,474 @@
+//! Persistent digest membership index for item stores.
+//!
+//! The index lives in its own directory (outside the store) and consists of:
+//!
+//! * `digests`: the sorted 20-byte digests of all items as of the last rebuild
+//!   or compaction.
+//! * `log`: one `+<digest>` or `-<digest>` line for every item saved to or
+//!   removed from the store since then.
+//! * `bloom` (optional): a Bloom filter over all indexed digests, which lets
+//!   most lookups for missing items skip the binary search.
+//!
+//! The index can always be rebuilt from the store's file names.
+
+use super::manifest::Manifest;
+use super::ItemStore;
+use aib_core::digest::Sha1Digest;
+use std::collections::HashSet;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+const DIGESTS_FILE_NAME: &str = "digests";
+const LOG_FILE_NAME: &str = "log";
+const BLOOM_FILE_NAME: &str = "bloom";
+const DIGEST_LEN: usize = 20;
+const BLOOM_FALSE_POSITIVE_RATE: f64 = 0.01;
+const BLOOM_MIN_CAPACITY: usize = 1_000_000;
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("I/O error")]
+    Io(#[from] std::io::Error),
+    #[error("Digest error")]
+    Digest(#[from] aib_core::digest::Error),
+    #[error("Manifest error")]
+    Manifest(#[from] super::manifest::Error),
+    #[error("Invalid index file")]
+    InvalidFile(PathBuf),
+    #[error("Invalid log line")]
+    InvalidLogLine(String),
+}
+
+pub struct DigestIndex {
+    base: PathBuf,
+    digests: Vec<Sha1Digest>,
+    state: Mutex<State>,
+    log: Mutex<BufWriter<File>>,
+}
+
+struct State {
+    added: HashSet<Sha1Digest>,
+    removed: HashSet<Sha1Digest>,
+    bloom: Option<Bloom>,
+}
+
+impl std::fmt::Debug for DigestIndex {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DigestIndex")
+            .field("base", &self.base)
+            .field("digests", &self.digests.len())
+            .finish()
+    }
+}
+
+impl DigestIndex {
+    /// Open an existing index, or create an empty one.
+    pub fn open<P: AsRef<Path>>(base: P) -> Result<Self, Error> {
+        let base = base.as_ref().to_path_buf();
+        std::fs::create_dir_all(&base)?;
+
+        let digests_path = base.join(DIGESTS_FILE_NAME);
+        let digests = if digests_path.is_file() {
+            read_digests(&digests_path)?
+        } else {
+            vec![]
+        };
+
+        let bloom_path = base.join(BLOOM_FILE_NAME);
+        let bloom = if bloom_path.is_file() {
+            Some(Bloom::read(&bloom_path)?)
+        } else {
+            None
+        };
+
+        let mut state = State {
+            added: HashSet::new(),
+            removed: HashSet::new(),
+            bloom,
+        };
+
+        let log_path = base.join(LOG_FILE_NAME);
+
+        if log_path.is_file() {
+            for line in BufReader::new(File::open(&log_path)?).lines() {
+                let line = line?;
+                let (op, digest) = line.split_at(line.len().min(1));
+                let digest = digest.parse()?;
+
+                match op {
+                    "+" => state.add(&digests, digest),
+                    "-" => state.remove(&digests, digest),
+                    _ => return Err(Error::InvalidLogLine(line)),
+                }
+            }
+        }
+
+        let log = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(log_path)?;
+
+        Ok(Self {
+            base,
+            digests,
+            state: Mutex::new(state),
+            log: Mutex::new(BufWriter::new(log)),
+        })
+    }
+
+    /// Rebuild the index from the file names in a store.
+    pub fn rebuild<P: AsRef<Path>>(base: P, store: &ItemStore, bloom: bool) -> Result<Self, Error> {
+        let base = base.as_ref();
+        std::fs::create_dir_all(base)?;
+
+        let manifest = Manifest::from_store(store)?;
+        write_digests(&base.join(DIGESTS_FILE_NAME), manifest.digests())?;
+
+        let bloom_path = base.join(BLOOM_FILE_NAME);
+
+        if bloom {
+            Bloom::from_digests(manifest.digests()).write(&bloom_path)?;
+        } else if bloom_path.exists() {
+            std::fs::remove_file(bloom_path)?;
+        }
+
+        File::create(base.join(LOG_FILE_NAME))?;
+
+        Self::open(base)
+    }
+
+    /// Merge the log into the sorted digest file.
+    pub fn compact(self) -> Result<Self, Error> {
+        let state = self.state.into_inner().unwrap();
+        let mut digests = self
+            .digests
+            .into_iter()
+            .filter(|digest| !state.removed.contains(digest))
+            .collect::<Vec<_>>();
+        digests.extend(state.added);
+        digests.sort();
+
+        write_digests(&self.base.join(DIGESTS_FILE_NAME), &digests)?;
+
+        if state.bloom.is_some() {
+            Bloom::from_digests(&digests).write(&self.base.join(BLOOM_FILE_NAME))?;
+        }
+
+        drop(self.log);
+        File::create(self.base.join(LOG_FILE_NAME))?;
+
+        Self::open(self.base)
+    }
+
+    pub fn len(&self) -> usize {
+        let state = self.state.lock().unwrap();
+
+        self.digests.len() + state.added.len() - state.removed.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn contains(&self, digest: &Sha1Digest) -> bool {
+        self.state.lock().unwrap().contains(&self.digests, digest)
+    }
+
+    /// Return the digests that are not in the index.
+    pub fn missing<I: IntoIterator<Item = Sha1Digest>>(&self, digests: I) -> Vec<Sha1Digest> {
+        let state = self.state.lock().unwrap();
+
+        digests
+            .into_iter()
+            .filter(|digest| !state.contains(&self.digests, digest))
+            .collect()
+    }
+
+    pub fn insert(&self, digest: Sha1Digest) -> Result<(), Error> {
+        let mut state = self.state.lock().unwrap();
+
+        if !state.contains(&self.digests, &digest) {
+            let mut log = self.log.lock().unwrap();
+            writeln!(log, "+{}", digest)?;
+            log.flush()?;
+            state.add(&self.digests, digest);
+        }
+
+        Ok(())
+    }
+
+    pub fn remove(&self, digest: Sha1Digest) -> Result<(), Error> {
+        let mut state = self.state.lock().unwrap();
+
+        if state.contains(&self.digests, &digest) {
+            let mut log = self.log.lock().unwrap();
+            writeln!(log, "-{}", digest)?;
+            log.flush()?;
+            state.remove(&self.digests, digest);
+        }
+
+        Ok(())
+    }
+}
+
+impl State {
+    fn contains(&self, digests: &[Sha1Digest], digest: &Sha1Digest) -> bool {
+        if self.added.contains(digest) {
+            true
+        } else if self.removed.contains(digest)
+            || self
+                .bloom
+                .as_ref()
+                .map(|bloom| !bloom.contains(digest))
+                .unwrap_or(false)
+        {
+            false
+        } else {
+            digests.binary_search(digest).is_ok()
+        }
+    }
+
+    fn add(&mut self, digests: &[Sha1Digest], digest: Sha1Digest) {
+        if !self.removed.remove(&digest) && digests.binary_search(&digest).is_err() {
+            self.added.insert(digest);
+        }
+
+        if let Some(bloom) = self.bloom.as_mut() {
+            bloom.insert(&digest);
+        }
+    }
+
+    fn remove(&mut self, digests: &[Sha1Digest], digest: Sha1Digest) {
+        if !self.added.remove(&digest) && digests.binary_search(&digest).is_ok() {
+            self.removed.insert(digest);
+        }
+    }
+}
+
+fn read_digests(path: &Path) -> Result<Vec<Sha1Digest>, Error> {
+    let mut bytes = vec![];
+    File::open(path)?.read_to_end(&mut bytes)?;
+
+    if bytes.len() % DIGEST_LEN != 0 {
+        return Err(Error::InvalidFile(path.to_path_buf()));
+    }
+
+    Ok(bytes
+        .chunks_exact(DIGEST_LEN)
+        .map(|chunk| {
+            let mut digest = [0; DIGEST_LEN];
+            digest.copy_from_slice(chunk);
+            Sha1Digest(digest)
+        })
+        .collect())
+}
+
+fn write_digests(path: &Path, digests: &[Sha1Digest]) -> Result<(), Error> {
+    let temporary_path = path.with_extension("tmp");
+    let mut writer = BufWriter::new(File::create(&temporary_path)?);
+
+    for digest in digests {
+        writer.write_all(&digest.0)?;
+    }
+
+    writer.flush()?;
+    drop(writer);
+    std::fs::rename(temporary_path, path)?;
+
+    Ok(())
+}
+
+/// A Bloom filter over SHA-1 digests.
+///
+/// Since the digests are already uniformly distributed, bit positions are
+/// derived directly from their bytes instead of from additional hashing.
+struct Bloom {
+    bits: Vec<u64>,
+    hash_count: u32,
+}
+
+impl Bloom {
+    fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(BLOOM_MIN_CAPACITY) as f64;
+        let ln_2 = std::f64::consts::LN_2;
+        let bit_count =
+            (-capacity * BLOOM_FALSE_POSITIVE_RATE.ln() / (ln_2 * ln_2)).ceil() as usize;
+        let hash_count = ((bit_count as f64 / capacity) * ln_2).round().max(1.0) as u32;
+
+        Self {
+            bits: vec![0; bit_count.div_ceil(64)],
+            hash_count,
+        }
+    }
+
+    fn from_digests(digests: &[Sha1Digest]) -> Self {
+        // Leave room for items added before the next compaction.
+        let mut bloom = Self::new(digests.len() * 2);
+
+        for digest in digests {
+            bloom.insert(digest);
+        }
+
+        bloom
+    }
+
+    fn positions(&self, digest: &Sha1Digest) -> impl Iterator<Item = usize> {
+        let bit_count = (self.bits.len() * 64) as u64;
+        let mut h1 = [0; 8];
+        let mut h2 = [0; 8];
+        h1.copy_from_slice(&digest.0[0..8]);
+        h2.copy_from_slice(&digest.0[8..16]);
+        let h1 = u64::from_le_bytes(h1);
+        let h2 = u64::from_le_bytes(h2);
+
+        (0..self.hash_count as u64)
+            .map(move |i| (h1.wrapping_add(i.wrapping_mul(h2)) % bit_count) as usize)
+    }
+
+    fn insert(&mut self, digest: &Sha1Digest) {
+        for position in self.positions(digest).collect::<Vec<_>>() {
+            self.bits[position / 64] |= 1 << (position % 64);
+        }
+    }
+
+    fn contains(&self, digest: &Sha1Digest) -> bool {
+        self.positions(digest)
+            .all(|position| self.bits[position / 64] & (1 << (position % 64)) != 0)
+    }
+
+    fn read(path: &Path) -> Result<Self, Error> {
+        let mut bytes = vec![];
+        File::open(path)?.read_to_end(&mut bytes)?;
+
+        if bytes.len() < 4 || (bytes.len() - 4) % 8 != 0 {
+            return Err(Error::InvalidFile(path.to_path_buf()));
+        }
+
+        let mut hash_count = [0; 4];
+        hash_count.copy_from_slice(&bytes[0..4]);
+
+        let bits = bytes[4..]
+            .chunks_exact(8)
+            .map(|chunk| {
+                let mut word = [0; 8];
+                word.copy_from_slice(chunk);
+                u64::from_le_bytes(word)
+            })
+            .collect::<Vec<_>>();
+
+        if bits.is_empty() {
+            Err(Error::InvalidFile(path.to_path_buf()))
+        } else {
+            Ok(Self {
+                bits,
+                hash_count: u32::from_le_bytes(hash_count),
+            })
+        }
+    }
+
+    fn write(&self, path: &Path) -> Result<(), Error> {
+        let temporary_path = path.with_extension("tmp");
+        let mut writer = BufWriter::new(File::create(&temporary_path)?);
+
+        writer.write_all(&self.hash_count.to_le_bytes())?;
+
+        for word in &self.bits {
+            writer.write_all(&word.to_le_bytes())?;
+        }
+
+        writer.flush()?;
+        drop(writer);
+        std::fs::rename(temporary_path, path)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn digest(value: &str) -> Sha1Digest {
+        aib_core::digest::compute_digest(&mut value.as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn rebuild_update_and_reopen() {
+        let store_dir = tempdir::TempDir::new("index-store").unwrap();
+        let index_dir = tempdir::TempDir::new("index").unwrap();
+        let store = ItemStore::new(store_dir.path(), None);
+
+        for value in ["foo", "bar"] {
+            store
+                .save(&digest(value).to_string(), &mut value.as_bytes())
+                .unwrap();
+        }
+
+        let index = DigestIndex::rebuild(index_dir.path(), &store, true).unwrap();
+
+        assert_eq!(index.len(), 2);
+        assert!(index.contains(&digest("foo")));
+        assert!(!index.contains(&digest("baz")));
+
+        index.insert(digest("baz")).unwrap();
+        index.remove(digest("foo")).unwrap();
+        drop(index);
+
+        let index = DigestIndex::open(index_dir.path()).unwrap();
+
+        assert_eq!(index.len(), 2);
+        assert_eq!(
+            index.missing(vec![
+                digest("foo"),
+                digest("bar"),
+                digest("baz"),
+                digest("qux")
+            ]),
+            vec![digest("foo"), digest("qux")]
+        );
+
+        let index = index.compact().unwrap();
+
+        assert_eq!(index.len(), 2);
+        assert!(index.contains(&digest("baz")));
+        assert!(!index.contains(&digest("foo")));
+    }
+
+    #[test]
+    fn store_updates_index() {
+        let store_dir = tempdir::TempDir::new("index-store").unwrap();
+        let index_dir = tempdir::TempDir::new("index").unwrap();
+        let quarantine_dir = tempdir::TempDir::new("index-quarantine").unwrap();
+        let store = ItemStore::new(store_dir.path(), None).with_index(
+            DigestIndex::rebuild(
+                index_dir.path(),
+                &ItemStore::new(store_dir.path(), None),
+                true,
+            )
+            .unwrap(),
+        );
+
+        for value in ["foo", "bar"] {
+            store
+                .save(&digest(value).to_string(), &mut value.as_bytes())
+                .unwrap();
+        }
+
+        assert!(store.contains(&digest("foo").to_string()));
+        assert!(store.contains(&digest("bar").to_string()));
+
+        store.remove(&digest("foo").to_string()).unwrap();
+
+        let entry = crate::items::Entry {
+            path: store.location(&digest("bar").to_string()).unwrap(),
+            digest: digest("bar"),
+        };
+        store.quarantine(&entry, quarantine_dir.path()).unwrap();
+
+        assert!(!store.contains(&digest("foo").to_string()));
+        assert!(!store.contains(&digest("bar").to_string()));
+        assert_eq!(store.index().unwrap().len(), 0);
+    }
+}
@@ -0,0 +1,211 @@
+//! A persistent record of item verification results.
+//!
+//! The ledger is an append-only CSV file with one line per verification. When
+//! it is opened, later lines for a digest replace earlier ones, so the
+//! in-memory view always reflects the most recent result for each item.
+
+use super::Entry;
+use aib_core::digest::Sha1Digest;
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::path::Path;
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("I/O error")]
+    Io(#[from] std::io::Error),
+    #[error("CSV error")]
+    Csv(#[from] csv::Error),
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    /// The item's contents match its digest.
+    Valid,
+    /// The item's contents do not match its digest.
+    Invalid,
+    /// The item could not be read or decompressed.
+    Unreadable,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct Record {
+    pub digest: Sha1Digest,
+    pub timestamp: DateTime<Utc>,
+    pub status: Status,
+    /// The digest of the item's actual contents, if it was invalid.
+    pub actual: Option<Sha1Digest>,
+}
+
+impl Record {
+    pub fn new(digest: Sha1Digest, status: Status, actual: Option<Sha1Digest>) -> Self {
+        Self {
+            digest,
+            timestamp: Utc::now(),
+            status,
+            actual,
+        }
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.status == Status::Valid
+    }
+}
+
+pub struct Ledger {
+    records: HashMap<Sha1Digest, Record>,
+    writer: csv::Writer<File>,
+}
+
+impl Ledger {
+    /// Open a ledger file, creating it if it does not exist.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let mut records = HashMap::new();
+
+        if path.is_file() {
+            let mut reader = csv::ReaderBuilder::new()
+                .has_headers(false)
+                .from_path(path)?;
+
+            for record in reader.deserialize::<Record>() {
+                let record = record?;
+                records.insert(record.digest, record);
+            }
+        }
+
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let writer = csv::WriterBuilder::new()
+            .has_headers(false)
+            .from_writer(file);
+
+        Ok(Self { records, writer })
+    }
+
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    pub fn get(&self, digest: &Sha1Digest) -> Option<&Record> {
+        self.records.get(digest)
+    }
+
+    /// Indicates whether an item has not been verified since the given time.
+    pub fn is_due(&self, digest: &Sha1Digest, cutoff: DateTime<Utc>) -> bool {
+        self.records
+            .get(digest)
+            .map(|record| record.timestamp < cutoff)
+            .unwrap_or(true)
+    }
+
+    /// Select entries that are due for verification.
+    ///
+    /// Entries that have never been verified come first, followed by the
+    /// others in order of their last verification.
+    pub fn select(
+        &self,
+        entries: Vec<Entry>,
+        cutoff: Option<DateTime<Utc>>,
+        limit: Option<usize>,
+    ) -> Vec<Entry> {
+        let mut selected = entries
+            .into_iter()
+            .filter(|entry| {
+                cutoff
+                    .map(|cutoff| self.is_due(&entry.digest, cutoff))
+                    .unwrap_or(true)
+            })
+            .collect::<Vec<_>>();
+
+        if let Some(limit) = limit {
+            selected.sort_by_key(|entry| {
+                self.records
+                    .get(&entry.digest)
+                    .map(|record| record.timestamp)
+            });
+            selected.truncate(limit);
+        }
+
+        selected
+    }
+
+    /// Record a verification result, writing it to the file immediately so
+    /// that an interrupted run keeps its progress.
+    pub fn record(&mut self, record: Record) -> Result<(), Error> {
+        self.writer.serialize(&record)?;
+        self.writer.flush()?;
+        self.records.insert(record.digest, record);
+
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> Result<(), Error> {
+        Ok(self.writer.flush()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+    use std::path::PathBuf;
+
+    fn entry(digest: &str) -> Entry {
+        Entry {
+            path: PathBuf::from(digest),
+            digest: digest.parse().unwrap(),
+        }
+    }
+
+    #[test]
+    fn select_and_reopen() {
+        let dir = tempdir::TempDir::new("ledger").unwrap();
+        let path = dir.path().join("ledger.csv");
+
+        let a = entry("2G3EOT7X6IEQZXKSM3OJJDW6RBCHB7YE");
+        let b = entry("5DECQVIU7Y3F276SIBAKKCRGDMVXJYFV");
+        let c = entry("AJBB526CEZFOBT3FCQYLRMXQ2MSFHE3O");
+
+        let now = Utc::now();
+
+        {
+            let mut ledger = Ledger::open(&path).unwrap();
+            ledger
+                .record(Record {
+                    digest: a.digest,
+                    timestamp: now - Duration::days(100),
+                    status: Status::Valid,
+                    actual: None,
+                })
+                .unwrap();
+            ledger
+                .record(Record {
+                    digest: b.digest,
+                    timestamp: now - Duration::days(10),
+                    status: Status::Invalid,
+                    actual: Some(c.digest),
+                })
+                .unwrap();
+
+            // Records are written as they're made.
+            assert_eq!(Ledger::open(&path).unwrap().len(), 2);
+        }
+
+        let ledger = Ledger::open(&path).unwrap();
+        let entries = vec![a.clone(), b.clone(), c.clone()];
+
+        assert_eq!(ledger.len(), 2);
+        assert_eq!(ledger.get(&b.digest).unwrap().actual, Some(c.digest));
+        assert_eq!(
+            ledger.select(entries.clone(), Some(now - Duration::days(90)), None),
+            vec![a.clone(), c.clone()]
+        );
+        assert_eq!(ledger.select(entries, None, Some(2)), vec![c, a]);
+    }
+}
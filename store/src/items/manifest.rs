@@ -0,0 +1,130 @@
+//! Sorted digest lists for item stores.
+//!
+//! A manifest is a zstd-compressed text file with one Base32 digest per line,
+//! in sorted order. It lets another store find out which items this one has
+//! without walking the directory tree.
+
+use super::{ItemStore, ValidationError};
+use aib_core::digest::Sha1Digest;
+use std::io::{BufRead, BufReader, Read, Write};
+
+const COMPRESSION_LEVEL: i32 = 3;
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("I/O error")]
+    Io(#[from] std::io::Error),
+    #[error("Digest error")]
+    Digest(#[from] aib_core::digest::Error),
+}
+
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Manifest {
+    digests: Vec<Sha1Digest>,
+}
+
+impl Manifest {
+    pub fn new(mut digests: Vec<Sha1Digest>) -> Self {
+        digests.sort();
+        digests.dedup();
+
+        Self { digests }
+    }
+
+    /// Build a manifest from the file names in a store (contents are not checked).
+    pub fn from_store(store: &ItemStore) -> Result<Self, Error> {
+        let mut digests = vec![];
+
+        for result in store.unchecked_entries() {
+            match result? {
+                Ok(entry) => digests.push(entry.digest),
+                Err(ValidationError::Unexpected(_) | ValidationError::InvalidDigest { .. }) => {}
+            }
+        }
+
+        Ok(Self::new(digests))
+    }
+
+    pub fn read<R: Read>(reader: R) -> Result<Self, Error> {
+        let reader = BufReader::new(zstd::Decoder::new(reader)?);
+        let digests = reader
+            .lines()
+            .map(|line| Ok(line?.parse()?))
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        Ok(Self::new(digests))
+    }
+
+    pub fn write<W: Write>(&self, writer: W) -> Result<(), Error> {
+        let mut writer = zstd::Encoder::new(writer, COMPRESSION_LEVEL)?;
+
+        for digest in &self.digests {
+            writeln!(writer, "{}", digest)?;
+        }
+
+        writer.finish()?;
+
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.digests.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.digests.is_empty()
+    }
+
+    pub fn digests(&self) -> &[Sha1Digest] {
+        &self.digests
+    }
+
+    pub fn contains(&self, digest: &Sha1Digest) -> bool {
+        self.digests.binary_search(digest).is_ok()
+    }
+
+    /// Digests in this manifest that are not in the other one.
+    pub fn difference(&self, other: &Manifest) -> Vec<Sha1Digest> {
+        let mut result = vec![];
+        let mut others = other.digests.iter().peekable();
+
+        for digest in &self.digests {
+            while others.next_if(|other| *other < digest).is_some() {}
+
+            if others.peek() != Some(&digest) {
+                result.push(*digest);
+            }
+        }
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn digest(value: &str) -> Sha1Digest {
+        aib_core::digest::compute_digest(&mut value.as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn round_trip_and_difference() {
+        let a = Manifest::new(vec![digest("a"), digest("b"), digest("c"), digest("a")]);
+        let b = Manifest::new(vec![digest("b"), digest("d")]);
+
+        let mut buffer = vec![];
+        a.write(&mut buffer).unwrap();
+        let read = Manifest::read(buffer.as_slice()).unwrap();
+
+        let mut expected = vec![digest("a"), digest("c")];
+        expected.sort();
+
+        assert_eq!(read, a);
+        assert_eq!(read.len(), 3);
+        assert!(read.contains(&digest("b")));
+        assert!(!read.contains(&digest("d")));
+        assert_eq!(a.difference(&b), expected);
+        assert_eq!(b.difference(&a), vec![digest("d")]);
+    }
+}
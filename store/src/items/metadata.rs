@@ -0,0 +1,310 @@
+//! Per-item metadata, so that consumers can skip items without decompressing them.
+//!
+//! The table is an append-only CSV file with one line per item. Entries are
+//! never updated, so the first-seen timestamp is preserved when an item is
+//! saved again.
+
+use super::ItemStore;
+use aib_core::{charset, digest::Sha1Digest};
+use chrono::{DateTime, Utc};
+use encoding_rs::{Encoding, UTF_8};
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::Read;
+use std::path::Path;
+
+/// The number of leading bytes used for type and charset detection.
+pub const SNIFF_LEN: usize = 1024;
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("I/O error")]
+    Io(#[from] std::io::Error),
+    #[error("CSV error")]
+    Csv(#[from] csv::Error),
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct Metadata {
+    pub digest: Sha1Digest,
+    pub length: u64,
+    pub compressed_length: u64,
+    pub mime_type: String,
+    /// The canonical name of the detected encoding, for text items (e.g. `UTF-8`).
+    pub charset: Option<String>,
+    pub first_seen: DateTime<Utc>,
+}
+
+impl Metadata {
+    pub fn new(
+        digest: Sha1Digest,
+        head: &[u8],
+        length: u64,
+        compressed_length: u64,
+        first_seen: DateTime<Utc>,
+    ) -> Self {
+        let (mime_type, charset) = sniff(head);
+
+        Self {
+            digest,
+            length,
+            compressed_length,
+            mime_type: mime_type.to_string(),
+            charset: charset.map(str::to_string),
+            first_seen,
+        }
+    }
+
+    /// Compute metadata for an item that is already in the store.
+    ///
+    /// The file's modification time is used as the first-seen timestamp.
+    pub fn compute(store: &ItemStore, digest: Sha1Digest) -> Result<Option<Self>, Error> {
+        match store
+            .location(&digest.to_string())
+            .filter(|path| path.is_file())
+        {
+            Some(path) => {
+                let file_metadata = path.metadata()?;
+                let mut reader = HeadReader::new(zstd::Decoder::new(File::open(&path)?)?);
+                let length = std::io::copy(&mut reader, &mut std::io::sink())?;
+
+                Ok(Some(Self::new(
+                    digest,
+                    reader.head(),
+                    length,
+                    file_metadata.len(),
+                    file_metadata.modified()?.into(),
+                )))
+            }
+            None => Ok(None),
+        }
+    }
+
+    pub fn is_html(&self) -> bool {
+        self.mime_type == "text/html"
+    }
+}
+
+pub struct MetadataTable {
+    items: HashMap<Sha1Digest, Metadata>,
+    writer: csv::Writer<File>,
+}
+
+impl std::fmt::Debug for MetadataTable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MetadataTable")
+            .field("items", &self.items.len())
+            .finish()
+    }
+}
+
+impl MetadataTable {
+    /// Open a metadata file, creating it if it does not exist.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let mut items = HashMap::new();
+
+        if path.is_file() {
+            let mut reader = csv::ReaderBuilder::new()
+                .has_headers(false)
+                .from_path(path)?;
+
+            for metadata in reader.deserialize::<Metadata>() {
+                let metadata = metadata?;
+                items.entry(metadata.digest).or_insert(metadata);
+            }
+        }
+
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let writer = csv::WriterBuilder::new()
+            .has_headers(false)
+            .from_writer(file);
+
+        Ok(Self { items, writer })
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    pub fn contains(&self, digest: &Sha1Digest) -> bool {
+        self.items.contains_key(digest)
+    }
+
+    pub fn get(&self, digest: &Sha1Digest) -> Option<&Metadata> {
+        self.items.get(digest)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Metadata> {
+        self.items.values()
+    }
+
+    /// Add metadata for an item, returning `false` if the item was already present.
+    pub fn insert(&mut self, metadata: Metadata) -> Result<bool, Error> {
+        if self.items.contains_key(&metadata.digest) {
+            Ok(false)
+        } else {
+            self.writer.serialize(&metadata)?;
+            self.items.insert(metadata.digest, metadata);
+
+            Ok(true)
+        }
+    }
+
+    pub fn flush(&mut self) -> Result<(), Error> {
+        Ok(self.writer.flush()?)
+    }
+}
+
+/// A reader that keeps a copy of the first `SNIFF_LEN` bytes read.
+pub struct HeadReader<R> {
+    underlying: R,
+    head: Vec<u8>,
+}
+
+impl<R> HeadReader<R> {
+    pub fn new(underlying: R) -> Self {
+        Self {
+            underlying,
+            head: Vec::with_capacity(SNIFF_LEN),
+        }
+    }
+
+    pub fn head(&self) -> &[u8] {
+        &self.head
+    }
+}
+
+impl<R: Read> Read for HeadReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let count = self.underlying.read(buf)?;
+        let remaining = SNIFF_LEN - self.head.len();
+        self.head.extend_from_slice(&buf[..count.min(remaining)]);
+
+        Ok(count)
+    }
+}
+
+/// Guess the MIME type and charset of an item from its leading bytes.
+///
+/// The charset is only detected for text, using [`aib_core::charset`].
+pub fn sniff(head: &[u8]) -> (&'static str, Option<&'static str>) {
+    let body = match Encoding::for_bom(head) {
+        Some((encoding, _)) if encoding != UTF_8 => {
+            return ("text/plain", Some(encoding.name()));
+        }
+        Some((_, bom_length)) => &head[bom_length..],
+        None => head,
+    };
+
+    let binary_type = [
+        (&b"%PDF-"[..], "application/pdf"),
+        (b"\x89PNG\r\n\x1A\n", "image/png"),
+        (b"GIF87a", "image/gif"),
+        (b"GIF89a", "image/gif"),
+        (b"\xFF\xD8\xFF", "image/jpeg"),
+        (b"PK\x03\x04", "application/zip"),
+        (b"\x1F\x8B", "application/gzip"),
+    ]
+    .into_iter()
+    .find(|(magic, _)| body.starts_with(magic))
+    .map(|(_, mime_type)| mime_type);
+
+    if let Some(mime_type) = binary_type {
+        return (mime_type, None);
+    }
+
+    let trimmed = body
+        .iter()
+        .position(|byte| !byte.is_ascii_whitespace())
+        .map(|start| &body[start..])
+        .unwrap_or_default();
+    let lowercase = trimmed.to_ascii_lowercase();
+
+    let mime_type = if [
+        "<!doctype html",
+        "<html",
+        "<head",
+        "<body",
+        "<script",
+        "<title",
+    ]
+    .iter()
+    .any(|marker| lowercase.starts_with(marker.as_bytes()))
+    {
+        "text/html"
+    } else if lowercase.starts_with(b"<?xml") {
+        "application/xml"
+    } else if lowercase.starts_with(b"{") || lowercase.starts_with(b"[") {
+        "application/json"
+    } else if body
+        .iter()
+        .any(|byte| byte.is_ascii_control() && !byte.is_ascii_whitespace())
+    {
+        return ("application/octet-stream", None);
+    } else {
+        "text/plain"
+    };
+
+    (mime_type, Some(charset::detect_prefix(head, None).name()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sniff_types_and_charsets() {
+        assert_eq!(
+            sniff(b"  <!DOCTYPE html><html><head><meta charset=\"ISO-8859-1\">"),
+            ("text/html", Some("windows-1252"))
+        );
+        assert_eq!(
+            sniff(b"<?xml version=\"1.0\" encoding=\"UTF-8\"?><rss>"),
+            ("application/xml", Some("UTF-8"))
+        );
+        assert_eq!(
+            sniff("{\"a\": \"é\"}".as_bytes()),
+            ("application/json", Some("UTF-8"))
+        );
+        assert_eq!(sniff(b"%PDF-1.4\n"), ("application/pdf", None));
+        assert_eq!(
+            sniff(b"caf\xE9 plain"),
+            ("text/plain", Some("windows-1252"))
+        );
+        assert_eq!(sniff(b"\xFF\xFEa\x00"), ("text/plain", Some("UTF-16LE")));
+        assert_eq!(sniff(b"\x00\x01\x02"), ("application/octet-stream", None));
+    }
+
+    #[test]
+    fn save_and_reopen() {
+        let store_dir = tempdir::TempDir::new("metadata-store").unwrap();
+        let table_dir = tempdir::TempDir::new("metadata").unwrap();
+        let path = table_dir.path().join("metadata.csv");
+        let contents = "<html><title>Foo</title></html>";
+        let digest = aib_core::digest::compute_digest(&mut contents.as_bytes()).unwrap();
+
+        let store = ItemStore::new(store_dir.path(), None)
+            .with_metadata(MetadataTable::open(&path).unwrap());
+        store
+            .save(&digest.to_string(), &mut contents.as_bytes())
+            .unwrap();
+
+        let saved = store.metadata(&digest).unwrap();
+        assert_eq!(saved.length, contents.len() as u64);
+        assert!(saved.is_html());
+        drop(store);
+
+        let table = MetadataTable::open(&path).unwrap();
+        let store = ItemStore::new(store_dir.path(), None);
+        let computed = Metadata::compute(&store, digest).unwrap().unwrap();
+
+        assert_eq!(table.get(&digest), Some(&saved));
+        assert_eq!(computed.compressed_length, saved.compressed_length);
+        assert_eq!(computed.charset.as_deref(), Some("UTF-8"));
+    }
+}
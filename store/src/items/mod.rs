@@ -1,14 +1,21 @@
 use aib_core::digest::{compute_digest, Sha1Digest};
 use flate2::bufread::GzDecoder;
-use futures::{FutureExt, Stream, TryStreamExt};
+use futures::{FutureExt, Stream, StreamExt, TryStreamExt};
 use std::fs::File;
 use std::io::{BufReader, Read};
 use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
 use zstd::Decoder;
 
 const DEFAULT_COMPRESSION_LEVEL: i32 = 14;
 
+pub mod index;
 pub mod iter;
+pub mod ledger;
+pub mod manifest;
+pub mod metadata;
+pub mod sync;
+pub mod writer;
 
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct Entry {
@@ -43,6 +50,10 @@ pub enum Error {
     },
     #[error("Validation I/O error")]
     ValidationIo { entry: Entry, error: std::io::Error },
+    #[error("Index error")]
+    Index(#[from] index::Error),
+    #[error("Metadata error")]
+    Metadata(#[from] metadata::Error),
 }
 
 fn is_valid_char(c: char) -> bool {
@@ -93,11 +104,33 @@ fn validate(path: &Path) -> Result<Entry, ValidationError> {
     }
 }
 
+fn verify_entry(entry: Entry) -> Result<Result<Entry, ValidationError>, Error> {
+    let file_digest = File::open(&entry.path)
+        .and_then(Decoder::new)
+        .and_then(|mut reader| compute_digest(&mut reader))
+        .map_err(|error| Error::ValidationIo {
+            entry: entry.clone(),
+            error,
+        })?;
+
+    if file_digest == entry.digest {
+        Ok(Ok(entry))
+    } else {
+        Ok(Err(ValidationError::InvalidDigest {
+            entry,
+            digest: file_digest.to_string(),
+        }))
+    }
+}
+
 /// A content-addressable store for compressed Wayback Machine pages.
 #[derive(Clone, Debug)]
 pub struct ItemStore {
     base: PathBuf,
     compression_level: i32,
+    index: Option<Arc<index::DigestIndex>>,
+    metadata: Option<Arc<Mutex<metadata::MetadataTable>>>,
+    temp_dir: Option<PathBuf>,
 }
 
 impl ItemStore {
@@ -105,9 +138,91 @@ impl ItemStore {
         Self {
             base: path.as_ref().to_path_buf(),
             compression_level: compression_level.unwrap_or(DEFAULT_COMPRESSION_LEVEL),
+            index: None,
+            metadata: None,
+            temp_dir: None,
+        }
+    }
+
+    /// Write incomplete items to the given directory instead of the system
+    /// temporary directory.
+    ///
+    /// Using a directory on the same device as the store avoids copying when
+    /// items are committed.
+    pub fn with_temp_dir<P: AsRef<Path>>(self, path: P) -> Self {
+        Self {
+            temp_dir: Some(path.as_ref().to_path_buf()),
+            ..self
         }
     }
 
+    /// Use a digest index for membership checks, and keep it up to date as
+    /// items are saved and removed.
+    pub fn with_index(self, index: index::DigestIndex) -> Self {
+        Self {
+            index: Some(Arc::new(index)),
+            ..self
+        }
+    }
+
+    pub fn index(&self) -> Option<&index::DigestIndex> {
+        self.index.as_deref()
+    }
+
+    /// Record metadata for items as they are saved.
+    pub fn with_metadata(self, table: metadata::MetadataTable) -> Self {
+        Self {
+            metadata: Some(Arc::new(Mutex::new(table))),
+            ..self
+        }
+    }
+
+    /// Metadata for an item, if a metadata table is attached and has an entry.
+    pub fn metadata(&self, digest: &Sha1Digest) -> Option<metadata::Metadata> {
+        self.metadata
+            .as_ref()
+            .and_then(|table| table.lock().unwrap().get(digest).cloned())
+    }
+
+    fn record_metadata(
+        &self,
+        digest: &str,
+        head: &[u8],
+        length: u64,
+        path: &Path,
+    ) -> Result<(), Error> {
+        if let Some(table) = &self.metadata {
+            let metadata = metadata::Metadata::new(
+                digest.parse()?,
+                head,
+                length,
+                path.metadata()?.len(),
+                chrono::Utc::now(),
+            );
+            let mut table = table.lock().unwrap();
+
+            if table.insert(metadata)? {
+                table.flush()?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn update_index(&self, digest: &str, present: bool) -> Result<(), Error> {
+        if let Some(index) = &self.index {
+            let digest = digest.parse()?;
+
+            if present {
+                index.insert(digest)?;
+            } else {
+                index.remove(digest)?;
+            }
+        }
+
+        Ok(())
+    }
+
     fn is_valid_digest(candidate: &str) -> bool {
         candidate.len() == 32 && candidate.chars().all(is_valid_char)
     }
@@ -126,9 +241,16 @@ impl ItemStore {
     }
 
     pub fn contains(&self, digest: &str) -> bool {
-        self.location(digest)
-            .map(|path| path.is_file())
-            .unwrap_or(false)
+        match &self.index {
+            Some(index) => digest
+                .parse()
+                .map(|digest| index.contains(&digest))
+                .unwrap_or(false),
+            None => self
+                .location(digest)
+                .map(|path| path.is_file())
+                .unwrap_or(false),
+        }
     }
 
     pub fn save_all<'a, E: 'a, I: 'a + Iterator<Item = Result<(String, PathBuf), E>>>(
@@ -170,19 +292,126 @@ impl ItemStore {
             }
 
             let mut writer =
-                zstd::stream::write::Encoder::new(File::create(path)?, self.compression_level)?;
+                zstd::stream::write::Encoder::new(File::create(&path)?, self.compression_level)?;
+            let mut reader = metadata::HeadReader::new(reader);
 
-            let written = std::io::copy(reader, &mut writer).map_err(|error| Error::ImportIo {
-                digest: digest.to_string(),
-                error,
-            })?;
+            let written =
+                std::io::copy(&mut reader, &mut writer).map_err(|error| Error::ImportIo {
+                    digest: digest.to_string(),
+                    error,
+                })?;
 
             writer.finish()?;
+            self.record_metadata(digest, reader.head(), written, &path)?;
+            self.update_index(digest, true)?;
 
             Ok(Some(written))
         }
     }
 
+    /// Start writing an item whose digest will be known once it is complete.
+    pub fn writer(&self) -> std::io::Result<writer::ItemWriter> {
+        let directory = self.temp_dir.clone().unwrap_or_else(std::env::temp_dir);
+
+        writer::ItemWriter::create(&directory, self.compression_level)
+    }
+
+    /// Move a completed item into the store under the given digest.
+    ///
+    /// The digest is not verified. If the item is already present, the new
+    /// copy is discarded and `None` is returned.
+    pub fn commit(&self, digest: &str, writer: writer::ItemWriter) -> Result<Option<u64>, Error> {
+        let path = self
+            .location(digest)
+            .ok_or_else(|| Error::InvalidDigest(digest.to_string()))?;
+
+        if path.exists() {
+            Ok(None)
+        } else {
+            let (temporary_path, head, length) = writer.finish()?;
+
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+
+            if let Err(error) = writer::move_file(&temporary_path, &path) {
+                let _ = std::fs::remove_file(&temporary_path);
+
+                return Err(error.into());
+            }
+
+            self.record_metadata(digest, &head, length, &path)?;
+            self.update_index(digest, true)?;
+
+            Ok(Some(length))
+        }
+    }
+
+    /// The decompressed contents of an item, if it is present.
+    pub fn read(&self, digest: &str) -> Result<Option<Vec<u8>>, Error> {
+        match self.location(digest).filter(|path| path.is_file()) {
+            Some(path) => Ok(Some(zstd::decode_all(File::open(path)?)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// The compressed contents of an item, if it is present.
+    pub fn read_compressed(&self, digest: &str) -> Result<Option<Vec<u8>>, Error> {
+        match self.location(digest).filter(|path| path.is_file()) {
+            Some(path) => Ok(Some(std::fs::read(path)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Save an item that is already compressed.
+    ///
+    /// The item is written to a temporary file first, so that an interrupted
+    /// write never leaves a partial item in the store.
+    pub fn save_compressed(&self, digest: &str, bytes: &[u8]) -> Result<Option<u64>, Error> {
+        let path = self
+            .location(digest)
+            .ok_or_else(|| Error::InvalidDigest(digest.to_string()))?;
+
+        if path.exists() {
+            Ok(None)
+        } else {
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+
+            let temporary_path = path.with_extension("zst.tmp");
+            std::fs::write(&temporary_path, bytes)?;
+            std::fs::rename(temporary_path, &path)?;
+
+            if self.metadata.is_some() {
+                let mut reader = metadata::HeadReader::new(zstd::Decoder::new(bytes)?);
+                let length = std::io::copy(&mut reader, &mut std::io::sink())?;
+                self.record_metadata(digest, reader.head(), length, &path)?;
+            }
+
+            self.update_index(digest, true)?;
+
+            Ok(Some(bytes.len() as u64))
+        }
+    }
+
+    /// Delete an item from the store, returning its compressed size if it was present.
+    pub fn remove(&self, digest: &str) -> Result<Option<u64>, Error> {
+        let path = self
+            .location(digest)
+            .ok_or_else(|| Error::InvalidDigest(digest.to_string()))?;
+
+        if path.is_file() {
+            let size = path.metadata()?.len();
+            std::fs::remove_file(path)?;
+            self.update_index(digest, false)?;
+
+            Ok(Some(size))
+        } else {
+            Ok(None)
+        }
+    }
+
     pub fn directories(&self) -> iter::DirectoryIter {
         iter::DirectoryIter::new(&self.base)
     }
@@ -191,33 +420,23 @@ impl ItemStore {
         iter::FileIter::new(self.directories())
     }
 
+    /// List the entries in the store without reading their contents.
+    pub fn unchecked_entries(
+        &self,
+    ) -> impl Iterator<Item = Result<Result<Entry, ValidationError>, std::io::Error>> {
+        self.files().map(|path| path.map(|path| validate(&path)))
+    }
+
     pub fn entries(
         &self,
         parallelism: usize,
     ) -> impl Stream<Item = Result<Result<Entry, ValidationError>, Error>> {
-        futures::stream::iter(self.files())
+        futures::stream::iter(self.unchecked_entries())
             .map_err(Error::from)
-            .map_ok(|path| {
+            .map_ok(|result| {
                 tokio::spawn(async move {
-                    match validate(&path) {
-                        Ok(entry) => {
-                            let file_digest = File::open(&path)
-                                .and_then(Decoder::new)
-                                .and_then(|mut reader| compute_digest(&mut reader))
-                                .map_err(|error| Error::ValidationIo {
-                                    entry: entry.clone(),
-                                    error,
-                                })?;
-
-                            if file_digest == entry.digest {
-                                Ok(Ok(entry))
-                            } else {
-                                Ok(Err(ValidationError::InvalidDigest {
-                                    entry,
-                                    digest: file_digest.to_string(),
-                                }))
-                            }
-                        }
+                    match result {
+                        Ok(entry) => verify_entry(entry),
                         Err(error) => Ok(Err(error)),
                     }
                 })
@@ -230,6 +449,49 @@ impl ItemStore {
             .try_buffer_unordered(parallelism)
     }
 
+    /// Check the contents of the given entries against their digests.
+    pub fn verify<I: Iterator<Item = Entry>>(
+        &self,
+        entries: I,
+        parallelism: usize,
+    ) -> impl Stream<Item = Result<Result<Entry, ValidationError>, Error>> {
+        futures::stream::iter(entries)
+            .map(|entry| {
+                tokio::spawn(async move { verify_entry(entry) }).map(|result| match result {
+                    Ok(Ok(value)) => Ok(value),
+                    Ok(Err(error)) => Err(error),
+                    Err(error) => Err(error.into()),
+                })
+            })
+            .buffer_unordered(parallelism)
+    }
+
+    /// Move an item out of the store into a quarantine directory.
+    ///
+    /// The item keeps its path relative to the store base, so it can be
+    /// inspected or restored later. It is removed from the digest index.
+    pub fn quarantine<P: AsRef<Path>>(&self, entry: &Entry, target: P) -> Result<PathBuf, Error> {
+        let relative = entry
+            .path
+            .strip_prefix(&self.base)
+            .map_err(|_| Error::Unexpected(entry.path.clone()))?;
+        let destination = target.as_ref().join(relative);
+
+        if let Some(parent) = destination.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        if std::fs::rename(&entry.path, &destination).is_err() {
+            // The quarantine directory may be on a different file system.
+            std::fs::copy(&entry.path, &destination)?;
+            std::fs::remove_file(&entry.path)?;
+        }
+
+        self.update_index(&entry.digest.to_string(), false)?;
+
+        Ok(destination)
+    }
+
     /*pub fn validate(&self) -> Result<(usize, Vec<String>), Error> {
         for ()
     }
@@ -0,0 +1,216 @@
+//! Digest-aware replication between item stores.
+//!
+//! The source can be another local store or a remote store served over HTTP.
+//! The remote protocol is deliberately minimal, so that any static file server
+//! pointed at a store directory can act as a source: items are requested at
+//! their relative store paths (`<AB>/<CD>/<digest>.zst`) under a base URL, and
+//! the list of available digests is read from a manifest file.
+//!
+//! Items are only written to the target after their digests have been
+//! verified, and they are written atomically, so an interrupted sync can be
+//! resumed by running it again.
+
+use super::{manifest::Manifest, ItemStore};
+use aib_core::digest::{compute_digest, Sha1Digest};
+use futures::{Stream, StreamExt};
+use reqwest::{Client, StatusCode};
+use std::time::Duration;
+
+const TCP_KEEPALIVE_DURATION: Duration = Duration::from_secs(20);
+const DEFAULT_REQUEST_TIMEOUT_DURATION: Duration = Duration::from_secs(300);
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("I/O error")]
+    Io(#[from] std::io::Error),
+    #[error("HTTP client error")]
+    Client(#[from] reqwest::Error),
+    #[error("Invalid URL")]
+    Url(#[from] url::ParseError),
+    #[error("Unexpected status code: {0:?}")]
+    UnexpectedStatus(StatusCode),
+    #[error("Item store error")]
+    Store(#[from] super::Error),
+    #[error("Manifest error")]
+    Manifest(#[from] super::manifest::Error),
+    #[error("Task error")]
+    Task(#[from] tokio::task::JoinError),
+    #[error("Missing item: {0}")]
+    MissingItem(Sha1Digest),
+    #[error("Digest mismatch: expected {expected}, found {actual}")]
+    DigestMismatch {
+        expected: Sha1Digest,
+        actual: Sha1Digest,
+    },
+}
+
+#[derive(Clone, Debug)]
+pub struct Remote {
+    client: Client,
+    base: url::Url,
+    manifest: url::Url,
+}
+
+impl Remote {
+    pub fn new(base: &str, manifest: &str) -> Result<Self, Error> {
+        // Ensure that relative item paths are resolved under the base.
+        let base = if base.ends_with('/') {
+            base.parse()?
+        } else {
+            format!("{}/", base).parse()?
+        };
+
+        Ok(Self {
+            client: Client::builder()
+                .timeout(DEFAULT_REQUEST_TIMEOUT_DURATION)
+                .tcp_keepalive(Some(TCP_KEEPALIVE_DURATION))
+                .build()?,
+            base,
+            manifest: manifest.parse()?,
+        })
+    }
+
+    async fn get(&self, url: url::Url) -> Result<Option<bytes::Bytes>, Error> {
+        let response = self.client.get(url).send().await?;
+
+        match response.status() {
+            StatusCode::OK => Ok(Some(response.bytes().await?)),
+            StatusCode::NOT_FOUND => Ok(None),
+            other => Err(Error::UnexpectedStatus(other)),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum Source {
+    Local(ItemStore),
+    Remote(Remote),
+}
+
+impl Source {
+    pub async fn manifest(&self) -> Result<Manifest, Error> {
+        match self {
+            Self::Local(store) => Ok(Manifest::from_store(store)?),
+            Self::Remote(remote) => {
+                let bytes = remote
+                    .get(remote.manifest.clone())
+                    .await?
+                    .ok_or_else(|| Error::UnexpectedStatus(StatusCode::NOT_FOUND))?;
+
+                Ok(Manifest::read(bytes.as_ref())?)
+            }
+        }
+    }
+
+    /// The compressed contents of an item.
+    pub async fn get(&self, digest: Sha1Digest) -> Result<Option<Vec<u8>>, Error> {
+        match self {
+            Self::Local(store) => {
+                let store = store.clone();
+
+                Ok(
+                    tokio::spawn(async move { store.read_compressed(&digest.to_string()) })
+                        .await??,
+                )
+            }
+            Self::Remote(remote) => {
+                let url = remote.base.join(&relative_path(digest))?;
+
+                Ok(remote.get(url).await?.map(|bytes| bytes.to_vec()))
+            }
+        }
+    }
+}
+
+fn relative_path(digest: Sha1Digest) -> String {
+    let digest = digest.to_string();
+
+    format!("{}/{}/{}.zst", &digest[0..2], &digest[2..4], digest)
+}
+
+/// Copy the given items from the source into the target.
+///
+/// Each result contains the digest and the number of compressed bytes
+/// written, or `None` if the target already had the item.
+pub fn sync<'a>(
+    source: &'a Source,
+    target: &'a ItemStore,
+    digests: Vec<Sha1Digest>,
+    parallelism: usize,
+) -> impl Stream<Item = Result<(Sha1Digest, Option<u64>), Error>> + 'a {
+    futures::stream::iter(digests)
+        .map(move |digest| async move {
+            let compressed = source
+                .get(digest)
+                .await?
+                .ok_or(Error::MissingItem(digest))?;
+            let target = target.clone();
+
+            tokio::spawn(async move {
+                let actual = compute_digest(&mut zstd::Decoder::new(compressed.as_slice())?)?;
+
+                if actual == digest {
+                    let written = target.save_compressed(&digest.to_string(), &compressed)?;
+
+                    Ok((digest, written))
+                } else {
+                    Err(Error::DigestMismatch {
+                        expected: digest,
+                        actual,
+                    })
+                }
+            })
+            .await?
+        })
+        .buffer_unordered(parallelism)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::TryStreamExt;
+
+    #[tokio::test]
+    async fn sync_local() {
+        let source_dir = tempdir::TempDir::new("sync-source").unwrap();
+        let target_dir = tempdir::TempDir::new("sync-target").unwrap();
+        let source_store = ItemStore::new(source_dir.path(), None);
+        let target_store = ItemStore::new(target_dir.path(), None);
+
+        let contents = ["foo", "bar", "baz"];
+
+        for value in contents {
+            let digest = compute_digest(&mut value.as_bytes()).unwrap();
+            source_store
+                .save(&digest.to_string(), &mut value.as_bytes())
+                .unwrap();
+        }
+
+        let shared = compute_digest(&mut "bar".as_bytes()).unwrap();
+        target_store
+            .save(&shared.to_string(), &mut "bar".as_bytes())
+            .unwrap();
+
+        let source = Source::Local(source_store);
+        let missing = source
+            .manifest()
+            .await
+            .unwrap()
+            .difference(&Manifest::from_store(&target_store).unwrap());
+
+        assert_eq!(missing.len(), 2);
+
+        let mut copied = sync(&source, &target_store, missing, 2)
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+        copied.sort();
+
+        assert_eq!(copied.len(), 2);
+        assert!(copied.iter().all(|(_, written)| written.is_some()));
+        assert_eq!(
+            Manifest::from_store(&target_store).unwrap(),
+            source.manifest().await.unwrap()
+        );
+    }
+}
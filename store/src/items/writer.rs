@@ -0,0 +1,153 @@
+//! Writing items whose digest is not known until they are complete.
+//!
+//! An [`ItemWriter`] compresses its input into a temporary file, which
+//! [`ItemStore::commit`](super::ItemStore::commit) moves into place once the
+//! caller has computed the digest (for example with a
+//! [`DigestWriter`](aib_core::digest::DigestWriter) wrapped around it).
+
+use super::metadata::SNIFF_LEN;
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+pub struct ItemWriter {
+    path: PathBuf,
+    encoder: Option<zstd::stream::write::Encoder<'static, File>>,
+    head: Vec<u8>,
+    length: u64,
+}
+
+impl ItemWriter {
+    pub(super) fn create(directory: &Path, compression_level: i32) -> std::io::Result<Self> {
+        std::fs::create_dir_all(directory)?;
+
+        let path = directory.join(format!(
+            "aib-item-{}-{}.zst.tmp",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+
+        let encoder = zstd::stream::write::Encoder::new(File::create(&path)?, compression_level)?;
+
+        Ok(Self {
+            path,
+            encoder: Some(encoder),
+            head: Vec::with_capacity(SNIFF_LEN),
+            length: 0,
+        })
+    }
+
+    /// The number of uncompressed bytes written so far.
+    pub fn length(&self) -> u64 {
+        self.length
+    }
+
+    /// Finish compression and return the temporary file's path, the first
+    /// bytes written, and the uncompressed length.
+    ///
+    /// The temporary file is no longer removed when the writer is dropped.
+    pub(super) fn finish(mut self) -> std::io::Result<(PathBuf, Vec<u8>, u64)> {
+        if let Some(encoder) = self.encoder.take() {
+            encoder.finish()?.sync_all()?;
+        }
+
+        Ok((
+            std::mem::take(&mut self.path),
+            std::mem::take(&mut self.head),
+            self.length,
+        ))
+    }
+
+    fn encoder(&mut self) -> std::io::Result<&mut zstd::stream::write::Encoder<'static, File>> {
+        self.encoder
+            .as_mut()
+            .ok_or_else(|| std::io::Error::other("Item writer already finished"))
+    }
+}
+
+impl Write for ItemWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let count = self.encoder()?.write(buf)?;
+
+        let remaining = SNIFF_LEN - self.head.len();
+        self.head.extend_from_slice(&buf[..count.min(remaining)]);
+        self.length += count as u64;
+
+        Ok(count)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.encoder()?.flush()
+    }
+}
+
+impl Drop for ItemWriter {
+    fn drop(&mut self) {
+        if !self.path.as_os_str().is_empty() {
+            self.encoder.take();
+            let _ = std::fs::remove_file(&self.path);
+        }
+    }
+}
+
+impl std::fmt::Debug for ItemWriter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ItemWriter")
+            .field("path", &self.path)
+            .field("length", &self.length)
+            .finish()
+    }
+}
+
+/// Move a file, falling back to copying when the target is on another device.
+pub(super) fn move_file(source: &Path, target: &Path) -> std::io::Result<()> {
+    if std::fs::rename(source, target).is_err() {
+        let temporary_target = target.with_extension("zst.tmp");
+        std::fs::copy(source, &temporary_target)?;
+        std::fs::rename(&temporary_target, target)?;
+        std::fs::remove_file(source)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::ItemStore;
+    use aib_core::digest::{compute_digest, DigestWriter};
+    use std::io::Write;
+
+    #[test]
+    fn write_and_commit() {
+        let base = tempdir::TempDir::new("item-store").unwrap();
+        let temp = tempdir::TempDir::new("item-store-temp").unwrap();
+        let store = ItemStore::new(base.path(), None).with_temp_dir(temp.path());
+        let content = b"<html><body>Hello, world!</body></html>".repeat(100);
+        let digest = compute_digest(&mut content.as_slice()).unwrap();
+
+        let mut writer = DigestWriter::new(store.writer().unwrap());
+
+        for chunk in content.chunks(100) {
+            writer.write_all(chunk).unwrap();
+        }
+
+        let (writer, computed, length) = writer.finish().unwrap();
+
+        assert_eq!(computed, digest);
+        assert_eq!(length, content.len() as u64);
+        assert_eq!(
+            store.commit(&digest.to_string(), writer).unwrap(),
+            Some(content.len() as u64)
+        );
+        assert_eq!(store.read(&digest.to_string()).unwrap(), Some(content));
+
+        let mut writer = store.writer().unwrap();
+        writer.write_all(b"abandoned").unwrap();
+        drop(writer);
+
+        assert_eq!(std::fs::read_dir(temp.path()).unwrap().count(), 0);
+    }
+}